@@ -2,8 +2,8 @@ use std::{collections::btree_map::Entry, fmt::Debug};
 
 use cosmwasm_std::{
     Addr, Attribute, Binary, CheckedMultiplyRatioError, Coin, CosmosMsg, Event, IbcBasicResponse,
-    IbcOrder, IbcPacket, IbcPacketAckMsg, IbcReceiveResponse, IbcTimeout, Response, SubMsg,
-    Timestamp,
+    IbcOrder, IbcPacket, IbcPacketAckMsg, IbcReceiveResponse, IbcTimeout, IbcTimeoutBlock,
+    Response, SubMsg, Timestamp,
 };
 use thiserror::Error;
 use unionlabs::encoding::{self, Decode, DecodeErrorOf, Encode};
@@ -11,8 +11,8 @@ use unionlabs::encoding::{self, Decode, DecodeErrorOf, Encode};
 use crate::{
     middleware::{InFlightPfmPacket, Memo, PacketForward},
     types::{
-        EncodingError, GenericAck, NormalizedTransferToken, TransferPacket, TransferPacketCommon,
-        TransferToken,
+        EncodingError, GenericAck, NormalizedTransferToken, PacketFee, TransferPacket,
+        TransferPacketCommon, TransferToken,
     },
 };
 
@@ -25,6 +25,12 @@ pub const TRANSFER_EVENT: &str = "ibc_transfer";
 pub const TIMEOUT_EVENT: &str = "timeout";
 pub const MESSAGE_EVENT: &str = "message";
 
+// Emitted when escrowing relayer fees, as done by the ICS-29 fee middleware.
+pub const INCENTIVIZED_PACKET_EVENT: &str = "incentivized_ibc_packet";
+
+// Emitted when refunding a token failed, such that it can be recovered manually.
+pub const REFUND_FAILURE_EVENT: &str = "refund_failure";
+
 pub const ATTR_MODULE: &str = "module";
 pub const ATTR_SENDER: &str = "sender";
 pub const ATTR_RECEIVER: &str = "receiver";
@@ -36,6 +42,11 @@ pub const ATTR_ACK: &str = "acknowledgement";
 pub const ATTR_PFM: &str = "pfm";
 pub const ATTR_ASSETS: &str = "assets";
 pub const ATTR_FEE_ASSETS: &str = "fee_assets";
+pub const ATTR_RECV_FEE: &str = "recv_fee";
+pub const ATTR_ACK_FEE: &str = "ack_fee";
+pub const ATTR_TIMEOUT_FEE: &str = "timeout_fee";
+pub const ATTR_ASYNC_ACK: &str = "async_ack";
+pub const ATTR_ERROR_CODE: &str = "error_code";
 
 pub const ATTR_VALUE_PFM_ACK: &str = "pfm_ack";
 pub const ATTR_VALUE_TRUE: &str = "true";
@@ -44,8 +55,69 @@ pub const ATTR_VALUE_FALSE: &str = "false";
 /// NOTE: This number holds no significance, and was arbitrarily chosen.
 pub const IBC_SEND_ID: u64 = 69;
 
+/// Default maximum number of tokens transferred in a single packet, see
+/// [`TransferProtocol::MAX_TOKENS_PER_PACKET`].
+pub const MAX_TOKENS_PER_PACKET: usize = 32;
+
 const ACK_ERR_TIMEOUT_MSG: &[u8] = b"giving up on forwarded packet after timeout";
 
+/// Machine readable code of an error, emitted as [`ATTR_ERROR_CODE`] alongside the raw error such
+/// that indexers don't have to parse the error itself.
+pub trait ErrorCode {
+    fn error_code(&self) -> &'static str;
+}
+
+/// The error of a reverted submessage, only known as a string.
+impl ErrorCode for String {
+    fn error_code(&self) -> &'static str {
+        "submessage_failed"
+    }
+}
+
+/// Classification of a failure acknowledgement written by the counterparty, whose error is only
+/// known as raw bytes. Failures of this protocol are classified from the typed error instead, see
+/// [`ErrorCode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckErrorCode {
+    /// The acknowledgement doesn't carry any detail about the failure.
+    Unspecified,
+    /// The packet was forwarded and the forward packet timed out.
+    ForwardTimeout,
+    /// The acknowledgement carries an error message, see [`ATTR_ERROR`].
+    Message,
+}
+
+impl AckErrorCode {
+    pub fn from_error_ack(error: &[u8]) -> Self {
+        match error {
+            [] | [0] => Self::Unspecified,
+            ACK_ERR_TIMEOUT_MSG => Self::ForwardTimeout,
+            _ => Self::Message,
+        }
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "unspecified",
+            Self::ForwardTimeout => "forward_timeout",
+            Self::Message => "message",
+        }
+    }
+}
+
+impl ErrorCode for ProtocolError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            ProtocolError::NoSuchChannel { .. } => "no_such_channel",
+            ProtocolError::Unauthorized => "unauthorized",
+            ProtocolError::InvalidTimeout => "invalid_timeout",
+            ProtocolError::MissingTimeout => "missing_timeout",
+            ProtocolError::TooManyTokens { .. } => "too_many_tokens",
+            ProtocolError::ZeroAmount { .. } => "zero_amount",
+        }
+    }
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum ProtocolError {
     #[error("channel doesn't exist: {channel_id}")]
@@ -54,18 +126,70 @@ pub enum ProtocolError {
     Unauthorized,
     #[error("timeout must be greater than or equal to 1 second")]
     InvalidTimeout,
+    #[error("either a timeout timestamp or a timeout height must be set")]
+    MissingTimeout,
+    #[error("a packet can transfer at most {max} tokens, got {count}")]
+    TooManyTokens { count: usize, max: usize },
+    #[error("the amount of {denom} must not be zero")]
+    ZeroAmount { denom: String },
 }
 
 pub type PacketExtensionOf<T> = <<T as TransferProtocol>::Packet as TransferPacket>::Extension;
 
 pub struct TransferInput {
     pub current_time: Timestamp,
-    pub timeout_delta: u64,
+    /// Number of seconds after `current_time` at which the packet times out.
+    pub timeout_delta: Option<u64>,
+    /// Number of seconds the counterparty clock is ahead of `current_time` (negative if it is
+    /// behind), added to the timeout timestamp so that it is meaningful on the counterparty.
+    pub clock_skew: i64,
+    /// Counterparty height at which the packet times out.
+    pub timeout_height: Option<IbcTimeoutBlock>,
     pub sender: Addr,
     pub receiver: String,
     pub tokens: Vec<TransferToken>,
 }
 
+impl TransferInput {
+    /// Build the packet timeout, using the timestamp and/or the height if set.
+    /// At least one of them must be set.
+    pub fn timeout(&self) -> Result<IbcTimeout, ProtocolError> {
+        let timestamp = self.timeout_delta.map(|delta| {
+            let timestamp = self.current_time.plus_seconds(delta).nanos();
+            // Saturate instead of panicking on absurd skews, the packet then times out immediately
+            // or never, which is the best we can do.
+            let skew = self.clock_skew.unsigned_abs().saturating_mul(1_000_000_000);
+            Timestamp::from_nanos(if self.clock_skew >= 0 {
+                timestamp.saturating_add(skew)
+            } else {
+                timestamp.saturating_sub(skew)
+            })
+        });
+        match (self.timeout_height.clone(), timestamp) {
+            (Some(block), Some(timestamp)) => Ok(IbcTimeout::with_both(block, timestamp)),
+            (Some(block), None) => Ok(block.into()),
+            (None, Some(timestamp)) => Ok(timestamp.into()),
+            (None, None) => Err(ProtocolError::MissingTimeout),
+        }
+    }
+
+    /// Ensure that at most `max` tokens are transferred and that none of them has a zero amount.
+    pub fn validate_tokens(&self, max: usize) -> Result<(), ProtocolError> {
+        if self.tokens.len() > max {
+            return Err(ProtocolError::TooManyTokens {
+                count: self.tokens.len(),
+                max,
+            });
+        }
+        match self.tokens.iter().find(|token| token.amount.is_zero()) {
+            Some(token) => Err(ProtocolError::ZeroAmount {
+                denom: token.denom.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
 pub fn tokens_to_attr(
     tokens: impl IntoIterator<Item = TransferToken>,
 ) -> Result<Vec<Attribute>, CheckedMultiplyRatioError> {
@@ -119,7 +243,11 @@ pub trait TransferProtocol {
     /// Must be unique per Protocol
     const VERSION: &'static str;
     const ORDERING: IbcOrder;
+    /// Must be unique per Protocol and must not collide with [`IBC_SEND_ID`]
     const RECEIVE_REPLY_ID: u64;
+    /// Maximum number of tokens a single packet can transfer, such that the packet stays
+    /// relayable within the message size and gas limits of the counterparty
+    const MAX_TOKENS_PER_PACKET: usize = MAX_TOKENS_PER_PACKET;
 
     type Packet: Decode<Self::Encoding> + Encode<Self::Encoding> + TransferPacket;
 
@@ -130,6 +258,7 @@ pub trait TransferProtocol {
     type CustomMsg;
 
     type Error: Debug
+        + ErrorCode
         + From<ProtocolError>
         + From<EncodingError>
         + From<DecodeErrorOf<Self::Encoding, Self::Packet>>
@@ -185,28 +314,76 @@ pub trait TransferProtocol {
         tokens: Vec<TransferToken>,
     ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error>;
 
+    /// Refund `tokens` to `sender`. Each token should be refunded in its own submessage, such
+    /// that a failure to refund one token does not prevent the others from being refunded.
     fn send_tokens_failure(
         &mut self,
         sender: &AddrOf<Self::Packet>,
         receiver: &AddrOf<Self::Packet>,
         tokens: Vec<TransferToken>,
+    ) -> Result<Vec<SubMsg<Self::CustomMsg>>, Self::Error>;
+
+    /// Escrow the relayer fees of the packet with the given data and timeout, paid by `payer`.
+    /// The funds must already be held by the protocol. The packet is not sent yet, the fees must
+    /// be bound to its sequence once known from the [`IBC_SEND_ID`] reply.
+    fn escrow_packet_fee(
+        &mut self,
+        payer: &Addr,
+        data: &Binary,
+        timeout: &IbcTimeout,
+        fee: PacketFee,
+    ) -> Result<(), Self::Error>;
+
+    /// Release the fees escrowed for `packet`, if any. The [`caller`](Self::caller) is paid for
+    /// relaying the acknowledgement or the timeout, the unused fees are refunded to the payer.
+    fn release_packet_fee(
+        &mut self,
+        packet: &IbcPacket,
+        timed_out: bool,
     ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error>;
 
+    /// Record the packet with the given data and timeout, sent for `input`, as pending until it is
+    /// either acknowledged or timed out. As for [`escrow_packet_fee`](Self::escrow_packet_fee),
+    /// the record must be bound to the sequence of the packet once known.
+    fn record_pending_packet(
+        &mut self,
+        input: &TransferInput,
+        data: &Binary,
+        timeout: &IbcTimeout,
+    ) -> Result<(), Self::Error>;
+
+    /// Clear the pending record of `packet`, if any, once it has been acknowledged or timed out.
+    fn clear_pending_packet(&mut self, packet: &IbcPacket) -> Result<(), Self::Error>;
+
     fn send(
         &mut self,
-        mut input: TransferInput,
+        input: TransferInput,
+        extension: PacketExtensionOf<Self>,
+    ) -> Result<Response<Self::CustomMsg>, Self::Error> {
+        self.send_with_fee(input, extension, PacketFee::default())
+    }
+
+    /// Same as [`send`](Self::send), escrowing `fee` to incentivize relayers if it is not empty.
+    fn send_with_fee(
+        &mut self,
+        input: TransferInput,
         extension: PacketExtensionOf<Self>,
+        fee: PacketFee,
     ) -> Result<Response<Self::CustomMsg>, Self::Error> {
-        input.tokens = input
+        input.validate_tokens(Self::MAX_TOKENS_PER_PACKET)?;
+
+        // `input` keeps the local denoms, the packet carries the normalized ones
+        let tokens = input
             .tokens
-            .into_iter()
+            .iter()
+            .cloned()
             .map(|token| self.normalize_for_ibc_transfer(token))
             .collect::<Result<Vec<_>, _>>()?;
 
         let packet = self.common_to_protocol_packet(TransferPacketCommon {
             sender: input.sender.clone().to_string(),
             receiver: input.receiver.clone(),
-            tokens: input.tokens.clone(),
+            tokens,
             extension: extension.clone(),
         })?;
 
@@ -220,10 +397,34 @@ pub trait TransferProtocol {
         };
 
         let tokens = packet.tokens();
-        let send_packet_msg = self.send_packet(
-            packet.encode().into(),
-            input.current_time.plus_seconds(input.timeout_delta).into(),
-        )?;
+        let data = Binary::from(packet.encode());
+        let timeout = input.timeout()?;
+
+        let incentivized_event = if fee.is_empty() {
+            None
+        } else {
+            let event = Event::new(INCENTIVIZED_PACKET_EVENT).add_attributes([
+                (ATTR_SENDER, input.sender.to_string()),
+                (
+                    ATTR_RECV_FEE,
+                    cosmwasm_std::to_json_string(&fee.recv_fee).expect("impossible"),
+                ),
+                (
+                    ATTR_ACK_FEE,
+                    cosmwasm_std::to_json_string(&fee.ack_fee).expect("impossible"),
+                ),
+                (
+                    ATTR_TIMEOUT_FEE,
+                    cosmwasm_std::to_json_string(&fee.timeout_fee).expect("impossible"),
+                ),
+            ]);
+            self.escrow_packet_fee(&input.sender, &data, &timeout, fee)?;
+            Some(event)
+        };
+
+        self.record_pending_packet(&input, &data, &timeout)?;
+
+        let send_packet_msg = self.send_packet(data, timeout)?;
         let sub = SubMsg::reply_always(send_packet_msg, IBC_SEND_ID);
 
         Ok(Response::new()
@@ -237,7 +438,8 @@ pub trait TransferProtocol {
                     ])
                     .add_attributes(tokens_to_attr(tokens)?),
                 Event::new(MESSAGE_EVENT).add_attribute(ATTR_MODULE, TRANSFER_MODULE),
-            ]))
+            ])
+            .add_events(incentivized_event))
     }
 
     fn send_ack(
@@ -273,7 +475,10 @@ pub trait TransferProtocol {
                             packet.sender(),
                             packet.receiver(),
                             packet.tokens(),
-                        )?,
+                        )?
+                        .into_iter()
+                        .map(SubMsg::new)
+                        .collect(),
                         Vec::from_iter(
                             (!value_string.is_empty())
                                 .then_some(Attribute::new(ATTR_SUCCESS, value_string)),
@@ -281,6 +486,7 @@ pub trait TransferProtocol {
                     )
                 }
                 Err(error) => {
+                    let error_code = AckErrorCode::from_error_ack(&error);
                     let error_string = Binary::from(error).to_string();
                     (
                         self.send_tokens_failure(
@@ -288,15 +494,19 @@ pub trait TransferProtocol {
                             packet.receiver(),
                             packet.tokens(),
                         )?,
-                        Vec::from_iter(
-                            (!error_string.is_empty())
-                                .then_some(Attribute::new(ATTR_ERROR, error_string)),
-                        ),
+                        (!error_string.is_empty())
+                            .then_some(Attribute::new(ATTR_ERROR, error_string))
+                            .into_iter()
+                            .chain([Attribute::new(ATTR_ERROR_CODE, error_code.as_str())])
+                            .collect(),
                     )
                 }
             }
         };
 
+        let fee_msgs = self.release_packet_fee(&ibc_packet.original_packet, false)?;
+        self.clear_pending_packet(&ibc_packet.original_packet)?;
+
         let packet_event = {
             Event::new(PACKET_EVENT)
                 .add_attributes((!memo.is_empty()).then_some((ATTR_MEMO, &memo)))
@@ -317,7 +527,8 @@ pub trait TransferProtocol {
                     .add_attributes(tokens_to_attr(packet.tokens())?),
             )
             .add_event(Event::new(PACKET_EVENT).add_attributes(ack_attr))
-            .add_messages(ack_msgs))
+            .add_submessages(ack_msgs)
+            .add_messages(fee_msgs))
     }
 
     fn send_timeout(
@@ -341,6 +552,8 @@ pub trait TransferProtocol {
             } else {
                 self.send_tokens_failure(packet.sender(), packet.receiver(), packet.tokens())?
             };
+        let fee_msgs = self.release_packet_fee(&ibc_packet, true)?;
+        self.clear_pending_packet(&ibc_packet)?;
 
         let timeout_event = if memo.is_empty() {
             Event::new(PACKET_EVENT)
@@ -357,7 +570,8 @@ pub trait TransferProtocol {
                     ])
                     .add_attributes(tokens_to_attr(packet.tokens())?),
             )
-            .add_messages(refund_msgs))
+            .add_submessages(refund_msgs)
+            .add_messages(fee_msgs))
     }
 
     #[allow(clippy::type_complexity)]
@@ -374,6 +588,41 @@ pub trait TransferProtocol {
         Self::Error,
     >;
 
+    /// Whether the acknowledgement of `packet` is written asynchronously. If so,
+    /// [`receive`](Self::receive) doesn't set an acknowledgement and the protocol must later
+    /// write it with [`write_async_ack`](Self::write_async_ack). The acknowledgement of a forwarded
+    /// packet is always deferred until the forward packet is acknowledged or timed out.
+    fn defer_ack(&self, _packet: &Self::Packet) -> bool {
+        false
+    }
+
+    /// Record that the acknowledgement of `packet` is deferred, such that it is written at most
+    /// once by [`write_async_ack`](Self::write_async_ack).
+    fn record_deferred_ack(&mut self, packet: &IbcPacket) -> Result<(), Self::Error>;
+
+    /// Write the acknowledgement of a packet for which the acknowledgement was deferred, see
+    /// [`defer_ack`](Self::defer_ack). `ack` must be encoded for the protocol the packet was
+    /// received with. Fails if the acknowledgement isn't deferred anymore, either because it was
+    /// already written or because a submessage of the receive reverted, in which case the failure
+    /// acknowledgement was written by the [`RECEIVE_REPLY_ID`] reply.
+    ///
+    /// [`RECEIVE_REPLY_ID`]: Self::RECEIVE_REPLY_ID
+    fn write_async_ack(
+        &mut self,
+        packet: &IbcPacket,
+        ack: Binary,
+    ) -> Result<CosmosMsg<Self::CustomMsg>, Self::Error>;
+
+    /// Whether the submessages of a received transfer also reply with [`RECEIVE_REPLY_ID`] on
+    /// success, allowing the protocol to run follow-up actions in the reply handler. The
+    /// acknowledgement is still only overwritten if a submessage reverts. Defaults to replying on
+    /// error only.
+    ///
+    /// [`RECEIVE_REPLY_ID`]: Self::RECEIVE_REPLY_ID
+    fn reply_on_receive_success(&self, _packet: &Self::Packet) -> bool {
+        false
+    }
+
     fn receive(&mut self, original_packet: IbcPacket) -> IbcReceiveResponse<Self::CustomMsg> {
         let handle = || -> Result<IbcReceiveResponse<Self::CustomMsg>, Self::Error> {
             let packet = Self::Packet::decode(original_packet.data.as_slice())?;
@@ -383,12 +632,29 @@ pub trait TransferProtocol {
             if let Ok(memo) = serde_json_wasm::from_str::<Memo>(&memo) {
                 match memo {
                     Memo::Forward { forward } => {
-                        return self.packet_forward(packet, original_packet, forward)
+                        let response =
+                            self.packet_forward(packet, original_packet.clone(), forward)?;
+                        self.record_deferred_ack(&original_packet)?;
+                        return Ok(response);
                     }
                     Memo::None { .. } => {}
                 };
             }
 
+            let defer_ack = self.defer_ack(&packet);
+            let reply_on_success = self.reply_on_receive_success(&packet);
+
+            // The reply of a reverted submessage must know the packet to drop its deferred
+            // acknowledgement, as the failure acknowledgement is then written by the reply. The
+            // success reply acts on the received packet as well.
+            let payload = if defer_ack || reply_on_success {
+                serde_json_wasm::to_vec(&original_packet)
+                    .expect("can serialize")
+                    .into()
+            } else {
+                Binary::default()
+            };
+
             // NOTE: The default message ack is always successful and only
             // overwritten if the submessage execution revert via the reply
             // handler. The caller must ensure that the protocol is called in
@@ -398,7 +664,14 @@ pub trait TransferProtocol {
                 .receive_transfer(packet.receiver(), packet.tokens(), true)?
                 .1
                 .into_iter()
-                .map(|msg| SubMsg::reply_on_error(msg, Self::RECEIVE_REPLY_ID));
+                .map(|msg| {
+                    if reply_on_success {
+                        SubMsg::reply_always(msg, Self::RECEIVE_REPLY_ID)
+                    } else {
+                        SubMsg::reply_on_error(msg, Self::RECEIVE_REPLY_ID)
+                    }
+                    .with_payload(payload.clone())
+                });
 
             let packet_event = if memo.is_empty() {
                 Event::new(PACKET_EVENT)
@@ -406,14 +679,26 @@ pub trait TransferProtocol {
                 Event::new(PACKET_EVENT).add_attribute(ATTR_MEMO, &memo)
             };
 
-            Ok(IbcReceiveResponse::new(Self::ack_success().encode())
+            let (response, status) = if defer_ack {
+                // only recorded once the receive can't fail anymore, a failure is acknowledged
+                // right away
+                self.record_deferred_ack(&original_packet)?;
+                (IbcReceiveResponse::without_ack(), ATTR_ASYNC_ACK)
+            } else {
+                (
+                    IbcReceiveResponse::new(Self::ack_success().encode()),
+                    ATTR_SUCCESS,
+                )
+            };
+
+            Ok(response
                 .add_event(
                     packet_event
                         .add_attributes([
                             (ATTR_MODULE, TRANSFER_MODULE),
                             (ATTR_SENDER, packet.sender().to_string().as_str()),
                             (ATTR_RECEIVER, packet.receiver().to_string().as_str()),
-                            (ATTR_SUCCESS, ATTR_VALUE_TRUE),
+                            (status, ATTR_VALUE_TRUE),
                         ])
                         .add_attributes(tokens_to_attr(packet.tokens())?),
                 )
@@ -427,7 +712,8 @@ pub trait TransferProtocol {
         }
     }
 
-    fn receive_error(error: impl Debug) -> IbcReceiveResponse<Self::CustomMsg> {
+    fn receive_error(error: impl Debug + ErrorCode) -> IbcReceiveResponse<Self::CustomMsg> {
+        let error_code = error.error_code();
         let error = format!("{:?}", error);
 
         IbcReceiveResponse::new(Self::ack_failure(error.clone()).encode()).add_event(
@@ -435,6 +721,7 @@ pub trait TransferProtocol {
                 (ATTR_MODULE, TRANSFER_MODULE),
                 (ATTR_SUCCESS, ATTR_VALUE_FALSE),
                 (ATTR_ERROR, &error),
+                (ATTR_ERROR_CODE, error_code),
             ]),
         )
     }
@@ -515,7 +802,7 @@ pub trait TransferProtocol {
         refund_info: InFlightPfmPacket,
         sender: &AddrOf<Self::Packet>,
         tokens: Vec<TransferToken>,
-    ) -> Result<(Vec<CosmosMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error>;
+    ) -> Result<(Vec<SubMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error>;
 
     fn convert_ack_to_foreign_protocol(
         &self,
@@ -528,10 +815,13 @@ pub trait TransferProtocol {
 
 #[cfg(test)]
 mod tests {
-    use cosmwasm_std::{Coin, Uint128};
+    use cosmwasm_std::{Addr, Coin, IbcTimeoutBlock, Timestamp, Uint128};
 
     use crate::{
-        protocol::{tokens_to_attr, ATTR_ASSETS, ATTR_FEE_ASSETS},
+        protocol::{
+            tokens_to_attr, AckErrorCode, ProtocolError, TransferInput, ACK_ERR_TIMEOUT_MSG,
+            ATTR_ASSETS, ATTR_FEE_ASSETS,
+        },
         types::{FeePerU128, TransferToken},
     };
 
@@ -561,4 +851,105 @@ mod tests {
         assert_eq!(fee_amount[1].denom, "factory/1/3/3");
         assert_eq!(fee_amount[1].amount, Uint128::from(0_u64));
     }
+
+    #[test]
+    fn test_transfer_input_validate_tokens() {
+        let token = |denom: &str, amount: u64| TransferToken {
+            denom: denom.into(),
+            amount: amount.into(),
+            fee: FeePerU128::zero(),
+        };
+        let mut input = TransferInput {
+            current_time: Timestamp::from_seconds(100),
+            timeout_delta: Some(10),
+            clock_skew: 0,
+            timeout_height: None,
+            sender: Addr::unchecked("sender"),
+            receiver: "receiver".into(),
+            tokens: vec![token("a", 1), token("b", 2)],
+        };
+        assert_eq!(input.validate_tokens(2), Ok(()));
+        assert_eq!(
+            input.validate_tokens(1),
+            Err(ProtocolError::TooManyTokens { count: 2, max: 1 })
+        );
+
+        input.tokens.push(token("c", 0));
+        assert_eq!(
+            input.validate_tokens(3),
+            Err(ProtocolError::ZeroAmount { denom: "c".into() })
+        );
+    }
+
+    #[test]
+    fn test_ack_error_code() {
+        assert_eq!(
+            AckErrorCode::from_error_ack(&[0]),
+            AckErrorCode::Unspecified
+        );
+        assert_eq!(
+            AckErrorCode::from_error_ack(ACK_ERR_TIMEOUT_MSG),
+            AckErrorCode::ForwardTimeout
+        );
+        assert_eq!(
+            AckErrorCode::from_error_ack(br#"{"error":"insufficient funds"}"#),
+            AckErrorCode::Message
+        );
+    }
+
+    #[test]
+    fn test_transfer_input_timeout() {
+        let mut input = TransferInput {
+            current_time: Timestamp::from_seconds(100),
+            timeout_delta: None,
+            clock_skew: 0,
+            timeout_height: None,
+            sender: Addr::unchecked("sender"),
+            receiver: "receiver".into(),
+            tokens: vec![],
+        };
+        assert_eq!(input.timeout(), Err(ProtocolError::MissingTimeout));
+
+        input.timeout_delta = Some(10);
+        let timeout = input.timeout().unwrap();
+        assert_eq!(timeout.timestamp(), Some(Timestamp::from_seconds(110)));
+        assert_eq!(timeout.block(), None);
+
+        let block = IbcTimeoutBlock {
+            revision: 1,
+            height: 1000,
+        };
+        input.timeout_height = Some(block.clone());
+        let timeout = input.timeout().unwrap();
+        assert_eq!(timeout.timestamp(), Some(Timestamp::from_seconds(110)));
+        assert_eq!(timeout.block(), Some(block.clone()));
+
+        input.timeout_delta = None;
+        let timeout = input.timeout().unwrap();
+        assert_eq!(timeout.timestamp(), None);
+        assert_eq!(timeout.block(), Some(block));
+    }
+
+    #[test]
+    fn test_transfer_input_timeout_clock_skew() {
+        let mut input = TransferInput {
+            current_time: Timestamp::from_seconds(100),
+            timeout_delta: Some(10),
+            clock_skew: 5,
+            timeout_height: None,
+            sender: Addr::unchecked("sender"),
+            receiver: "receiver".into(),
+            tokens: vec![],
+        };
+        let timeout = input.timeout().unwrap();
+        assert_eq!(timeout.timestamp(), Some(Timestamp::from_seconds(115)));
+
+        input.clock_skew = -5;
+        let timeout = input.timeout().unwrap();
+        assert_eq!(timeout.timestamp(), Some(Timestamp::from_seconds(105)));
+
+        input.clock_skew = i64::MIN;
+        let timeout = input.timeout().unwrap();
+        assert_eq!(timeout.timestamp(), Some(Timestamp::from_nanos(0)));
+    }
 }
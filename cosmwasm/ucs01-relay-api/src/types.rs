@@ -48,8 +48,15 @@ impl Encode<JsonWasm> for Ics20Ack {
 impl Decode<JsonWasm> for Ics20Ack {
     type Error = serde_json_wasm::de::Error;
 
+    /// Decodes the standard json acknowledgement, as well as the raw single byte
+    /// acknowledgements used by some counterparties ([`ICS20_RAW_ACK_SUCCESS`] and
+    /// [`ICS20_RAW_ACK_FAILURE`]).
     fn decode(bytes: &[u8]) -> Result<Self, serde_json_wasm::de::Error> {
-        serde_json_wasm::from_slice(bytes)
+        match bytes {
+            [ICS20_RAW_ACK_SUCCESS] => Ok(Ics20Ack::Result(bytes.to_vec().into())),
+            [ICS20_RAW_ACK_FAILURE] => Ok(Ics20Ack::Error("raw failure acknowledgement".into())),
+            _ => serde_json_wasm::from_slice(bytes),
+        }
     }
 }
 
@@ -126,6 +133,31 @@ impl From<(Coin, FeePerU128)> for TransferToken {
 
 pub type Fees = BTreeMap<String, FeePerU128>;
 
+/// ICS-29 fees incentivizing relayers to relay a packet to completion.
+#[cw_serde]
+#[derive(Default)]
+pub struct PacketFee {
+    /// Paid to the relayer delivering the packet.
+    pub recv_fee: Vec<Coin>,
+    /// Paid to the relayer delivering the acknowledgement.
+    pub ack_fee: Vec<Coin>,
+    /// Paid to the relayer delivering the timeout.
+    pub timeout_fee: Vec<Coin>,
+}
+
+impl PacketFee {
+    /// All the coins of the fee, possibly with duplicated denoms.
+    pub fn coins(&self) -> impl Iterator<Item = &Coin> {
+        [&self.recv_fee, &self.ack_fee, &self.timeout_fee]
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coins().all(|coin| coin.amount.is_zero())
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Ucs01TransferPacket {
     /// the sender address
@@ -352,6 +384,14 @@ impl From<Ucs01Ack> for GenericAck {
     }
 }
 
+/// Raw success acknowledgement written by counterparties that don't use the json form of
+/// [`Ics20Ack`].
+pub const ICS20_RAW_ACK_SUCCESS: u8 = 1;
+
+/// Raw failure acknowledgement written by counterparties that don't use the json form of
+/// [`Ics20Ack`].
+pub const ICS20_RAW_ACK_FAILURE: u8 = 0;
+
 /// Standard ICS20 acknowledgement https://github.com/cosmos/cosmos-sdk/blob/v0.42.0/proto/ibc/core/channel/v1/channel.proto#L141-L147
 #[cw_serde]
 pub enum Ics20Ack {
@@ -439,7 +479,9 @@ mod tests {
     use unionlabs::encoding::{Decode, DecodeAs, Encode, EncodeAs};
 
     use super::{Ics20Packet, TransferToken, Ucs01Ack, Ucs01TransferPacket};
-    use crate::types::{DenomOrigin, FeePerU128, Ics20Ack, JsonWasm};
+    use crate::types::{
+        DenomOrigin, FeePerU128, Ics20Ack, JsonWasm, ICS20_RAW_ACK_FAILURE, ICS20_RAW_ACK_SUCCESS,
+    };
 
     #[test]
     fn ucs01_packet_encode_decode_iso() {
@@ -521,6 +563,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ics20_ack_decode_raw() {
+        assert_eq!(
+            Ics20Ack::Result(vec![ICS20_RAW_ACK_SUCCESS].into()),
+            Ics20Ack::decode_as::<JsonWasm>(&[ICS20_RAW_ACK_SUCCESS]).unwrap()
+        );
+        assert!(matches!(
+            Ics20Ack::decode_as::<JsonWasm>(&[ICS20_RAW_ACK_FAILURE]).unwrap(),
+            Ics20Ack::Error(_)
+        ));
+        assert!(Ics20Ack::decode_as::<JsonWasm>(&[2]).is_err());
+    }
+
     #[test]
     fn denom_origin_parse_local() {
         assert_eq!(
@@ -1,13 +1,17 @@
+use std::collections::BTreeMap;
+
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, Coins, Deps, DepsMut, Env, IbcChannel, IbcEndpoint, IbcQuery,
-    ListChannelsResponse, MessageInfo, Order, PortIdResponse, Response, StdError, StdResult,
+    to_json_binary, Addr, Binary, Coins, Deps, DepsMut, Empty, Env, IbcChannel, IbcEndpoint,
+    IbcMsg, IbcQuery, ListChannelsResponse, MessageInfo, Order, PortIdResponse, Response, StdError,
+    StdResult, Uint128, Uint512,
 };
 use cw2::set_contract_version;
+use cw_storage_plus::Bound;
 use token_factory_api::TokenFactoryMsg;
 use ucs01_relay_api::{
-    protocol::{TransferInput, TransferProtocol},
+    protocol::{ProtocolError, TransferInput, TransferProtocol},
     types::{make_factory_denom, FeePerU128, TransferToken},
 };
 use unionlabs::primitives::H256;
@@ -16,17 +20,29 @@ use crate::{
     error::ContractError,
     ibc::{enforce_order_and_version, execute_ibc_union},
     msg::{
-        ChannelBalances, ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, PortResponse,
-        QueryMsg, TransferMsg,
+        ChannelBalances, ChannelHistoryResponse, ConfigResponse, DenomTraceResponse, ExecuteMsg,
+        InstantiateMsg, MigrateMsg, PendingPacketsResponse, PortResponse, QueryMsg, RateLimit,
+        TotalEscrowedResponse, TransferMsg,
+    },
+    protocol::{
+        consume_rate_limit, decode_denom_hash, encode_denom_hash, packet_key, Flow, Ics20Protocol,
+        ProtocolCommon, Ucs01Protocol,
+    },
+    state::{
+        Config, RateLimitState, ADMIN, CHANNEL_HISTORY, CHANNEL_STATE, CLOCK_SKEWS,
+        CLOSED_CHANNELS, CONFIG, DEFERRED_ACKS, DENOM_FILTER, FOREIGN_DENOM_TO_HASH,
+        HASH_TO_FOREIGN_DENOM, IN_FLIGHT_PFM_PACKETS, MIN_TRANSFER_AMOUNTS, PAUSED_CHANNELS,
+        PENDING_PACKETS, RATE_LIMITS,
     },
-    protocol::{encode_denom_hash, Ics20Protocol, ProtocolCommon, Ucs01Protocol},
-    state::{Config, ADMIN, CHANNEL_STATE, CONFIG, FOREIGN_DENOM_TO_HASH, HASH_TO_FOREIGN_DENOM},
 };
 
 // REVIEW: This isn't on crates.io, what else should we use?
 const CONTRACT_NAME: &str = "crates.io:ucs01-relay";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const DEFAULT_HISTORY_LIMIT: u32 = 10;
+const MAX_HISTORY_LIMIT: u32 = 100;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     mut deps: DepsMut,
@@ -41,6 +57,9 @@ pub fn instantiate(
         &Config {
             default_timeout: msg.default_timeout,
             ibc_host,
+            max_timeout: msg.max_timeout,
+            paused: false,
+            reply_on_receive_success: msg.reply_on_receive_success,
         },
     )?;
 
@@ -71,6 +90,82 @@ pub fn execute(
             let admin = deps.api.addr_validate(&admin)?;
             Ok(ADMIN.execute_update_admin(deps, info, Some(admin))?)
         }
+        ExecuteMsg::CloseChannel { channel_id, force } => {
+            ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+            let ChannelBalances { balances, .. } =
+                query_channel(deps.as_ref(), channel_id.clone())?;
+            if !force && balances.iter().any(|(_, amount)| !amount.is_zero()) {
+                return Err(ContractError::ChannelHasOutstandingBalances { channel_id });
+            }
+            // Prevent any transfer while the channel is closing.
+            CLOSED_CHANNELS.save(deps.storage, &channel_id, &Empty {})?;
+            Ok(Response::default().add_message(IbcMsg::CloseChannel { channel_id }))
+        }
+        ExecuteMsg::SetDenomFilter { filter } => {
+            ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+            match filter {
+                Some(filter) => DENOM_FILTER.save(deps.storage, &filter)?,
+                None => DENOM_FILTER.remove(deps.storage),
+            }
+            Ok(Response::default())
+        }
+        ExecuteMsg::SetMinTransferAmount { denom, amount } => {
+            ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+            match amount {
+                Some(amount) => MIN_TRANSFER_AMOUNTS.save(deps.storage, &denom, &amount)?,
+                None => MIN_TRANSFER_AMOUNTS.remove(deps.storage, &denom),
+            }
+            Ok(Response::default())
+        }
+        ExecuteMsg::SetRateLimit {
+            channel_id,
+            denom,
+            limit,
+        } => {
+            ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+            match limit {
+                Some(RateLimit { window: 0, .. }) => return Err(ContractError::InvalidRateLimit),
+                Some(limit) => RATE_LIMITS.save(
+                    deps.storage,
+                    (&channel_id, &denom),
+                    &RateLimitState {
+                        limit,
+                        window_start: env.block.time,
+                        inflow: Uint128::zero(),
+                        outflow: Uint128::zero(),
+                        previous_inflow: Uint128::zero(),
+                        previous_outflow: Uint128::zero(),
+                    },
+                )?,
+                None => RATE_LIMITS.remove(deps.storage, (&channel_id, &denom)),
+            }
+            Ok(Response::default())
+        }
+        ExecuteMsg::SetClockSkew { channel_id, skew } => {
+            ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+            match skew {
+                Some(skew) => CLOCK_SKEWS.save(deps.storage, &channel_id, &skew)?,
+                None => CLOCK_SKEWS.remove(deps.storage, &channel_id),
+            }
+            Ok(Response::default())
+        }
+        ExecuteMsg::SetPaused { paused } => {
+            ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+            CONFIG.update(deps.storage, |config| -> StdResult<_> {
+                Ok(Config { paused, ..config })
+            })?;
+            Ok(Response::default().add_attribute("paused", paused.to_string()))
+        }
+        ExecuteMsg::SetChannelPaused { channel_id, paused } => {
+            ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+            if paused {
+                PAUSED_CHANNELS.save(deps.storage, &channel_id, &Empty {})?;
+            } else {
+                PAUSED_CHANNELS.remove(deps.storage, &channel_id);
+            }
+            Ok(Response::default()
+                .add_attributes([("channel_id", channel_id), ("paused", paused.to_string())]))
+        }
         ExecuteMsg::RegisterDenom {
             local_endpoint,
             denom,
@@ -100,14 +195,43 @@ pub fn execute(
 }
 
 pub fn execute_transfer(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: TransferMsg,
 ) -> Result<Response<TokenFactoryMsg>, ContractError> {
     let fees = msg.fees.unwrap_or_default();
-    let tokens: Vec<TransferToken> = Coins::try_from(info.funds.clone())
-        .map_err(|_| StdError::generic_err("Couldn't decode funds to Coins"))?
+    let packet_fee = msg.packet_fee.unwrap_or_default();
+    let mut funds = Coins::try_from(info.funds.clone())
+        .map_err(|_| StdError::generic_err("Couldn't decode funds to Coins"))?;
+    // The packet fee is taken out of the funds and stays escrowed in the contract
+    for coin in packet_fee.coins().filter(|coin| !coin.amount.is_zero()) {
+        funds
+            .sub(coin.clone())
+            .map_err(|_| ContractError::InsufficientPacketFeeFunds {
+                denom: coin.denom.clone(),
+            })?;
+    }
+    if let Some(filter) = DENOM_FILTER.may_load(deps.storage)? {
+        if let Some(denom) = funds.denoms().into_iter().find(|d| !filter.is_allowed(d)) {
+            return Err(ContractError::DenomNotAllowed {
+                denom: denom.to_string(),
+            });
+        }
+    }
+    for denom in funds.denoms() {
+        let amount = funds.amount_of(denom);
+        if let Some(min_amount) = MIN_TRANSFER_AMOUNTS.may_load(deps.storage, denom)? {
+            if amount < min_amount {
+                return Err(ContractError::BelowMinTransferAmount {
+                    denom: denom.to_string(),
+                    amount,
+                    min_amount,
+                });
+            }
+        }
+    }
+    let tokens: Vec<TransferToken> = funds
         .into_vec()
         .into_iter()
         .map(|coin| {
@@ -127,14 +251,65 @@ pub fn execute_transfer(
 
     let config = CONFIG.load(deps.storage)?;
 
+    if config.paused {
+        return Err(ContractError::Paused);
+    }
+
+    if PAUSED_CHANNELS.has(deps.storage, &msg.channel) {
+        return Err(ContractError::ChannelPaused {
+            channel_id: msg.channel,
+        });
+    }
+
+    if let Some(timeout) = msg.timeout {
+        if timeout == 0 {
+            return Err(ProtocolError::InvalidTimeout.into());
+        }
+        if let Some(max_timeout) = config.max_timeout {
+            if timeout > max_timeout {
+                return Err(ContractError::TimeoutTooLarge {
+                    timeout,
+                    max_timeout,
+                });
+            }
+        }
+    } else if let (Some(max_timeout), Some(_)) = (config.max_timeout, &msg.timeout_height) {
+        // a height only timeout can't be bounded, the packet could otherwise never time out
+        return Err(ContractError::TimeoutRequired { max_timeout });
+    }
+
+    for token in &tokens {
+        consume_rate_limit(
+            deps.branch(),
+            env.block.time,
+            &msg.channel,
+            &token.denom,
+            token.amount,
+            Flow::Outflow,
+        )?;
+    }
+
     let input = TransferInput {
         current_time: env.block.time,
-        timeout_delta: msg.timeout.unwrap_or(config.default_timeout),
+        timeout_delta: match (msg.timeout, &msg.timeout_height) {
+            (None, None) => Some(config.default_timeout),
+            (timeout, _) => timeout,
+        },
+        clock_skew: CLOCK_SKEWS
+            .may_load(deps.storage, &msg.channel)?
+            .unwrap_or_default(),
+        timeout_height: msg.timeout_height,
         sender: info.sender.clone(),
         receiver: msg.receiver,
         tokens,
     };
 
+    if CLOSED_CHANNELS.has(deps.storage, &msg.channel) {
+        return Err(ContractError::ChannelClosed {
+            channel_id: msg.channel,
+        });
+    }
+
     let channel = query_ibc_channel(deps.as_ref(), msg.channel.clone())?;
 
     match channel.version.as_ref() {
@@ -146,7 +321,7 @@ pub fn execute_transfer(
                 channel,
             },
         }
-        .send(input, msg.memo),
+        .send_with_fee(input, msg.memo, packet_fee),
         Ucs01Protocol::VERSION => Ucs01Protocol {
             common: ProtocolCommon {
                 deps,
@@ -155,7 +330,7 @@ pub fn execute_transfer(
                 channel,
             },
         }
-        .send(input, msg.memo),
+        .send_with_fee(input, msg.memo, packet_fee),
         v => Err(ContractError::UnknownProtocol {
             channel_id: msg.channel,
             protocol_version: v.into(),
@@ -164,7 +339,17 @@ pub fn execute_transfer(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_: DepsMut, _: Env, _: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, _: Env, _: MigrateMsg) -> Result<Response, ContractError> {
+    // The acknowledgement of the origin packet of an in flight PFM packet is deferred, packets
+    // forwarded before deferred acknowledgements were recorded must be backfilled for their
+    // acknowledgement to be written.
+    let origin_packet_keys = IN_FLIGHT_PFM_PACKETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|entry| entry.map(|(_, in_flight_packet)| packet_key(&in_flight_packet.origin_packet)))
+        .collect::<StdResult<Vec<_>>>()?;
+    for key in origin_packet_keys {
+        DEFERRED_ACKS.save(deps.storage, key, &Empty {})?;
+    }
     Ok(Response::new())
 }
 
@@ -175,6 +360,47 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
         QueryMsg::ListChannels {} => Ok(to_json_binary(&query_list(deps)?)?),
         QueryMsg::Channel { id } => Ok(to_json_binary(&query_channel(deps, id)?)?),
         QueryMsg::Config {} => Ok(to_json_binary(&query_config(deps)?)?),
+        QueryMsg::TotalEscrowed {} => Ok(to_json_binary(&query_total_escrowed(deps)?)?),
+        QueryMsg::EscrowedByDenom { denom } => Ok(to_json_binary(
+            &query_total_escrowed(deps)?
+                .balances
+                .into_iter()
+                .find_map(|(d, amount)| (d == denom).then_some(amount))
+                .unwrap_or_default(),
+        )?),
+        QueryMsg::DenomTrace { denom } => {
+            Ok(to_json_binary(&query_denom_trace(deps, &env, &denom)?)?)
+        }
+        QueryMsg::DenomFilter {} => Ok(to_json_binary(&DENOM_FILTER.may_load(deps.storage)?)?),
+        QueryMsg::MinTransferAmount { denom } => Ok(to_json_binary(
+            &MIN_TRANSFER_AMOUNTS.may_load(deps.storage, &denom)?,
+        )?),
+        QueryMsg::RateLimit { channel_id, denom } => Ok(to_json_binary(
+            &RATE_LIMITS.may_load(deps.storage, (&channel_id, &denom))?,
+        )?),
+        QueryMsg::ClockSkew { channel_id } => Ok(to_json_binary(
+            &CLOCK_SKEWS.may_load(deps.storage, &channel_id)?,
+        )?),
+        QueryMsg::ChannelHistory {
+            id,
+            limit,
+            start_after,
+        } => Ok(to_json_binary(&query_channel_history(
+            deps,
+            id,
+            limit,
+            start_after,
+        )?)?),
+        QueryMsg::PendingPackets {
+            channel_id,
+            limit,
+            start_after,
+        } => Ok(to_json_binary(&query_pending_packets(
+            deps,
+            channel_id,
+            limit,
+            start_after,
+        )?)?),
         QueryMsg::Admin {} => Ok(to_json_binary(&ADMIN.query_admin(deps)?)?),
         QueryMsg::ForeignDenomToLocal {
             source_channel,
@@ -202,6 +428,36 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
     }
 }
 
+pub fn query_denom_trace(
+    deps: Deps,
+    env: &Env,
+    denom: &str,
+) -> Result<Option<DenomTraceResponse>, ContractError> {
+    let Some(subdenom) = denom
+        .strip_prefix("factory/")
+        .and_then(|denom| denom.strip_prefix(env.contract.address.as_str()))
+        .and_then(|denom| denom.strip_prefix('/'))
+    else {
+        return Ok(None);
+    };
+    let denom_hash =
+        decode_denom_hash(subdenom).ok_or_else(|| ContractError::InvalidFactoryDenom {
+            denom: denom.to_string(),
+        })?;
+    let Some(foreign_denom) = HASH_TO_FOREIGN_DENOM.may_load(deps.storage, denom_hash)? else {
+        return Ok(None);
+    };
+    // foreign denoms are always prefixed with the local endpoint, see `make_foreign_denom`
+    let mut segments = foreign_denom.splitn(3, '/');
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some(port_id), Some(channel_id), Some(base_denom)) => Ok(Some(DenomTraceResponse {
+            path: format!("{port_id}/{channel_id}"),
+            base_denom: base_denom.to_string(),
+        })),
+        _ => Err(StdError::generic_err(format!("invalid foreign denom {foreign_denom}")).into()),
+    }
+}
+
 fn query_port(deps: Deps) -> Result<PortResponse, ContractError> {
     let query = IbcQuery::PortId {}.into();
     let PortIdResponse { port_id, .. } = deps.querier.query(&query)?;
@@ -241,12 +497,611 @@ pub fn query_channel(deps: Deps, id: String) -> Result<ChannelBalances, Contract
     Ok(ChannelBalances { channel, balances })
 }
 
+pub fn query_channel_history(
+    deps: Deps,
+    id: String,
+    limit: Option<u32>,
+    start_after: Option<u64>,
+) -> Result<ChannelHistoryResponse, ContractError> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let entries = CHANNEL_HISTORY
+        .prefix(&id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ChannelHistoryResponse { entries })
+}
+
+pub fn query_pending_packets(
+    deps: Deps,
+    channel_id: String,
+    limit: Option<u32>,
+    start_after: Option<Binary>,
+) -> Result<PendingPacketsResponse, ContractError> {
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT) as usize;
+    let packets = PENDING_PACKETS
+        .prefix(&channel_id)
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|r| r.map(|(key, packet)| (Binary::from(key), packet)))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PendingPacketsResponse { packets })
+}
+
+pub fn query_total_escrowed(deps: Deps) -> Result<TotalEscrowedResponse, ContractError> {
+    let mut balances = BTreeMap::<String, Uint512>::new();
+    for entry in CHANNEL_STATE.range(deps.storage, None, None, Order::Ascending) {
+        let ((_, denom), state) = entry?;
+        let balance = balances.entry(denom).or_default();
+        *balance = balance.checked_add(state.outstanding)?;
+    }
+    Ok(TotalEscrowedResponse {
+        balances: balances.into_iter().collect(),
+    })
+}
+
 fn query_config(deps: Deps) -> Result<ConfigResponse, ContractError> {
     let cfg = CONFIG.load(deps.storage)?;
     let admin = ADMIN.get(deps)?.unwrap_or_else(|| Addr::unchecked(""));
     let res = ConfigResponse {
         default_timeout: cfg.default_timeout,
+        max_timeout: cfg.max_timeout,
+        paused: cfg.paused,
+        reply_on_receive_success: cfg.reply_on_receive_success,
         gov_contract: admin.into(),
     };
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        coins,
+        testing::{message_info, mock_dependencies, mock_env},
+        Addr, CosmosMsg, DepsMut, IbcChannel, IbcEndpoint, IbcMsg, IbcOrder, IbcTimeoutBlock,
+        StdResult, Timestamp, Uint128, Uint512,
+    };
+    use ucs01_relay_api::protocol::ProtocolError;
+
+    use super::{execute, query_channel_history, query_denom_trace, query_total_escrowed};
+    use crate::{
+        error::ContractError,
+        msg::{DenomFilter, DenomTraceResponse, ExecuteMsg, RateLimit, TransferMsg},
+        protocol::{encode_denom_hash, hash_denom},
+        state::{
+            ChannelBalanceChange, ChannelState, Config, ADMIN, CHANNEL_HISTORY, CHANNEL_STATE,
+            CLOSED_CHANNELS, CONFIG, HASH_TO_FOREIGN_DENOM, RATE_LIMITS,
+        },
+    };
+
+    #[test]
+    fn total_escrowed_sums_channels() {
+        let mut deps = mock_dependencies();
+        for (channel_id, denom, outstanding) in [
+            ("channel-0", "muno", 10u128),
+            ("channel-1", "muno", 32),
+            ("channel-1", "uatom", 5),
+        ] {
+            CHANNEL_STATE
+                .save(
+                    deps.as_mut().storage,
+                    (channel_id, denom),
+                    &ChannelState {
+                        outstanding: outstanding.into(),
+                    },
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            query_total_escrowed(deps.as_ref()).unwrap().balances,
+            vec![
+                ("muno".to_string(), Uint512::from(42u128)),
+                ("uatom".to_string(), Uint512::from(5u128)),
+            ]
+        );
+    }
+
+    #[test]
+    fn channel_history_paginates() {
+        let mut deps = mock_dependencies();
+        for index in 0..5u64 {
+            CHANNEL_HISTORY
+                .save(
+                    deps.as_mut().storage,
+                    ("channel-0", index),
+                    &ChannelBalanceChange {
+                        denom: "muno".to_string(),
+                        previous: index.into(),
+                        outstanding: (index + 1).into(),
+                        height: index,
+                        time: Timestamp::from_seconds(index),
+                    },
+                )
+                .unwrap();
+        }
+        let indices = |limit, start_after| {
+            query_channel_history(deps.as_ref(), "channel-0".to_string(), limit, start_after)
+                .unwrap()
+                .entries
+                .into_iter()
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(indices(None, None), vec![0, 1, 2, 3, 4]);
+        assert_eq!(indices(Some(2), Some(1)), vec![2, 3]);
+        assert_eq!(indices(None, Some(4)), Vec::<u64>::new());
+    }
+
+    /// Set up the admin and the config of the contract, returning the admin.
+    fn setup(mut deps: DepsMut) -> Addr {
+        let admin = Addr::unchecked("admin");
+        ADMIN.set(deps.branch(), Some(admin.clone())).unwrap();
+        CONFIG
+            .save(
+                deps.storage,
+                &Config {
+                    default_timeout: 100,
+                    ibc_host: Addr::unchecked("ibc_host"),
+                    max_timeout: None,
+                    paused: false,
+                    reply_on_receive_success: false,
+                },
+            )
+            .unwrap();
+        admin
+    }
+
+    fn transfer_msg(channel: &str) -> ExecuteMsg {
+        ExecuteMsg::Transfer(TransferMsg {
+            channel: channel.into(),
+            receiver: "receiver".into(),
+            timeout: None,
+            timeout_height: None,
+            memo: String::new(),
+            fees: None,
+            packet_fee: None,
+        })
+    }
+
+    #[test]
+    fn paused_contract_rejects_transfers() {
+        let mut deps = mock_dependencies();
+        let admin = setup(deps.as_mut());
+
+        let sender = Addr::unchecked("sender");
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &[]),
+                ExecuteMsg::SetPaused { paused: true }
+            ),
+            Err(ContractError::Admin(_))
+        ));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            ExecuteMsg::SetPaused { paused: true },
+        )
+        .unwrap();
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &coins(100, "muno")),
+                transfer_msg("channel-0")
+            ),
+            Err(ContractError::Paused)
+        ));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            ExecuteMsg::SetPaused { paused: false },
+        )
+        .unwrap();
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &coins(100, "muno")),
+                transfer_msg("channel-0")
+            ),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+    }
+
+    #[test]
+    fn paused_channel_rejects_transfers() {
+        let mut deps = mock_dependencies();
+        let admin = setup(deps.as_mut());
+
+        let set_channel_paused = |paused| ExecuteMsg::SetChannelPaused {
+            channel_id: "channel-0".into(),
+            paused,
+        };
+        let sender = Addr::unchecked("sender");
+
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &[]),
+                set_channel_paused(true)
+            ),
+            Err(ContractError::Admin(_))
+        ));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            set_channel_paused(true),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &coins(100, "muno")),
+                transfer_msg("channel-0")
+            ),
+            Err(ContractError::ChannelPaused { channel_id }) if channel_id == "channel-0"
+        ));
+        // other channels are unaffected and fail later on, as the channel doesn't exist
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &coins(100, "muno")),
+                transfer_msg("channel-1")
+            ),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            set_channel_paused(false),
+        )
+        .unwrap();
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&sender, &coins(100, "muno")),
+                transfer_msg("channel-0")
+            ),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+    }
+
+    #[test]
+    fn transfer_timeout_bounded_by_max_timeout() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+        CONFIG
+            .update(deps.as_mut().storage, |config| -> StdResult<_> {
+                Ok(Config {
+                    max_timeout: Some(50),
+                    ..config
+                })
+            })
+            .unwrap();
+
+        let transfer_with_height = |deps: DepsMut, timeout, timeout_height| {
+            execute(
+                deps,
+                mock_env(),
+                message_info(&Addr::unchecked("sender"), &coins(100, "muno")),
+                ExecuteMsg::Transfer(TransferMsg {
+                    channel: "channel-0".into(),
+                    receiver: "receiver".into(),
+                    timeout,
+                    timeout_height,
+                    memo: String::new(),
+                    fees: None,
+                    packet_fee: None,
+                }),
+            )
+        };
+        let transfer = |deps: DepsMut, timeout| transfer_with_height(deps, Some(timeout), None);
+        assert!(matches!(
+            transfer(deps.as_mut(), 0),
+            Err(ContractError::Protocol(ProtocolError::InvalidTimeout))
+        ));
+        assert!(matches!(
+            transfer(deps.as_mut(), 51),
+            Err(ContractError::TimeoutTooLarge {
+                timeout: 51,
+                max_timeout: 50
+            })
+        ));
+        // the maximum is inclusive, the transfer then fails as the channel doesn't exist
+        assert!(matches!(
+            transfer(deps.as_mut(), 50),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+
+        // a timeout height alone can't be bounded
+        let timeout_height = IbcTimeoutBlock {
+            revision: 0,
+            height: 100,
+        };
+        assert!(matches!(
+            transfer_with_height(deps.as_mut(), None, Some(timeout_height.clone())),
+            Err(ContractError::TimeoutRequired { max_timeout: 50 })
+        ));
+        assert!(matches!(
+            transfer_with_height(deps.as_mut(), Some(50), Some(timeout_height)),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+    }
+
+    #[test]
+    fn denom_filter_restricts_transfers() {
+        let mut deps = mock_dependencies();
+        let admin = setup(deps.as_mut());
+
+        let set_denom_filter = |deps: DepsMut, filter| {
+            execute(
+                deps,
+                mock_env(),
+                message_info(&admin, &[]),
+                ExecuteMsg::SetDenomFilter { filter },
+            )
+            .unwrap();
+        };
+        let transfer = |deps: DepsMut, denom: &str| {
+            execute(
+                deps,
+                mock_env(),
+                message_info(&Addr::unchecked("sender"), &coins(100, denom)),
+                transfer_msg("channel-0"),
+            )
+        };
+
+        set_denom_filter(
+            deps.as_mut(),
+            Some(DenomFilter::AllowList {
+                denoms: vec!["muno".into()],
+            }),
+        );
+        assert!(matches!(
+            transfer(deps.as_mut(), "uatom"),
+            Err(ContractError::DenomNotAllowed { denom }) if denom == "uatom"
+        ));
+        // allowed denoms fail later on, as the channel doesn't exist
+        assert!(matches!(
+            transfer(deps.as_mut(), "muno"),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+
+        set_denom_filter(
+            deps.as_mut(),
+            Some(DenomFilter::DenyList {
+                denoms: vec!["muno".into()],
+            }),
+        );
+        assert!(matches!(
+            transfer(deps.as_mut(), "muno"),
+            Err(ContractError::DenomNotAllowed { denom }) if denom == "muno"
+        ));
+        assert!(matches!(
+            transfer(deps.as_mut(), "uatom"),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+
+        set_denom_filter(deps.as_mut(), None);
+        assert!(matches!(
+            transfer(deps.as_mut(), "muno"),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+    }
+
+    #[test]
+    fn close_channel_requires_settled_balances_unless_forced() {
+        let mut deps = mock_dependencies();
+        let admin = setup(deps.as_mut());
+        let endpoint = IbcEndpoint {
+            port_id: "wasm.contract".into(),
+            channel_id: "channel-0".into(),
+        };
+        deps.querier.update_ibc(
+            "wasm.contract",
+            &[IbcChannel::new(
+                endpoint.clone(),
+                endpoint,
+                IbcOrder::Unordered,
+                "ucs01-relay-1",
+                "connection-0",
+            )],
+        );
+        CHANNEL_STATE
+            .save(
+                deps.as_mut().storage,
+                ("channel-0", "muno"),
+                &ChannelState {
+                    outstanding: 10u128.into(),
+                },
+            )
+            .unwrap();
+
+        let close_channel = |force| ExecuteMsg::CloseChannel {
+            channel_id: "channel-0".into(),
+            force,
+        };
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&Addr::unchecked("sender"), &[]),
+                close_channel(true)
+            ),
+            Err(ContractError::Admin(_))
+        ));
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&admin, &[]),
+                close_channel(false)
+            ),
+            Err(ContractError::ChannelHasOutstandingBalances { channel_id })
+                if channel_id == "channel-0"
+        ));
+        assert!(!CLOSED_CHANNELS.has(deps.as_ref().storage, "channel-0"));
+
+        let response = execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            close_channel(true),
+        )
+        .unwrap();
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Ibc(IbcMsg::CloseChannel {
+                channel_id: "channel-0".into()
+            })
+        );
+        assert!(CLOSED_CHANNELS.has(deps.as_ref().storage, "channel-0"));
+
+        // no transfer is accepted on the closing channel
+        assert!(matches!(
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                message_info(&Addr::unchecked("sender"), &coins(100, "muno")),
+                transfer_msg("channel-0")
+            ),
+            Err(ContractError::ChannelClosed { channel_id }) if channel_id == "channel-0"
+        ));
+    }
+
+    #[test]
+    fn transfer_below_min_amount_rejected() {
+        let mut deps = mock_dependencies();
+        let admin = setup(deps.as_mut());
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            message_info(&admin, &[]),
+            ExecuteMsg::SetMinTransferAmount {
+                denom: "muno".into(),
+                amount: Some(Uint128::new(100)),
+            },
+        )
+        .unwrap();
+
+        let transfer = |deps: DepsMut, amount: u128| {
+            execute(
+                deps,
+                mock_env(),
+                message_info(&Addr::unchecked("sender"), &coins(amount, "muno")),
+                transfer_msg("channel-0"),
+            )
+        };
+        assert!(matches!(
+            transfer(deps.as_mut(), 99),
+            Err(ContractError::BelowMinTransferAmount { amount, min_amount, .. })
+                if amount == Uint128::new(99) && min_amount == Uint128::new(100)
+        ));
+        // the minimum is inclusive, the transfer then fails as the channel doesn't exist
+        assert!(matches!(
+            transfer(deps.as_mut(), 100),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+    }
+
+    #[test]
+    fn rate_limit_window_must_be_positive() {
+        let mut deps = mock_dependencies();
+        let admin = setup(deps.as_mut());
+        let set_rate_limit = |deps: DepsMut, window| {
+            execute(
+                deps,
+                mock_env(),
+                message_info(&admin, &[]),
+                ExecuteMsg::SetRateLimit {
+                    channel_id: "channel-0".into(),
+                    denom: "muno".into(),
+                    limit: Some(RateLimit {
+                        amount: Uint128::new(100),
+                        window,
+                    }),
+                },
+            )
+        };
+        assert!(matches!(
+            set_rate_limit(deps.as_mut(), 0),
+            Err(ContractError::InvalidRateLimit)
+        ));
+        assert!(!RATE_LIMITS.has(deps.as_ref().storage, ("channel-0", "muno")));
+        set_rate_limit(deps.as_mut(), 1).unwrap();
+        assert!(RATE_LIMITS.has(deps.as_ref().storage, ("channel-0", "muno")));
+    }
+
+    #[test]
+    fn denom_trace_of_voucher() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let foreign_denom = "wasm.contract/channel-0/transfer/channel-1/muno";
+        let hash = hash_denom(foreign_denom);
+        HASH_TO_FOREIGN_DENOM
+            .save(deps.as_mut().storage, hash, &foreign_denom.to_string())
+            .unwrap();
+
+        assert_eq!(
+            query_denom_trace(
+                deps.as_ref(),
+                &env,
+                &format!(
+                    "factory/{}/{}",
+                    env.contract.address,
+                    encode_denom_hash(hash)
+                )
+            )
+            .unwrap(),
+            Some(DenomTraceResponse {
+                path: "wasm.contract/channel-0".into(),
+                base_denom: "transfer/channel-1/muno".into(),
+            })
+        );
+        // unknown vouchers and denoms that aren't vouchers of this contract have no trace
+        assert_eq!(
+            query_denom_trace(
+                deps.as_ref(),
+                &env,
+                &format!(
+                    "factory/{}/{}",
+                    env.contract.address,
+                    encode_denom_hash(hash_denom("muno"))
+                )
+            )
+            .unwrap(),
+            None
+        );
+        assert_eq!(
+            query_denom_trace(deps.as_ref(), &env, "muno").unwrap(),
+            None
+        );
+    }
+}
@@ -1,9 +1,15 @@
 use std::string::FromUtf8Error;
 
-use cosmwasm_std::{CheckedMultiplyRatioError, IbcOrder, OverflowError, StdError, SubMsgResult};
+use cosmwasm_std::{
+    CheckedMultiplyRatioError, IbcOrder, OverflowError, StdError, SubMsgResult, Uint128, Uint512,
+};
 use cw_controllers::AdminError;
 use thiserror::Error;
-use ucs01_relay_api::{middleware::MiddlewareError, protocol::ProtocolError, types::EncodingError};
+use ucs01_relay_api::{
+    middleware::MiddlewareError,
+    protocol::{ErrorCode, ProtocolError},
+    types::EncodingError,
+};
 
 /// Never is a placeholder to ensure we don't return any errors
 #[derive(Error, Debug)]
@@ -35,6 +41,12 @@ pub enum ContractError {
         actual: IbcOrder,
     },
 
+    #[error("Channel {channel_id} is closed")]
+    ChannelClosed { channel_id: String },
+
+    #[error("Channel {channel_id} has outstanding balances, closing it requires force")]
+    ChannelHasOutstandingBalances { channel_id: String },
+
     #[error("Insufficient funds to redeem on channel")]
     InsufficientFunds,
 
@@ -83,6 +95,105 @@ pub enum ContractError {
 
     #[error("The reply was invalid")]
     InvalidReply,
+
+    #[error("Transfers of {denom} are not allowed")]
+    DenomNotAllowed { denom: String },
+
+    #[error("Transfer of {amount}{denom} is below the minimum of {min_amount}{denom}")]
+    BelowMinTransferAmount {
+        denom: String,
+        amount: Uint128,
+        min_amount: Uint128,
+    },
+
+    #[error("Transfers are paused")]
+    Paused,
+
+    #[error("Transfers on channel {channel_id} are paused")]
+    ChannelPaused { channel_id: String },
+
+    #[error("Rate limit exceeded for {denom} on channel {channel_id}")]
+    RateLimitExceeded { channel_id: String, denom: String },
+
+    #[error("The window of a rate limit must be at least one second")]
+    InvalidRateLimit,
+
+    #[error("Timeout of {timeout} seconds exceeds the maximum of {max_timeout} seconds")]
+    TimeoutTooLarge { timeout: u64, max_timeout: u64 },
+
+    #[error("A timeout of at most {max_timeout} seconds is required")]
+    TimeoutRequired { max_timeout: u64 },
+
+    #[error("Insufficient funds to pay the packet fee in {denom}")]
+    InsufficientPacketFeeFunds { denom: String },
+
+    #[error("A packet fee is already escrowed for this packet")]
+    PacketFeeAlreadyEscrowed,
+
+    #[error(
+        "Insufficient escrow of {denom} on channel {channel_id}: {outstanding} outstanding but \
+        {amount} to be released"
+    )]
+    InsufficientEscrow {
+        channel_id: String,
+        denom: String,
+        outstanding: Uint512,
+        amount: Uint128,
+    },
+
+    #[error("Invalid receiver address {receiver}: {error}")]
+    InvalidReceiver { receiver: String, error: StdError },
+
+    #[error("Invalid denom {denom}: the subdenom of a factory denom must be a denom hash")]
+    InvalidFactoryDenom { denom: String },
+
+    #[error("The acknowledgement of the packet is not deferred or was already written")]
+    NoDeferredAck,
+}
+
+impl ErrorCode for ContractError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            ContractError::Std(_) => "std",
+            ContractError::Admin(_) => "admin",
+            ContractError::NoSuchChannel { .. } => "no_such_channel",
+            ContractError::NoFunds => "no_funds",
+            ContractError::Overflow { .. } => "overflow",
+            ContractError::InvalidChannelOrdering { .. } => "invalid_channel_ordering",
+            ContractError::ChannelClosed { .. } => "channel_closed",
+            ContractError::ChannelHasOutstandingBalances { .. } => {
+                "channel_has_outstanding_balances"
+            }
+            ContractError::InsufficientFunds => "insufficient_funds",
+            ContractError::UnknownReply { .. } => "unknown_reply",
+            ContractError::PfmSendPacketError { .. } => "pfm_send_packet",
+            ContractError::Protocol(error) => error.error_code(),
+            ContractError::ProtocolEncoding(_) => "invalid_packet",
+            ContractError::UnknownProtocol { .. } => "unknown_protocol",
+            ContractError::ProtocolMismatch { .. } => "protocol_mismatch",
+            ContractError::Unauthorized => "unauthorized",
+            ContractError::Unsupported => "unsupported",
+            ContractError::MiddlewareError(_) => "packet_forward",
+            ContractError::SerdeJson(_) => "invalid_json",
+            ContractError::Arithmetic(_) => "arithmetic",
+            ContractError::OnlyIBCHost => "only_ibc_host",
+            ContractError::InvalidReply => "invalid_reply",
+            ContractError::DenomNotAllowed { .. } => "denom_not_allowed",
+            ContractError::BelowMinTransferAmount { .. } => "below_min_transfer_amount",
+            ContractError::Paused => "paused",
+            ContractError::ChannelPaused { .. } => "channel_paused",
+            ContractError::RateLimitExceeded { .. } => "rate_limit_exceeded",
+            ContractError::InvalidRateLimit => "invalid_rate_limit",
+            ContractError::TimeoutTooLarge { .. } => "timeout_too_large",
+            ContractError::TimeoutRequired { .. } => "timeout_required",
+            ContractError::InsufficientPacketFeeFunds { .. } => "insufficient_packet_fee_funds",
+            ContractError::PacketFeeAlreadyEscrowed => "packet_fee_already_escrowed",
+            ContractError::InsufficientEscrow { .. } => "insufficient_escrow",
+            ContractError::InvalidReceiver { .. } => "invalid_receiver",
+            ContractError::InvalidFactoryDenom { .. } => "invalid_factory_denom",
+            ContractError::NoDeferredAck => "no_deferred_ack",
+        }
+    }
 }
 
 impl From<FromUtf8Error> for ContractError {
@@ -1,11 +1,11 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_json, wasm_execute, Addr, DepsMut, Env, Ibc3ChannelOpenResponse, IbcAcknowledgement,
-    IbcBasicResponse, IbcChannel, IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg,
-    IbcEndpoint, IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
-    IbcReceiveResponse, IbcTimeout, IbcTimeoutBlock, MessageInfo, Reply, Response, SubMsgResult,
-    Timestamp,
+    from_json, to_json_string, wasm_execute, Addr, Binary, Deps, DepsMut, Env, Event,
+    Ibc3ChannelOpenResponse, IbcAcknowledgement, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
+    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcEndpoint, IbcOrder, IbcPacket, IbcPacketAckMsg,
+    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, IbcTimeout, IbcTimeoutBlock,
+    MessageInfo, Reply, Response, SubMsgResult, Timestamp,
 };
 use ibc_solidity::{Channel, Packet};
 use ibc_union_msg::{
@@ -16,18 +16,21 @@ use ibc_union_msg::{
 use prost::{Message, Name};
 use protos::cosmwasm::wasm::v1::MsgIbcSendResponse;
 use token_factory_api::TokenFactoryMsg;
-use ucs01_relay_api::{
-    middleware::InFlightPfmPacket,
-    protocol::{TransferProtocol, IBC_SEND_ID},
-};
+use ucs01_relay_api::{middleware::InFlightPfmPacket, protocol::TransferProtocol};
 
 pub type IbcResponse = IbcBasicResponse<TokenFactoryMsg>;
 
 use crate::{
-    contract::query_ibc_channel,
+    contract::{query_channel, query_ibc_channel},
     error::ContractError,
-    protocol::{packet_key, protocol_ordering, Ics20Protocol, ProtocolCommon, Ucs01Protocol},
-    state::{CONFIG, IN_FLIGHT_PFM_PACKETS},
+    msg::ChannelBalances,
+    protocol::{
+        commit_staged_send, packet_key, protocol_ordering, refund_failed, reply_id_handler,
+        Ics20Protocol, ProtocolCommon, Refund, ReplyHandler, Ucs01Protocol,
+    },
+    state::{
+        CLOSED_CHANNELS, CONFIG, DEFERRED_ACKS, IN_FLIGHT_PFM_PACKETS, PAUSED_CHANNELS, STAGED_SEND,
+    },
 };
 
 fn to_response<T>(
@@ -54,47 +57,70 @@ fn to_response<T>(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(
     deps: DepsMut,
-    _: Env,
+    env: Env,
     reply: Reply,
 ) -> Result<Response<TokenFactoryMsg>, ContractError> {
-    match (reply.id, reply.result) {
+    let Some(handler) = reply_id_handler(reply.id) else {
+        return Err(ContractError::UnknownReply {
+            id: reply.id,
+            variant: reply.result,
+        });
+    };
+    match (handler, reply.result) {
         // RECEIVE_REPLY_ID is associated with submessages emitted during handling of `ibc_packet_receive`
-        (Ics20Protocol::RECEIVE_REPLY_ID, SubMsgResult::Err(err)) => {
+        (ReplyHandler::Ics20Receive, SubMsgResult::Err(err)) => {
+            drop_deferred_ack(deps, &reply.payload)?;
             Ok(to_response(Ics20Protocol::receive_error(err)))
         }
-        (Ucs01Protocol::RECEIVE_REPLY_ID, SubMsgResult::Err(err)) => {
+        (ReplyHandler::Ucs01Receive, SubMsgResult::Err(err)) => {
+            drop_deferred_ack(deps, &reply.payload)?;
             Ok(to_response(Ucs01Protocol::receive_error(err)))
         }
+        // only emitted if the contract is configured with `reply_on_receive_success`, the
+        // acknowledgement was already written by the receive and must not be overwritten
+        (ReplyHandler::Ics20Receive | ReplyHandler::Ucs01Receive, SubMsgResult::Ok(_)) => {
+            let packet = from_json::<IbcPacket>(&reply.payload)?;
+            Ok(
+                Response::new().add_event(Event::new("receive_success").add_attributes([
+                    ("channel_id", packet.dest.channel_id),
+                    ("packet_sequence", packet.sequence.to_string()),
+                ])),
+            )
+        }
         // IBC_SEND_ID is associated with submessages emitted during handling of `send`, which is called via `execute_transfer`, which is used both in PFM and non-PFM contexts
-        (IBC_SEND_ID, SubMsgResult::Ok(value)) => {
-            // this means this is not pfm
-            if reply.payload.is_empty() {
-                return Ok(Response::new());
-            }
-
-            let mut in_flight_packet =
-                serde_json_wasm::from_slice::<InFlightPfmPacket>(reply.payload.as_slice())
-                    .expect("binary is type");
-
-            match value
+        (ReplyHandler::Send, SubMsgResult::Ok(value)) => {
+            let sequence = match value
                 .msg_responses
                 .iter()
                 .find(|msg_response| msg_response.type_url == MsgIbcSendResponse::type_url())
             {
                 Some(msg_response) => {
-                    let send_response = MsgIbcSendResponse::decode(msg_response.value.as_slice())
-                        .expect("is type url");
-                    in_flight_packet.forward_packet.sequence = send_response.sequence;
+                    MsgIbcSendResponse::decode(msg_response.value.as_slice())
+                        .expect("is type url")
+                        .sequence
                 }
-                None =>
-                {
+                // packets sent through the union IBC stack don't have a sequence
+                None => {
                     #[allow(deprecated)]
                     if from_json::<Packet>(value.data.unwrap_or_default()).is_err() {
                         return Err(ContractError::InvalidReply);
                     }
+                    0
                 }
             };
 
+            commit_staged_send(deps.storage, sequence)?;
+
+            // this means this is not pfm
+            if reply.payload.is_empty() {
+                return Ok(Response::new());
+            }
+
+            let mut in_flight_packet =
+                serde_json_wasm::from_slice::<InFlightPfmPacket>(reply.payload.as_slice())
+                    .expect("binary is type");
+            in_flight_packet.forward_packet.sequence = sequence;
+
             let refund_packet_key = packet_key(&in_flight_packet.forward_packet);
 
             IN_FLIGHT_PFM_PACKETS
@@ -103,7 +129,10 @@ pub fn reply(
 
             Ok(Response::new().add_event(in_flight_packet.create_hop_event()?))
         }
-        (IBC_SEND_ID, SubMsgResult::Err(err)) => {
+        (ReplyHandler::Send, SubMsgResult::Err(err)) => {
+            // the packet was not sent, drop its staged fee and pending record
+            STAGED_SEND.remove(deps.storage);
+
             // this means this is not pfm
             if reply.payload.is_empty() {
                 return Err(ContractError::PfmSendPacketError { err });
@@ -114,6 +143,9 @@ pub fn reply(
                 serde_json_wasm::from_slice::<InFlightPfmPacket>(reply.payload.as_slice())
                     .expect("binary is type");
 
+            // the failure acknowledgement of the origin packet is written right away
+            DEFERRED_ACKS.remove(deps.storage, packet_key(&in_flight_packet.origin_packet));
+
             match &*in_flight_packet.origin_protocol_version {
                 Ucs01Protocol::VERSION => Ok(to_response(Ucs01Protocol::receive_error(err))),
                 Ics20Protocol::VERSION => Ok(to_response(Ics20Protocol::receive_error(err))),
@@ -121,6 +153,11 @@ pub fn reply(
                 version => unreachable!("unknown protocol version: {version}"),
             }
         }
+        // REFUND_REPLY_ID is associated with the per-token refunds emitted when handling failure
+        // acknowledgements and timeouts, a failed refund must not revert the others
+        (ReplyHandler::Refund, SubMsgResult::Err(err)) => {
+            refund_failed(deps, &env.block, from_json::<Refund>(&reply.payload)?, err)
+        }
         (_, result) => Err(ContractError::UnknownReply {
             id: reply.id,
             variant: result,
@@ -128,6 +165,55 @@ pub fn reply(
     }
 }
 
+/// The failure acknowledgement of a reverted receive is written by its reply, the deferred
+/// acknowledgement of the packet, carried by the payload if any, must then not be written anymore.
+fn drop_deferred_ack(deps: DepsMut, payload: &Binary) -> Result<(), ContractError> {
+    if !payload.is_empty() {
+        DEFERRED_ACKS.remove(deps.storage, packet_key(&from_json::<IbcPacket>(payload)?));
+    }
+    Ok(())
+}
+
+/// Channels of the union IBC stack are always unordered.
+fn enforce_union_order<T: TransferProtocol>() -> Result<(), ContractError> {
+    if T::ORDERING != IbcOrder::Unordered {
+        return Err(ContractError::InvalidChannelOrdering {
+            expected: T::ORDERING,
+            actual: IbcOrder::Unordered,
+        });
+    }
+    Ok(())
+}
+
+/// Union packets carry no sequence, they can only be received on channels of unordered protocols.
+fn union_receive_order(channel_id: u32, version: &str) -> Result<IbcOrder, ContractError> {
+    match protocol_ordering(version) {
+        Some(IbcOrder::Unordered) => Ok(IbcOrder::Unordered),
+        Some(order) => Err(ContractError::InvalidChannelOrdering {
+            expected: IbcOrder::Unordered,
+            actual: order,
+        }),
+        None => Err(ContractError::UnknownProtocol {
+            channel_id: channel_id.to_string(),
+            protocol_version: version.to_string(),
+        }),
+    }
+}
+
+/// Incoming packets are rejected while the contract or the receiving channel is paused, such that
+/// they can be relayed once resumed, or timed out on the counterparty.
+fn ensure_not_paused(deps: Deps, channel_id: &str) -> Result<(), ContractError> {
+    if CONFIG.load(deps.storage)?.paused {
+        return Err(ContractError::Paused);
+    }
+    if PAUSED_CHANNELS.has(deps.storage, channel_id) {
+        return Err(ContractError::ChannelPaused {
+            channel_id: channel_id.to_owned(),
+        });
+    }
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 /// enforces ordering and versioning constraints
 pub fn ibc_channel_open(
@@ -183,12 +269,27 @@ pub(crate) fn enforce_order_and_version(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
+/// only the closing initiated by the admin is accepted, the outstanding balances are frozen
 pub fn ibc_channel_close(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
-    _channel: IbcChannelCloseMsg,
+    msg: IbcChannelCloseMsg,
 ) -> Result<IbcResponse, ContractError> {
-    Err(ContractError::Unauthorized)
+    match msg {
+        IbcChannelCloseMsg::CloseInit { channel }
+            if CLOSED_CHANNELS.has(deps.storage, &channel.endpoint.channel_id) =>
+        {
+            let ChannelBalances { balances, .. } =
+                query_channel(deps.as_ref(), channel.endpoint.channel_id.clone())?;
+            Ok(
+                IbcResponse::new().add_event(Event::new("channel_close").add_attributes([
+                    ("channel_id", channel.endpoint.channel_id),
+                    ("frozen_balances", to_json_string(&balances)?),
+                ])),
+            )
+        }
+        _ => Err(ContractError::Unauthorized),
+    }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -199,6 +300,8 @@ pub fn ibc_packet_receive(
     env: Env,
     msg: IbcPacketReceiveMsg,
 ) -> Result<IbcReceiveResponse<TokenFactoryMsg>, ContractError> {
+    ensure_not_paused(deps.as_ref(), &msg.packet.dest.channel_id)?;
+
     let channel = query_ibc_channel(deps.as_ref(), msg.packet.dest.channel_id.clone())?;
 
     let info = MessageInfo {
@@ -334,6 +437,7 @@ pub(crate) fn execute_ibc_union(
                     protocol_version: version.to_string(),
                 });
             }
+            enforce_union_order::<Ucs01Protocol>()?;
             Ok(Response::new())
         }
         IbcUnionMsg::OnChannelOpenTry {
@@ -348,6 +452,20 @@ pub(crate) fn execute_ibc_union(
                     protocol_version: version.to_string(),
                 });
             }
+            if counterparty_version != Ucs01Protocol::VERSION {
+                return Err(ContractError::UnknownProtocol {
+                    channel_id: channel_id.to_string(),
+                    protocol_version: counterparty_version.to_string(),
+                });
+            }
+            enforce_union_order::<Ucs01Protocol>()?;
+            Ok(Response::new())
+        }
+        IbcUnionMsg::OnChannelOpenAck {
+            channel_id,
+            counterparty_version,
+            ..
+        } => {
             if counterparty_version != Ucs01Protocol::VERSION {
                 return Err(ContractError::UnknownProtocol {
                     channel_id: channel_id.to_string(),
@@ -356,7 +474,6 @@ pub(crate) fn execute_ibc_union(
             }
             Ok(Response::new())
         }
-        IbcUnionMsg::OnChannelOpenAck { .. } => Ok(Response::new()),
         IbcUnionMsg::OnChannelOpenConfirm { .. } => Ok(Response::new()),
         IbcUnionMsg::OnChannelCloseInit { .. } => Err(ContractError::Unauthorized),
         IbcUnionMsg::OnChannelCloseConfirm { .. } => Err(ContractError::Unauthorized),
@@ -364,6 +481,8 @@ pub(crate) fn execute_ibc_union(
         IbcUnionMsg::OnRecvPacket {
             packet, relayer, ..
         } => {
+            ensure_not_paused(deps.as_ref(), &packet.destination_channel.to_string())?;
+
             let channel = deps.querier.query_wasm_smart::<Channel>(
                 &ibc_host,
                 &IbcUnionQuery::GetChannel {
@@ -371,6 +490,8 @@ pub(crate) fn execute_ibc_union(
                 },
             )?;
 
+            let order = union_receive_order(packet.destination_channel, &channel.version)?;
+
             let info = MessageInfo {
                 sender: Addr::unchecked(relayer),
                 funds: Default::default(),
@@ -389,7 +510,7 @@ pub(crate) fn execute_ibc_union(
             let ibc_channel = IbcChannel::new(
                 ibc_endpoint_dst.clone(),
                 ibc_endpoint_src.clone(),
-                cosmwasm_std::IbcOrder::Ordered,
+                order,
                 channel.version.clone(),
                 channel.connection_id.to_string(),
             );
@@ -618,3 +739,164 @@ pub(crate) fn execute_ibc_union(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{mock_dependencies, mock_env},
+        Addr, DepsMut, Empty, IbcChannel, IbcChannelCloseMsg, IbcChannelOpenMsg, IbcEndpoint,
+        IbcOrder, IbcPacket, IbcPacketReceiveMsg, IbcTimeout, Timestamp,
+    };
+    use ucs01_relay_api::protocol::TransferProtocol;
+
+    use super::{ibc_channel_close, ibc_channel_open, ibc_packet_receive, union_receive_order};
+    use crate::{
+        error::ContractError,
+        protocol::{Ics20Protocol, Ucs01Protocol},
+        state::{Config, CLOSED_CHANNELS, CONFIG, PAUSED_CHANNELS},
+    };
+
+    fn receive_msg(channel_id: &str, sequence: u64) -> IbcPacketReceiveMsg {
+        let endpoint = IbcEndpoint {
+            port_id: "wasm.contract".into(),
+            channel_id: channel_id.into(),
+        };
+        IbcPacketReceiveMsg::new(
+            IbcPacket::new(
+                b"packet".to_vec(),
+                endpoint.clone(),
+                endpoint,
+                sequence,
+                IbcTimeout::with_timestamp(Timestamp::from_seconds(1)),
+            ),
+            Addr::unchecked("relayer"),
+        )
+    }
+
+    fn mock_channel(channel_id: &str, order: IbcOrder) -> IbcChannel {
+        let endpoint = IbcEndpoint {
+            port_id: "wasm.contract".into(),
+            channel_id: channel_id.into(),
+        };
+        IbcChannel::new(
+            endpoint.clone(),
+            endpoint,
+            order,
+            Ics20Protocol::VERSION,
+            "connection-0",
+        )
+    }
+
+    fn save_config(deps: DepsMut, paused: bool) {
+        CONFIG
+            .save(
+                deps.storage,
+                &Config {
+                    default_timeout: 100,
+                    ibc_host: Addr::unchecked("ibc_host"),
+                    max_timeout: None,
+                    paused,
+                    reply_on_receive_success: false,
+                },
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn paused_contract_rejects_packets() {
+        let mut deps = mock_dependencies();
+
+        save_config(deps.as_mut(), true);
+        assert!(matches!(
+            ibc_packet_receive(deps.as_mut(), mock_env(), receive_msg("channel-0", 1)),
+            Err(ContractError::Paused)
+        ));
+
+        save_config(deps.as_mut(), false);
+        PAUSED_CHANNELS
+            .save(deps.as_mut().storage, "channel-0", &Empty {})
+            .unwrap();
+        assert!(matches!(
+            ibc_packet_receive(deps.as_mut(), mock_env(), receive_msg("channel-0", 1)),
+            Err(ContractError::ChannelPaused { channel_id }) if channel_id == "channel-0"
+        ));
+        // other channels are unaffected and fail later on, as the channel doesn't exist
+        assert!(matches!(
+            ibc_packet_receive(deps.as_mut(), mock_env(), receive_msg("channel-1", 1)),
+            Err(ContractError::NoSuchChannel { .. })
+        ));
+    }
+
+    #[test]
+    fn channel_open_enforces_protocol_ordering() {
+        assert!(matches!(
+            ibc_channel_open(
+                mock_dependencies().as_mut(),
+                mock_env(),
+                IbcChannelOpenMsg::new_init(mock_channel("channel-0", IbcOrder::Ordered)),
+            ),
+            Err(ContractError::InvalidChannelOrdering {
+                expected: IbcOrder::Unordered,
+                actual: IbcOrder::Ordered,
+            })
+        ));
+        assert!(ibc_channel_open(
+            mock_dependencies().as_mut(),
+            mock_env(),
+            IbcChannelOpenMsg::new_init(mock_channel("channel-0", IbcOrder::Unordered)),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn union_receive_requires_unordered_protocol() {
+        assert_eq!(
+            union_receive_order(1, Ics20Protocol::VERSION).unwrap(),
+            IbcOrder::Unordered
+        );
+        assert_eq!(
+            union_receive_order(1, Ucs01Protocol::VERSION).unwrap(),
+            IbcOrder::Unordered
+        );
+        assert!(matches!(
+            union_receive_order(1, "ucs00-pingpong-1"),
+            Err(ContractError::UnknownProtocol { channel_id, .. }) if channel_id == "1"
+        ));
+    }
+
+    #[test]
+    fn channel_close_only_initiated_by_admin() {
+        let mut deps = mock_dependencies();
+        let channel = mock_channel("channel-0", IbcOrder::Unordered);
+        deps.querier.update_ibc("wasm.contract", &[channel.clone()]);
+
+        assert!(matches!(
+            ibc_channel_close(
+                deps.as_mut(),
+                mock_env(),
+                IbcChannelCloseMsg::new_init(channel.clone())
+            ),
+            Err(ContractError::Unauthorized)
+        ));
+
+        CLOSED_CHANNELS
+            .save(deps.as_mut().storage, "channel-0", &Empty {})
+            .unwrap();
+        let response = ibc_channel_close(
+            deps.as_mut(),
+            mock_env(),
+            IbcChannelCloseMsg::new_init(channel.clone()),
+        )
+        .unwrap();
+        assert_eq!(response.events[0].ty, "channel_close");
+        // closing initiated by the counterparty is never accepted
+        assert!(matches!(
+            ibc_channel_close(
+                deps.as_mut(),
+                mock_env(),
+                IbcChannelCloseMsg::new_confirm(channel)
+            ),
+            Err(ContractError::Unauthorized)
+        ));
+    }
+}
@@ -1,8 +1,10 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Binary, CosmosMsg, IbcChannel, IbcEndpoint, Uint512};
+use cosmwasm_std::{Binary, CosmosMsg, IbcChannel, IbcEndpoint, IbcTimeoutBlock, Uint128, Uint512};
 use ibc_union_msg::module::IbcUnionMsg;
 use token_factory_api::TokenFactoryMsg;
-use ucs01_relay_api::types::Fees;
+use ucs01_relay_api::types::{Fees, PacketFee};
+
+use crate::state::{ChannelBalanceChange, PendingPacket};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -14,6 +16,13 @@ pub struct InstantiateMsg {
     pub channel: Option<IbcChannel>,
     // the union ibc stack host
     pub ibc_host: String,
+    /// Maximum timeout for packets, specified in seconds, transfers must then specify a timeout
+    /// when specifying a timeout height. If not set, any timeout is accepted
+    #[serde(default)]
+    pub max_timeout: Option<u64>,
+    /// Whether received transfers reply on success, emitting a `receive_success` event
+    #[serde(default)]
+    pub reply_on_receive_success: bool,
 }
 
 #[cw_serde]
@@ -33,6 +42,46 @@ pub enum ExecuteMsg {
     UpdateAdmin {
         admin: String,
     },
+    /// Close a channel (must be called by current admin). Channels with outstanding balances
+    /// can only be closed if `force` is set, the balances are then frozen.
+    CloseChannel {
+        channel_id: String,
+        #[serde(default)]
+        force: bool,
+    },
+    /// Pause or resume all transfers (must be called by current admin)
+    SetPaused {
+        paused: bool,
+    },
+    /// Pause or resume transfers on a single channel (must be called by current admin). Transfers
+    /// are blocked if either the channel or the whole contract is paused.
+    SetChannelPaused {
+        channel_id: String,
+        paused: bool,
+    },
+    /// Set or remove the denom filter applied to transfers (must be called by current admin)
+    SetDenomFilter {
+        filter: Option<DenomFilter>,
+    },
+    /// Set or remove the minimum amount of a local denom that can be transferred (must be called
+    /// by current admin)
+    SetMinTransferAmount {
+        denom: String,
+        amount: Option<Uint128>,
+    },
+    /// Set or remove the rate limit of a denom on a channel (must be called by current admin)
+    SetRateLimit {
+        channel_id: String,
+        /// The local denom
+        denom: String,
+        limit: Option<RateLimit>,
+    },
+    /// Set or remove the known clock skew of the counterparty of a channel, in seconds. A positive
+    /// skew means the counterparty clock is ahead of ours (must be called by current admin)
+    SetClockSkew {
+        channel_id: String,
+        skew: Option<i64>,
+    },
     BatchExecute {
         msgs: Vec<CosmosMsg<TokenFactoryMsg>>,
     },
@@ -46,12 +95,47 @@ pub struct TransferMsg {
     pub channel: String,
     /// The remote address to send to.
     pub receiver: String,
-    /// How long the packet lives in seconds. If not specified, use default_timeout
+    /// How long the packet lives in seconds, bounded by max_timeout. If neither this nor
+    /// `timeout_height` is specified, use default_timeout
     pub timeout: Option<u64>,
-    /// The memo
+    /// The counterparty height at which the packet times out
+    #[serde(default)]
+    pub timeout_height: Option<IbcTimeoutBlock>,
+    /// The memo, carried as the packet extension by both the ics20 and ucs01 protocols. This can
+    /// be used to forward the transfer to another chain. Defaults to an empty memo.
+    #[serde(default)]
     pub memo: String,
     /// Fee associated with the transfer, denominated in transferred coins
     pub fees: Option<Fees>,
+    /// Relayer fees escrowed alongside the transfer, paid from the sent funds
+    #[serde(default)]
+    pub packet_fee: Option<PacketFee>,
+}
+
+/// Restricts the denoms that can be transferred.
+#[cw_serde]
+pub enum DenomFilter {
+    /// Only the listed denoms can be transferred
+    AllowList { denoms: Vec<String> },
+    /// All denoms but the listed ones can be transferred
+    DenyList { denoms: Vec<String> },
+}
+
+impl DenomFilter {
+    pub fn is_allowed(&self, denom: &str) -> bool {
+        match self {
+            DenomFilter::AllowList { denoms } => denoms.iter().any(|d| d == denom),
+            DenomFilter::DenyList { denoms } => denoms.iter().all(|d| d != denom),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct RateLimit {
+    /// Maximum amount that can be transferred in each direction during any window
+    pub amount: Uint128,
+    /// Duration of the rolling window, in seconds
+    pub window: u64,
 }
 
 #[cw_serde]
@@ -71,6 +155,47 @@ pub enum QueryMsg {
         source_channel: String,
         denom: String,
     },
+    /// Returns the outstanding balance of each denom, summed over all channels.
+    TotalEscrowed {},
+    /// Returns the outstanding balance of a denom, summed over all channels.
+    EscrowedByDenom {
+        denom: String,
+    },
+    /// Returns the trace of a voucher minted by this contract, if any.
+    DenomTrace {
+        /// The factory denom of the voucher
+        denom: String,
+    },
+    /// Returns the denom filter, if any.
+    DenomFilter {},
+    /// Returns the minimum amount of a local denom that can be transferred, if any.
+    MinTransferAmount {
+        denom: String,
+    },
+    /// Returns the rate limit of a local denom on a channel, along with its current usage.
+    RateLimit {
+        channel_id: String,
+        denom: String,
+    },
+    /// Returns the clock skew of the counterparty of a channel, if any.
+    ClockSkew {
+        channel_id: String,
+    },
+    /// Returns the log of changes to the balances of a channel, oldest first.
+    ChannelHistory {
+        id: String,
+        limit: Option<u32>,
+        /// Only return entries with an index greater than this.
+        start_after: Option<u64>,
+    },
+    /// Returns the packets sent on a channel that were neither acknowledged nor timed out yet,
+    /// ordered by packet key.
+    PendingPackets {
+        channel_id: String,
+        limit: Option<u32>,
+        /// Only return packets with a key greater than this.
+        start_after: Option<Binary>,
+    },
 }
 
 #[cw_serde]
@@ -81,6 +206,33 @@ pub struct ChannelBalances {
     pub balances: Vec<(String, Uint512)>,
 }
 
+#[cw_serde]
+pub struct ChannelHistoryResponse {
+    /// The balance changes of the channel along with their index in the log
+    pub entries: Vec<(u64, ChannelBalanceChange)>,
+}
+
+#[cw_serde]
+pub struct PendingPacketsResponse {
+    /// The pending packets of the channel along with their key
+    pub packets: Vec<(Binary, PendingPacket)>,
+}
+
+#[cw_serde]
+pub struct TotalEscrowedResponse {
+    /// How many tokens we currently have pending over all channels, by denom
+    pub balances: Vec<(String, Uint512)>,
+}
+
+#[cw_serde]
+pub struct DenomTraceResponse {
+    /// The `port/channel` the voucher was received on
+    pub path: String,
+    /// The denom on the counterparty, which is itself a trace if the token didn't originate
+    /// from the counterparty
+    pub base_denom: String,
+}
+
 #[cw_serde]
 pub struct PortResponse {
     pub port_id: String,
@@ -89,5 +241,8 @@ pub struct PortResponse {
 #[cw_serde]
 pub struct ConfigResponse {
     pub default_timeout: u64,
+    pub max_timeout: Option<u64>,
+    pub paused: bool,
+    pub reply_on_receive_success: bool,
     pub gov_contract: String,
 }
@@ -1,9 +1,10 @@
 use base58::{FromBase58, ToBase58};
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    from_json, wasm_execute, Addr, Attribute, BankMsg, Binary, Coin, CosmosMsg, DepsMut, Env,
-    HexBinary, IbcAcknowledgement, IbcChannel, IbcEndpoint, IbcMsg, IbcOrder, IbcPacket,
-    IbcReceiveResponse, IbcTimeout, IbcTimeoutBlock, MessageInfo, StdError, Timestamp, Uint128,
-    Uint512, WasmMsg,
+    from_json, to_json_binary, wasm_execute, Addr, Attribute, BankMsg, Binary, BlockInfo, Coin,
+    Coins, CosmosMsg, DepsMut, Empty, Env, Event, HexBinary, IbcAcknowledgement, IbcChannel,
+    IbcEndpoint, IbcMsg, IbcOrder, IbcPacket, IbcReceiveResponse, IbcTimeout, IbcTimeoutBlock,
+    MessageInfo, Response, StdError, Storage, SubMsg, Timestamp, Uint128, Uint512, WasmMsg,
 };
 use ibc_solidity::{Channel, Packet};
 use ibc_union_msg::{
@@ -15,24 +16,30 @@ use token_factory_api::TokenFactoryMsg;
 use ucs01_relay_api::{
     middleware::{InFlightPfmPacket, Memo, MiddlewareError, PacketForward, PacketForwardError},
     protocol::{
-        AddrOf, ProtocolSwitch, TransferProtocol, ATTR_ERROR, ATTR_PFM, ATTR_SUCCESS,
-        ATTR_VALUE_PFM_ACK, IBC_SEND_ID,
+        AckErrorCode, AddrOf, ProtocolSwitch, TransferInput, TransferProtocol, ATTR_ERROR,
+        ATTR_ERROR_CODE, ATTR_PFM, ATTR_RECEIVER, ATTR_SUCCESS, ATTR_VALUE_PFM_ACK, IBC_SEND_ID,
+        REFUND_FAILURE_EVENT,
     },
     types::{
         make_factory_denom, make_foreign_denom, DenomOrigin, EncodingError, GenericAck, Ics20Ack,
-        Ics20Packet, JsonWasm, NormalizedTransferToken, TransferToken, Ucs01Ack,
-        Ucs01TransferPacket,
+        Ics20Packet, JsonWasm, NormalizedTransferToken, PacketFee, TransferPacket, TransferToken,
+        Ucs01Ack, Ucs01TransferPacket,
     },
 };
-use unionlabs::{encoding, ethereum::keccak256};
+use unionlabs::{
+    encoding::{self, Decode},
+    ethereum::keccak256,
+};
 
 use crate::{
     contract::{execute_transfer, query_ibc_channel},
     error::ContractError,
     msg::{ExecuteMsg, TransferMsg},
     state::{
-        DenomHash, PfmRefundPacketKey, CHANNEL_STATE, CONFIG, FOREIGN_DENOM_TO_HASH,
-        HASH_TO_FOREIGN_DENOM, IN_FLIGHT_PFM_PACKETS, MAX_SUBDENOM_LENGTH,
+        ChannelBalanceChange, DenomHash, EscrowedPacketFee, PacketFeeKey, PendingPacket,
+        PfmRefundPacketKey, StagedSend, CHANNEL_HISTORY, CHANNEL_STATE, CONFIG, DEFERRED_ACKS,
+        FOREIGN_DENOM_TO_HASH, HASH_TO_FOREIGN_DENOM, IN_FLIGHT_PFM_PACKETS, MAX_SUBDENOM_LENGTH,
+        NEXT_CHANNEL_HISTORY_INDEX, PACKET_FEES, PENDING_PACKETS, RATE_LIMITS, STAGED_SEND,
     },
 };
 
@@ -63,6 +70,141 @@ pub fn packet_key(packet: &IbcPacket) -> PfmRefundPacketKey {
     .into()
 }
 
+/// Key of the fees escrowed for a packet sent on `channel_id`. Only the timeout height is used
+/// as the revision is dropped when going through the union IBC stack.
+pub fn packet_fee_key(
+    channel_id: &str,
+    sequence: u64,
+    data: &[u8],
+    timeout: &IbcTimeout,
+) -> PacketFeeKey {
+    keccak256(
+        [
+            data,
+            channel_id.as_bytes(),
+            &sequence.to_be_bytes(),
+            &timeout
+                .block()
+                .map(|block| block.height)
+                .unwrap_or_default()
+                .to_be_bytes(),
+            &timeout
+                .timestamp()
+                .unwrap_or(Timestamp::from_nanos(0))
+                .nanos()
+                .to_be_bytes(),
+        ]
+        .concat(),
+    )
+    .into()
+}
+
+/// Undo the receive of the origin packet of a failed PFM hop: local tokens that were unescrowed
+/// are escrowed back and vouchers that were minted to the contract are burnt.
+fn revert_pfm_receive(
+    deps: DepsMut,
+    block: &BlockInfo,
+    contract_address: &Addr,
+    refund_info: &InFlightPfmPacket,
+) -> Result<Vec<CosmosMsg<TokenFactoryMsg>>, ContractError> {
+    let origin_packet = &refund_info.origin_packet;
+    let tokens = match refund_info.origin_protocol_version.as_str() {
+        Ics20Protocol::VERSION => {
+            <Ics20Packet as Decode<JsonWasm>>::decode(origin_packet.data.as_slice())?.tokens()
+        }
+        Ucs01Protocol::VERSION => <Ucs01TransferPacket as Decode<encoding::EthAbi>>::decode(
+            origin_packet.data.as_slice(),
+        )?
+        .tokens(),
+        v => {
+            return Err(ContractError::UnknownProtocol {
+                channel_id: origin_packet.dest.channel_id.clone(),
+                protocol_version: v.to_string(),
+            })
+        }
+    };
+
+    let mut msgs = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        // Same origin as computed in `receive_phase1_transfer`, fees are never cut on a PFM hop.
+        match DenomOrigin::from((token.denom.as_str(), &origin_packet.src)) {
+            DenomOrigin::Local { denom } => {
+                increase_outstanding(
+                    deps.branch(),
+                    block,
+                    &origin_packet.dest.channel_id,
+                    denom,
+                    token.amount,
+                )?;
+            }
+            DenomOrigin::Remote { denom } => {
+                let foreign_denom = make_foreign_denom(&origin_packet.dest, denom);
+                let factory_denom = make_factory_denom(
+                    contract_address,
+                    &encode_denom_hash(hash_denom(&foreign_denom)),
+                );
+                msgs.push(
+                    TokenFactoryMsg::BurnTokens {
+                        denom: factory_denom,
+                        amount: token.amount,
+                        burn_from_address: contract_address.to_string(),
+                    }
+                    .into(),
+                );
+            }
+        }
+    }
+    Ok(msgs)
+}
+
+/// Stage the fee or the pending record of the packet being sent, see [`STAGED_SEND`].
+fn stage_send(
+    storage: &mut dyn Storage,
+    channel_id: &str,
+    data: &Binary,
+    timeout: &IbcTimeout,
+    f: impl FnOnce(&mut StagedSend),
+) -> Result<(), ContractError> {
+    let mut staged = STAGED_SEND
+        .may_load(storage)?
+        .unwrap_or_else(|| StagedSend {
+            channel_id: channel_id.into(),
+            data: data.clone(),
+            timeout: timeout.clone(),
+            fee: None,
+            packet: None,
+        });
+    f(&mut staged);
+    STAGED_SEND.save(storage, &staged)?;
+    Ok(())
+}
+
+/// Move the fee and pending record staged for the packet sent by the current transfer to
+/// `PACKET_FEES` and `PENDING_PACKETS`, now that the `sequence` of the packet is known from the
+/// [`IBC_SEND_ID`] reply.
+pub fn commit_staged_send(storage: &mut dyn Storage, sequence: u64) -> Result<(), ContractError> {
+    let Some(staged) = STAGED_SEND.may_load(storage)? else {
+        return Ok(());
+    };
+    STAGED_SEND.remove(storage);
+
+    let key = packet_fee_key(&staged.channel_id, sequence, &staged.data, &staged.timeout);
+    if let Some(fee) = staged.fee {
+        if PACKET_FEES.has(storage, key) {
+            return Err(ContractError::PacketFeeAlreadyEscrowed);
+        }
+        PACKET_FEES.save(storage, key, &fee)?;
+    }
+    if let Some(packet) = staged.packet {
+        PENDING_PACKETS.save(
+            storage,
+            (staged.channel_id.as_str(), key.0.get().as_slice()),
+            &packet,
+        )?;
+    }
+    Ok(())
+}
+
 pub trait TransferProtocolExt<'a>:
     TransferProtocol<Error: From<ContractError> + From<StdError>, CustomMsg = TokenFactoryMsg>
 {
@@ -76,6 +218,167 @@ pub trait TransferProtocolExt<'a>:
             .ok()
     }
 
+    fn do_consume_inflow(&mut self, tokens: &[NormalizedTransferToken]) -> Result<(), Self::Error> {
+        let common = self.common_mut();
+        for NormalizedTransferToken { token, .. } in tokens {
+            consume_rate_limit(
+                common.deps.branch(),
+                common.env.block.time,
+                &common.channel.endpoint.channel_id,
+                &token.denom,
+                token.amount,
+                Flow::Inflow,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn do_escrow_packet_fee(
+        &mut self,
+        payer: &Addr,
+        data: &Binary,
+        timeout: &IbcTimeout,
+        fee: PacketFee,
+    ) -> Result<(), Self::Error> {
+        let common = self.common_mut();
+        stage_send(
+            common.deps.storage,
+            &common.channel.endpoint.channel_id,
+            data,
+            timeout,
+            |staged| {
+                staged.fee = Some(EscrowedPacketFee {
+                    payer: payer.clone(),
+                    fee,
+                })
+            },
+        )?;
+        Ok(())
+    }
+
+    fn do_release_packet_fee(
+        &mut self,
+        packet: &IbcPacket,
+        timed_out: bool,
+    ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error> {
+        let key = packet_fee_key(
+            &packet.src.channel_id,
+            packet.sequence,
+            &packet.data,
+            &packet.timeout,
+        );
+        let storage = &mut *self.common_mut().deps.storage;
+        let Some(EscrowedPacketFee { payer, fee }) = PACKET_FEES.may_load(storage, key)? else {
+            return Ok(vec![]);
+        };
+        PACKET_FEES.remove(storage, key);
+
+        // The relayer that delivered the packet on the counterparty is unknown, the recv fee is
+        // therefore paid to the relayer delivering the acknowledgement.
+        let PacketFee {
+            recv_fee,
+            ack_fee,
+            timeout_fee,
+        } = fee;
+        let (paid, refunded) = if timed_out {
+            (timeout_fee, [recv_fee, ack_fee].concat())
+        } else {
+            ([recv_fee, ack_fee].concat(), timeout_fee)
+        };
+
+        let relayer = self.common().info.sender.clone();
+        let mut msgs = vec![];
+        for (to_address, amount) in [(relayer, paid), (payer, refunded)] {
+            let mut coins = Coins::default();
+            for coin in amount {
+                coins.add(coin)?;
+            }
+            if !coins.is_empty() {
+                msgs.push(
+                    BankMsg::Send {
+                        to_address: to_address.into(),
+                        amount: coins.into_vec(),
+                    }
+                    .into(),
+                );
+            }
+        }
+        Ok(msgs)
+    }
+
+    fn do_record_pending_packet(
+        &mut self,
+        input: &TransferInput,
+        data: &Binary,
+        timeout: &IbcTimeout,
+    ) -> Result<(), Self::Error> {
+        let common = self.common_mut();
+        let packet = PendingPacket {
+            sender: input.sender.clone(),
+            receiver: input.receiver.clone(),
+            tokens: input
+                .tokens
+                .iter()
+                .map(|token| Coin::new(token.amount, token.denom.clone()))
+                .collect(),
+            timeout: timeout.clone(),
+            sent_at: common.env.block.time,
+        };
+        stage_send(
+            common.deps.storage,
+            &common.channel.endpoint.channel_id,
+            data,
+            timeout,
+            |staged| staged.packet = Some(packet),
+        )?;
+        Ok(())
+    }
+
+    fn do_clear_pending_packet(&mut self, packet: &IbcPacket) -> Result<(), Self::Error> {
+        let key = packet_fee_key(
+            &packet.src.channel_id,
+            packet.sequence,
+            &packet.data,
+            &packet.timeout,
+        );
+        PENDING_PACKETS.remove(
+            self.common_mut().deps.storage,
+            (packet.src.channel_id.as_str(), key.0.get().as_slice()),
+        );
+        Ok(())
+    }
+
+    fn do_record_deferred_ack(&mut self, packet: &IbcPacket) -> Result<(), Self::Error> {
+        DEFERRED_ACKS.save(
+            self.common_mut().deps.storage,
+            packet_key(packet),
+            &Empty {},
+        )?;
+        Ok(())
+    }
+
+    fn do_reply_on_receive_success(&self) -> bool {
+        CONFIG
+            .may_load(self.common().deps.storage)
+            .ok()
+            .flatten()
+            .is_some_and(|config| config.reply_on_receive_success)
+    }
+
+    fn do_write_async_ack(
+        &mut self,
+        packet: &IbcPacket,
+        ack: Binary,
+    ) -> Result<CosmosMsg<Self::CustomMsg>, Self::Error> {
+        let key = packet_key(packet);
+        let storage = &mut *self.common_mut().deps.storage;
+        if !DEFERRED_ACKS.has(storage, key) {
+            return Err(ContractError::NoDeferredAck.into());
+        }
+        DEFERRED_ACKS.remove(storage, key);
+        self.write_acknowledgement(packet, ack)
+    }
+
     #[allow(clippy::type_complexity)]
     fn do_pfm_ack(
         &mut self,
@@ -84,7 +387,7 @@ pub trait TransferProtocolExt<'a>:
         ack: Result<Vec<u8>, Vec<u8>>,
         sender: &AddrOf<Self::Packet>,
         tokens: Vec<TransferToken>,
-    ) -> Result<(Vec<CosmosMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error> {
+    ) -> Result<(Vec<SubMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error> {
         let ack =
             self.convert_ack_to_foreign_protocol(&refund_info.origin_protocol_version, ack)?;
 
@@ -92,7 +395,10 @@ pub trait TransferProtocolExt<'a>:
             Ok(value) => {
                 let value_string = Binary::from(value.clone()).to_string();
                 (
-                    self.send_tokens_success(sender, &Default::default(), tokens)?,
+                    self.send_tokens_success(sender, &Default::default(), tokens)?
+                        .into_iter()
+                        .map(SubMsg::new)
+                        .collect(),
                     Vec::from_iter(
                         (!value_string.is_empty())
                             .then_some(Attribute::new(ATTR_SUCCESS, value_string)),
@@ -100,17 +406,42 @@ pub trait TransferProtocolExt<'a>:
                     value.to_vec(),
                 )
             }
-            Err(error) => (
-                self.send_tokens_failure(sender, &Default::default(), tokens)?,
-                Vec::from_iter((!error.is_empty()).then_some(Attribute::new(
-                    ATTR_ERROR,
-                    Binary::from(error.clone()).to_string(),
-                ))),
-                error.to_vec(),
-            ),
+            Err(error) => {
+                // The tokens are refunded to the contract, we must then undo the receive of the
+                // origin packet as the origin chain is going to refund the original sender.
+                let mut msgs = self.send_tokens_failure(sender, &Default::default(), tokens)?;
+                let common = self.common_mut();
+                msgs.extend(
+                    revert_pfm_receive(
+                        common.deps.branch(),
+                        &common.env.block,
+                        &common.env.contract.address,
+                        &refund_info,
+                    )?
+                    .into_iter()
+                    .map(SubMsg::new),
+                );
+                (
+                    msgs,
+                    (!error.is_empty())
+                        .then_some(Attribute::new(
+                            ATTR_ERROR,
+                            Binary::from(error.clone()).to_string(),
+                        ))
+                        .into_iter()
+                        .chain([Attribute::new(
+                            ATTR_ERROR_CODE,
+                            AckErrorCode::from_error_ack(&error).as_str(),
+                        )])
+                        .collect(),
+                    error.to_vec(),
+                )
+            }
         };
 
-        ack_msgs.push(self.write_acknowledgement(&refund_info.origin_packet, ack_bytes.into())?);
+        ack_msgs.push(SubMsg::new(
+            self.write_async_ack(&refund_info.origin_packet, ack_bytes.into())?,
+        ));
 
         ack_attrs.push(Attribute::new(ATTR_PFM, ATTR_VALUE_PFM_ACK.to_string()));
 
@@ -145,8 +476,10 @@ pub trait TransferProtocolExt<'a>:
             channel: forward.channel.clone().to_string(),
             receiver: forward.receiver.value(),
             timeout: Some(timeout),
+            timeout_height: None,
             memo,
             fees: forward.fees,
+            packet_fee: None,
         };
 
         // Send forward message
@@ -298,6 +631,64 @@ pub fn encode_denom_hash(denom_hash: DenomHash) -> String {
     result
 }
 
+/// Inverse of [`encode_denom_hash`], returns `None` if `subdenom` isn't an encoded denom hash.
+pub fn decode_denom_hash(subdenom: &str) -> Option<DenomHash> {
+    subdenom
+        .from_base58()
+        .ok()
+        .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+        .map(|hash| DenomHash(unionlabs::primitives::H256::new(hash)))
+}
+
+/// Handlers of the contract's `reply` entry point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyHandler {
+    /// Submessages emitted while receiving an [`Ics20Protocol`] transfer.
+    Ics20Receive,
+    /// Submessages emitted while receiving an [`Ucs01Protocol`] transfer.
+    Ucs01Receive,
+    /// The packet sent by a transfer, PFM or not.
+    Send,
+    /// The per-token refunds emitted by [`refund_tokens`].
+    Refund,
+}
+
+/// Registry of the reply ids handled by the contract's `reply` entry point, which dispatches on
+/// it. Each id must be routed to a single handler, register any new submessage reply id here.
+pub const REPLY_IDS: [(u64, ReplyHandler); 4] = [
+    (Ics20Protocol::RECEIVE_REPLY_ID, ReplyHandler::Ics20Receive),
+    (Ucs01Protocol::RECEIVE_REPLY_ID, ReplyHandler::Ucs01Receive),
+    (IBC_SEND_ID, ReplyHandler::Send),
+    (REFUND_REPLY_ID, ReplyHandler::Refund),
+];
+
+/// Reply id of the submessages emitted by [`refund_tokens`].
+pub const REFUND_REPLY_ID: u64 = 2;
+
+const _: () = assert!(reply_ids_unique(&REPLY_IDS), "reply ids must be unique");
+
+const fn reply_ids_unique<T>(ids: &[(u64, T)]) -> bool {
+    let mut i = 0;
+    while i < ids.len() {
+        let mut j = i + 1;
+        while j < ids.len() {
+            if ids[i].0 == ids[j].0 {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Returns the handler registered for the reply id, if any.
+pub fn reply_id_handler(id: u64) -> Option<ReplyHandler> {
+    REPLY_IDS
+        .iter()
+        .find_map(|(reply_id, handler)| (*reply_id == id).then_some(*handler))
+}
+
 pub fn protocol_ordering(version: &str) -> Option<IbcOrder> {
     match version {
         Ics20Protocol::VERSION => Some(Ics20Protocol::ORDERING),
@@ -320,6 +711,7 @@ fn batch_submessages(
 
 fn update_outstanding<F>(
     deps: DepsMut,
+    block: &BlockInfo,
     channel_id: &str,
     denom: &str,
     f: F,
@@ -327,26 +719,40 @@ fn update_outstanding<F>(
 where
     F: FnOnce(Option<Uint512>) -> Result<Uint512, ContractError>,
 {
-    CHANNEL_STATE.update(
+    let state = CHANNEL_STATE.may_load(deps.storage, (channel_id, denom))?;
+    let new_outstanding = f(state.as_ref().map(|x| x.outstanding))?;
+    let mut state = state.unwrap_or_default();
+    let previous = state.outstanding;
+    state.outstanding = new_outstanding;
+    CHANNEL_STATE.save(deps.storage, (channel_id, denom), &state)?;
+
+    let index = NEXT_CHANNEL_HISTORY_INDEX
+        .may_load(deps.storage, channel_id)?
+        .unwrap_or_default();
+    CHANNEL_HISTORY.save(
         deps.storage,
-        (channel_id, denom),
-        |state| -> Result<_, ContractError> {
-            let new_outstanding = f(state.as_ref().map(|x| x.outstanding))?;
-            let mut state = state.unwrap_or_default();
-            state.outstanding = new_outstanding;
-            Ok(state)
+        (channel_id, index),
+        &ChannelBalanceChange {
+            denom: denom.to_string(),
+            previous,
+            outstanding: new_outstanding,
+            height: block.height,
+            time: block.time,
         },
     )?;
+    NEXT_CHANNEL_HISTORY_INDEX.save(deps.storage, channel_id, &(index + 1))?;
+
     Ok(())
 }
 
 fn increase_outstanding(
     deps: DepsMut,
+    block: &BlockInfo,
     channel_id: &str,
     denom: &str,
     amount: Uint128,
 ) -> Result<(), ContractError> {
-    update_outstanding(deps, channel_id, denom, |outstanding| {
+    update_outstanding(deps, block, channel_id, denom, |outstanding| {
         let new_outstanding = outstanding.unwrap_or_default().checked_add(amount.into())?;
         Ok(new_outstanding)
     })
@@ -354,18 +760,80 @@ fn increase_outstanding(
 
 fn decrease_outstanding(
     deps: DepsMut,
+    block: &BlockInfo,
     channel_id: &str,
     denom: &str,
     amount: Uint128,
 ) -> Result<(), ContractError> {
-    update_outstanding(deps, channel_id, denom, |outstanding| {
-        let new_outstanding = outstanding
-            .ok_or(ContractError::InsufficientFunds)?
-            .checked_sub(amount.into())?;
-        Ok(new_outstanding)
+    update_outstanding(deps, block, channel_id, denom, |outstanding| {
+        // Releasing more than what was escrowed means that the accounting drifted, refuse it
+        // instead of silently over-refunding.
+        let outstanding = outstanding.unwrap_or_default();
+        outstanding
+            .checked_sub(amount.into())
+            .map_err(|_| ContractError::InsufficientEscrow {
+                channel_id: channel_id.to_string(),
+                denom: denom.to_string(),
+                outstanding,
+                amount,
+            })
     })
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Inflow,
+    Outflow,
+}
+
+/// Account `amount` of `denom` flowing through `channel_id` against its rate limit, if any.
+///
+/// The limit applies to a window of `limit.window` seconds ending now. The usage is tracked per
+/// fixed window, the usage of the previous fixed window being assumed to be evenly spread over it
+/// and weighted by the portion of it still covered by the rolling window.
+pub fn consume_rate_limit(
+    deps: DepsMut,
+    now: Timestamp,
+    channel_id: &str,
+    denom: &str,
+    amount: Uint128,
+    flow: Flow,
+) -> Result<(), ContractError> {
+    let Some(mut state) = RATE_LIMITS.may_load(deps.storage, (channel_id, denom))? else {
+        return Ok(());
+    };
+    let window = state.limit.window;
+    let elapsed = now.seconds().saturating_sub(state.window_start.seconds());
+    if elapsed >= window {
+        // the usage of the window that just ended becomes the previous one, unless no transfer
+        // happened during a whole window since
+        if elapsed < window.saturating_mul(2) {
+            state.previous_inflow = state.inflow;
+            state.previous_outflow = state.outflow;
+        } else {
+            state.previous_inflow = Uint128::zero();
+            state.previous_outflow = Uint128::zero();
+        }
+        state.window_start = state.window_start.plus_seconds(elapsed - elapsed % window);
+        state.inflow = Uint128::zero();
+        state.outflow = Uint128::zero();
+    }
+    let elapsed = now.seconds().saturating_sub(state.window_start.seconds());
+    let (used, previous) = match flow {
+        Flow::Inflow => (&mut state.inflow, state.previous_inflow),
+        Flow::Outflow => (&mut state.outflow, state.previous_outflow),
+    };
+    *used = used.checked_add(amount)?;
+    if used.checked_add(previous.multiply_ratio(window - elapsed, window))? > state.limit.amount {
+        return Err(ContractError::RateLimitExceeded {
+            channel_id: channel_id.to_string(),
+            denom: denom.to_string(),
+        });
+    }
+    RATE_LIMITS.save(deps.storage, (channel_id, denom), &state)?;
+    Ok(())
+}
+
 fn normalize_for_ibc_transfer(
     mut hash_to_denom: impl FnMut(DenomHash) -> Result<Option<String>, ContractError>,
     contract_address: &str,
@@ -379,15 +847,12 @@ fn normalize_for_ibc_transfer(
         .and_then(|denom| denom.strip_prefix("/"))
     {
         Some(denom_hash) => {
-            if let Some(normalized_denom) =
-                hash_to_denom(DenomHash(unionlabs::primitives::H256::new(
-                    denom_hash
-                        .from_base58()
-                        .expect("impossible")
-                        .try_into()
-                        .expect("impossible"),
-                )))?
-            {
+            let denom_hash = decode_denom_hash(denom_hash).ok_or_else(|| {
+                ContractError::InvalidFactoryDenom {
+                    denom: token.denom.clone(),
+                }
+            })?;
+            if let Some(normalized_denom) = hash_to_denom(denom_hash)? {
                 // This is the POV of the counterparty chain, where we transfer from A to B. It's a similar check than in receive_phase1.
                 // If the denom is prefixed by the source chain path (A), it means it was local (originating from B and minted on A).
                 // If the denom isn't prefixed by the source chain path (A), it means it was remote (originating from A chain).
@@ -569,6 +1034,7 @@ trait OnReceive {
 
 pub struct StatefulOnReceive<'a> {
     deps: DepsMut<'a>,
+    block: BlockInfo,
 }
 impl OnReceive for StatefulOnReceive<'_> {
     fn foreign_toggle(
@@ -604,7 +1070,7 @@ impl OnReceive for StatefulOnReceive<'_> {
         denom: &str,
         amount: Uint128,
     ) -> Result<(), ContractError> {
-        decrease_outstanding(self.deps.branch(), channel_id, denom, amount)?;
+        decrease_outstanding(self.deps.branch(), &self.block, channel_id, denom, amount)?;
         Ok(())
     }
 }
@@ -665,6 +1131,7 @@ trait ForTokens {
 
 struct StatefulSendTokens<'a> {
     deps: DepsMut<'a>,
+    block: BlockInfo,
     contract_address: String,
 }
 
@@ -679,7 +1146,13 @@ impl ForTokens for StatefulSendTokens<'_> {
         let total_amount = amount
             .checked_add(fee_amount)
             .expect("impossible; fee must be split from the base amount");
-        increase_outstanding(self.deps.branch(), channel_id, denom, total_amount)?;
+        increase_outstanding(
+            self.deps.branch(),
+            &self.block,
+            channel_id,
+            denom,
+            total_amount,
+        )?;
         Ok(Default::default())
     }
 
@@ -703,6 +1176,7 @@ impl ForTokens for StatefulSendTokens<'_> {
 
 struct StatefulRefundTokens<'a> {
     deps: DepsMut<'a>,
+    block: BlockInfo,
     receiver: String,
 }
 
@@ -717,7 +1191,13 @@ impl ForTokens for StatefulRefundTokens<'_> {
         let total_amount = amount
             .checked_add(fee_amount)
             .expect("impossible; fee must be split from the base amount");
-        decrease_outstanding(self.deps.branch(), channel_id, denom, total_amount)?;
+        decrease_outstanding(
+            self.deps.branch(),
+            &self.block,
+            channel_id,
+            denom,
+            total_amount,
+        )?;
         Ok(vec![BankMsg::Send {
             to_address: self.receiver.clone(),
             amount: vec![Coin {
@@ -747,6 +1227,88 @@ impl ForTokens for StatefulRefundTokens<'_> {
     }
 }
 
+/// The payload of the submessages emitted by [`refund_tokens`], used to surface the refund if it
+/// fails.
+#[cw_serde]
+pub struct Refund {
+    pub channel_id: String,
+    pub receiver: String,
+    pub denom: String,
+    pub amount: Uint128,
+    /// Whether the refunded tokens were released from the escrow of the channel, as opposed to
+    /// minted.
+    pub escrowed: bool,
+}
+
+/// Refund each of `tokens` to `receiver` in its own submessage, such that a failure to refund one
+/// token (e.g. a token factory error) does not prevent the others from being refunded. Failed
+/// refunds are handled by [`refund_failed`].
+fn refund_tokens(
+    deps: DepsMut,
+    block: &BlockInfo,
+    contract_address: &Addr,
+    endpoint: &IbcEndpoint,
+    receiver: &str,
+    tokens: Vec<TransferToken>,
+) -> Result<Vec<SubMsg<TokenFactoryMsg>>, ContractError> {
+    let mut refund_tokens = StatefulRefundTokens {
+        deps,
+        block: block.clone(),
+        receiver: receiver.into(),
+    };
+    let mut submessages = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let payload = to_json_binary(&Refund {
+            channel_id: endpoint.channel_id.clone(),
+            receiver: receiver.into(),
+            denom: token.denom.clone(),
+            amount: token.amount,
+            escrowed: matches!(
+                DenomOrigin::from((token.denom.as_str(), endpoint)),
+                DenomOrigin::Remote { .. }
+            ),
+        })?;
+        submessages.extend(
+            refund_tokens
+                .execute(contract_address, endpoint, vec![token])?
+                .into_iter()
+                .map(|msg| {
+                    SubMsg::reply_on_error(msg, REFUND_REPLY_ID).with_payload(payload.clone())
+                }),
+        );
+    }
+    Ok(submessages)
+}
+
+/// Handle a failed refund emitted by [`refund_tokens`]. The escrow released for the refund is
+/// restored, as the tokens are still held by the contract, and the refund is surfaced in an event
+/// such that it can be recovered manually.
+pub fn refund_failed(
+    deps: DepsMut,
+    block: &BlockInfo,
+    refund: Refund,
+    error: String,
+) -> Result<Response<TokenFactoryMsg>, ContractError> {
+    if refund.escrowed {
+        increase_outstanding(
+            deps,
+            block,
+            &refund.channel_id,
+            &refund.denom,
+            refund.amount,
+        )?;
+    }
+    Ok(
+        Response::new().add_event(Event::new(REFUND_FAILURE_EVENT).add_attributes([
+            ("channel_id", refund.channel_id),
+            (ATTR_RECEIVER, refund.receiver),
+            ("denom", refund.denom),
+            ("amount", refund.amount.to_string()),
+            (ATTR_ERROR, error),
+        ])),
+    )
+}
+
 pub struct ProtocolCommon<'a> {
     pub deps: DepsMut<'a>,
     pub env: Env,
@@ -823,6 +1385,7 @@ impl TransferProtocol for Ics20Protocol<'_> {
     ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error> {
         StatefulSendTokens {
             deps: self.common.deps.branch(),
+            block: self.common.env.block.clone(),
             contract_address: self.common.env.contract.address.to_string(),
         }
         .execute(
@@ -846,14 +1409,13 @@ impl TransferProtocol for Ics20Protocol<'_> {
         sender: &String,
         _receiver: &String,
         tokens: Vec<TransferToken>,
-    ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error> {
-        StatefulRefundTokens {
-            deps: self.common.deps.branch(),
-            receiver: sender.into(),
-        }
-        .execute(
+    ) -> Result<Vec<SubMsg<Self::CustomMsg>>, Self::Error> {
+        refund_tokens(
+            self.common.deps.branch(),
+            &self.common.env.block,
             &self.common.env.contract.address,
             &self.common.channel.endpoint,
+            sender,
             tokens,
         )
     }
@@ -871,8 +1433,20 @@ impl TransferProtocol for Ics20Protocol<'_> {
         ),
         Self::Error,
     > {
+        // an invalid receiver would otherwise only fail when the transfer submessages are
+        // executed, reject it upfront so that the sender is refunded with a failure ack
+        let receiver = self
+            .common
+            .deps
+            .api
+            .addr_validate(receiver)
+            .map_err(|error| ContractError::InvalidReceiver {
+                receiver: receiver.clone(),
+                error,
+            })?;
         let (tokens, msgs) = StatefulOnReceive {
             deps: self.common.deps.branch(),
+            block: self.common.env.block.clone(),
         }
         .receive_phase1_transfer(
             &self.common.env.contract.address,
@@ -883,6 +1457,7 @@ impl TransferProtocol for Ics20Protocol<'_> {
             tokens,
             cut_fees,
         )?;
+        self.do_consume_inflow(&tokens)?;
         Ok((tokens, batch_submessages(self.self_addr(), msgs)?))
     }
 
@@ -928,7 +1503,7 @@ impl TransferProtocol for Ics20Protocol<'_> {
         refund_info: InFlightPfmPacket,
         sender: &AddrOf<Self::Packet>,
         tokens: Vec<TransferToken>,
-    ) -> Result<(Vec<CosmosMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error> {
+    ) -> Result<(Vec<SubMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error> {
         self.do_pfm_ack(ibc_packet, refund_info, ack, sender, tokens)
     }
 
@@ -956,6 +1531,53 @@ impl TransferProtocol for Ics20Protocol<'_> {
         self.do_get_in_flight_packet(forward_packet)
     }
 
+    fn escrow_packet_fee(
+        &mut self,
+        payer: &Addr,
+        data: &Binary,
+        timeout: &IbcTimeout,
+        fee: PacketFee,
+    ) -> Result<(), Self::Error> {
+        self.do_escrow_packet_fee(payer, data, timeout, fee)
+    }
+
+    fn release_packet_fee(
+        &mut self,
+        packet: &IbcPacket,
+        timed_out: bool,
+    ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error> {
+        self.do_release_packet_fee(packet, timed_out)
+    }
+
+    fn record_pending_packet(
+        &mut self,
+        input: &TransferInput,
+        data: &Binary,
+        timeout: &IbcTimeout,
+    ) -> Result<(), Self::Error> {
+        self.do_record_pending_packet(input, data, timeout)
+    }
+
+    fn clear_pending_packet(&mut self, packet: &IbcPacket) -> Result<(), Self::Error> {
+        self.do_clear_pending_packet(packet)
+    }
+
+    fn record_deferred_ack(&mut self, packet: &IbcPacket) -> Result<(), Self::Error> {
+        self.do_record_deferred_ack(packet)
+    }
+
+    fn write_async_ack(
+        &mut self,
+        packet: &IbcPacket,
+        ack: Binary,
+    ) -> Result<CosmosMsg<Self::CustomMsg>, Self::Error> {
+        self.do_write_async_ack(packet, ack)
+    }
+
+    fn reply_on_receive_success(&self, _packet: &Self::Packet) -> bool {
+        self.do_reply_on_receive_success()
+    }
+
     fn load_channel_protocol_version(&self, channel_id: &str) -> Result<String, Self::Error> {
         Ok(query_ibc_channel(self.common.deps.as_ref(), channel_id.to_string())?.version)
     }
@@ -1095,6 +1717,7 @@ impl TransferProtocol for Ucs01Protocol<'_> {
     ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error> {
         StatefulSendTokens {
             deps: self.common.deps.branch(),
+            block: self.common.env.block.clone(),
             contract_address: self.common.env.contract.address.to_string(),
         }
         .execute(
@@ -1119,15 +1742,14 @@ impl TransferProtocol for Ucs01Protocol<'_> {
         sender: &HexBinary,
         _receiver: &HexBinary,
         tokens: Vec<TransferToken>,
-    ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error> {
+    ) -> Result<Vec<SubMsg<Self::CustomMsg>>, Self::Error> {
         let addr = self.common.deps.api.addr_humanize(&sender.clone().into())?;
-        StatefulRefundTokens {
-            deps: self.common.deps.branch(),
-            receiver: addr.to_string(),
-        }
-        .execute(
+        refund_tokens(
+            self.common.deps.branch(),
+            &self.common.env.block,
             &self.common.env.contract.address,
             &self.common.channel.endpoint,
+            addr.as_str(),
             tokens,
         )
     }
@@ -1145,14 +1767,19 @@ impl TransferProtocol for Ucs01Protocol<'_> {
         ),
         Self::Error,
     > {
-        let receiver = self
-            .common
-            .deps
-            .api
-            .addr_humanize(&receiver.clone().into())?;
-        // TODO(aeryz): call `addr_validate` here
+        // an invalid receiver would otherwise only fail when the transfer submessages are
+        // executed, reject it upfront so that the sender is refunded with a failure ack
+        let api = self.common.deps.api;
+        let receiver = api
+            .addr_humanize(&receiver.clone().into())
+            .and_then(|receiver| api.addr_validate(receiver.as_str()))
+            .map_err(|error| ContractError::InvalidReceiver {
+                receiver: receiver.to_hex(),
+                error,
+            })?;
         let (tokens, msgs) = StatefulOnReceive {
             deps: self.common.deps.branch(),
+            block: self.common.env.block.clone(),
         }
         .receive_phase1_transfer(
             &self.common.env.contract.address,
@@ -1163,6 +1790,7 @@ impl TransferProtocol for Ucs01Protocol<'_> {
             tokens,
             cut_fees,
         )?;
+        self.do_consume_inflow(&tokens)?;
 
         Ok((tokens, batch_submessages(self.self_addr(), msgs)?))
     }
@@ -1228,7 +1856,7 @@ impl TransferProtocol for Ucs01Protocol<'_> {
         refund_info: InFlightPfmPacket,
         sender: &AddrOf<Self::Packet>,
         tokens: Vec<TransferToken>,
-    ) -> Result<(Vec<CosmosMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error> {
+    ) -> Result<(Vec<SubMsg<Self::CustomMsg>>, Vec<Attribute>), Self::Error> {
         self.do_pfm_ack(ibc_packet, refund_info, ack, sender, tokens)
     }
 
@@ -1255,6 +1883,53 @@ impl TransferProtocol for Ucs01Protocol<'_> {
         self.do_get_in_flight_packet(forward_packet)
     }
 
+    fn escrow_packet_fee(
+        &mut self,
+        payer: &Addr,
+        data: &Binary,
+        timeout: &IbcTimeout,
+        fee: PacketFee,
+    ) -> Result<(), Self::Error> {
+        self.do_escrow_packet_fee(payer, data, timeout, fee)
+    }
+
+    fn release_packet_fee(
+        &mut self,
+        packet: &IbcPacket,
+        timed_out: bool,
+    ) -> Result<Vec<CosmosMsg<Self::CustomMsg>>, Self::Error> {
+        self.do_release_packet_fee(packet, timed_out)
+    }
+
+    fn record_pending_packet(
+        &mut self,
+        input: &TransferInput,
+        data: &Binary,
+        timeout: &IbcTimeout,
+    ) -> Result<(), Self::Error> {
+        self.do_record_pending_packet(input, data, timeout)
+    }
+
+    fn clear_pending_packet(&mut self, packet: &IbcPacket) -> Result<(), Self::Error> {
+        self.do_clear_pending_packet(packet)
+    }
+
+    fn record_deferred_ack(&mut self, packet: &IbcPacket) -> Result<(), Self::Error> {
+        self.do_record_deferred_ack(packet)
+    }
+
+    fn write_async_ack(
+        &mut self,
+        packet: &IbcPacket,
+        ack: Binary,
+    ) -> Result<CosmosMsg<Self::CustomMsg>, Self::Error> {
+        self.do_write_async_ack(packet, ack)
+    }
+
+    fn reply_on_receive_success(&self, _packet: &Self::Packet) -> bool {
+        self.do_reply_on_receive_success()
+    }
+
     fn load_channel_protocol_version(&self, channel_id: &str) -> Result<String, Self::Error> {
         Ok(query_ibc_channel(self.common.deps.as_ref(), channel_id.to_string())?.version)
     }
@@ -1274,21 +1949,41 @@ impl TransferProtocol for Ucs01Protocol<'_> {
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::{
-        testing::{message_info, mock_dependencies, mock_env},
-        wasm_execute, Addr, BankMsg, Coin, CosmosMsg, IbcChannel, IbcEndpoint, Uint128,
+        from_json,
+        testing::{message_info, mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage},
+        to_json_binary, wasm_execute, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut,
+        HexBinary, IbcAcknowledgement, IbcChannel, IbcEndpoint, IbcMsg, IbcPacket, IbcPacketAckMsg,
+        IbcReceiveResponse, IbcTimeout, MsgResponse, OwnedDeps, Reply, ReplyOn, SubMsgResponse,
+        SubMsgResult, Timestamp, Uint128,
     };
+    use prost::{Message, Name};
+    use protos::cosmwasm::wasm::v1::MsgIbcSendResponse;
     use token_factory_api::TokenFactoryMsg;
     use ucs01_relay_api::{
-        protocol::TransferProtocol,
-        types::{FeePerU128, TransferToken},
+        middleware::InFlightPfmPacket,
+        protocol::{
+            ProtocolError, TransferInput, TransferProtocol, ATTR_ERROR_CODE, IBC_SEND_ID,
+            REFUND_FAILURE_EVENT,
+        },
+        types::{make_factory_denom, FeePerU128, Ics20Packet, PacketFee, TransferToken},
     };
+    use unionlabs::encoding::Encode;
 
-    use super::{hash_denom, ForTokens, OnReceive, StatefulOnReceive};
+    use super::{
+        consume_rate_limit, hash_denom, packet_key, refund_failed, refund_tokens, reply_id_handler,
+        reply_ids_unique, revert_pfm_receive, Flow, ForTokens, OnReceive, Refund, ReplyHandler,
+        StatefulOnReceive, StatefulRefundTokens, StatefulSendTokens, REFUND_REPLY_ID, REPLY_IDS,
+    };
     use crate::{
+        contract::{migrate, query_pending_packets},
         error::ContractError,
-        msg::ExecuteMsg,
-        protocol::{encode_denom_hash, normalize_for_ibc_transfer, Ics20Protocol},
-        state::DenomHash,
+        ibc::reply,
+        msg::{ExecuteMsg, MigrateMsg, RateLimit},
+        protocol::{encode_denom_hash, normalize_for_ibc_transfer, Ics20Protocol, Ucs01Protocol},
+        state::{
+            ChannelBalanceChange, Config, DenomHash, PendingPacket, RateLimitState,
+            CHANNEL_HISTORY, CHANNEL_STATE, CONFIG, IN_FLIGHT_PFM_PACKETS, RATE_LIMITS,
+        },
     };
 
     #[test]
@@ -1322,6 +2017,110 @@ mod tests {
         );
     }
 
+    #[test]
+    fn receive_error_emits_error_code() {
+        let error_code = |response: IbcReceiveResponse<TokenFactoryMsg>| {
+            response.events[0]
+                .attributes
+                .iter()
+                .find(|attr| attr.key == ATTR_ERROR_CODE)
+                .map(|attr| attr.value.clone())
+        };
+
+        assert_eq!(
+            error_code(Ics20Protocol::receive_error(ContractError::ChannelPaused {
+                channel_id: "channel-0".into()
+            })),
+            Some("channel_paused".into())
+        );
+        assert_eq!(
+            error_code(Ucs01Protocol::receive_error(ContractError::Protocol(
+                ProtocolError::ZeroAmount {
+                    denom: "muno".into()
+                }
+            ))),
+            Some("zero_amount".into())
+        );
+        // the error of a reverted submessage is only known as a string
+        assert_eq!(
+            error_code(Ics20Protocol::receive_error("out of gas".to_string())),
+            Some("submessage_failed".into())
+        );
+    }
+
+    #[test]
+    fn receive_transfer_rejects_invalid_receiver() {
+        let mut deps = mock_dependencies();
+        let endpoint = IbcEndpoint {
+            port_id: "transfer".to_string(),
+            channel_id: "channel-0".to_string(),
+        };
+        let mut protocol = Ics20Protocol {
+            common: super::ProtocolCommon {
+                deps: deps.as_mut(),
+                env: mock_env(),
+                info: message_info(&Addr::unchecked("relayer"), &[]),
+                channel: IbcChannel::new(
+                    endpoint.clone(),
+                    endpoint,
+                    cosmwasm_std::IbcOrder::Unordered,
+                    Ics20Protocol::VERSION,
+                    "connection-0",
+                ),
+            },
+        };
+
+        assert!(matches!(
+            protocol.receive_transfer(
+                &"not an address".to_string(),
+                vec![TransferToken {
+                    denom: "transfer/channel-0/denom".into(),
+                    amount: Uint128::from(100u128),
+                    fee: FeePerU128::zero(),
+                }],
+                true,
+            ),
+            Err(ContractError::InvalidReceiver { .. })
+        ));
+    }
+
+    #[test]
+    fn ucs01_receive_transfer_rejects_invalid_receiver() {
+        let mut deps = mock_dependencies();
+        let endpoint = IbcEndpoint {
+            port_id: "ucs01".to_string(),
+            channel_id: "channel-0".to_string(),
+        };
+        let mut protocol = Ucs01Protocol {
+            common: super::ProtocolCommon {
+                deps: deps.as_mut(),
+                env: mock_env(),
+                info: message_info(&Addr::unchecked("relayer"), &[]),
+                channel: IbcChannel::new(
+                    endpoint.clone(),
+                    endpoint,
+                    cosmwasm_std::IbcOrder::Unordered,
+                    Ucs01Protocol::VERSION,
+                    "connection-0",
+                ),
+            },
+        };
+
+        // an empty canonical address can't be humanized
+        assert!(matches!(
+            protocol.receive_transfer(
+                &HexBinary::default(),
+                vec![TransferToken {
+                    denom: "ucs01/channel-0/denom".into(),
+                    amount: Uint128::from(100u128),
+                    fee: FeePerU128::zero(),
+                }],
+                true,
+            ),
+            Err(ContractError::InvalidReceiver { .. })
+        ));
+    }
+
     struct TestOnReceive {
         toggle: bool,
     }
@@ -1335,6 +2134,7 @@ mod tests {
             let mut deps = mock_dependencies();
             let (_, hash, msg) = StatefulOnReceive {
                 deps: deps.as_mut(),
+                block: mock_env().block,
             }
             .foreign_toggle(contract_address, local_endpoint, denom)?;
             Ok((self.toggle, hash, msg))
@@ -1721,6 +2521,167 @@ mod tests {
         assert_eq!(state.total, Uint128::from(119u128 + 129u128));
     }
 
+    #[test]
+    fn send_tokens_failure_refunds_escrow() {
+        let mut deps = mock_dependencies();
+        let endpoint = IbcEndpoint {
+            port_id: "transfer".into(),
+            channel_id: "channel-0".into(),
+        };
+        let tokens = vec![TransferToken {
+            denom: "muno".into(),
+            amount: Uint128::from(100u128),
+            fee: FeePerU128::zero(),
+        }];
+        let outstanding = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+            CHANNEL_STATE
+                .load(deps.as_ref().storage, ("channel-0", "muno"))
+                .unwrap()
+                .outstanding
+        };
+
+        StatefulSendTokens {
+            deps: deps.as_mut(),
+            block: mock_env().block,
+            contract_address: "0xDEADC0DE".into(),
+        }
+        .execute(&Addr::unchecked("0xDEADC0DE"), &endpoint, tokens.clone())
+        .unwrap();
+        assert_eq!(outstanding(&deps), 100u128.into());
+        assert_eq!(
+            CHANNEL_HISTORY
+                .load(deps.as_ref().storage, ("channel-0", 0))
+                .unwrap(),
+            ChannelBalanceChange {
+                denom: "muno".into(),
+                previous: 0u128.into(),
+                outstanding: 100u128.into(),
+                height: mock_env().block.height,
+                time: mock_env().block.time,
+            }
+        );
+
+        // the failure ack refunds the sender and releases the escrow
+        assert_eq!(
+            StatefulRefundTokens {
+                deps: deps.as_mut(),
+                block: mock_env().block,
+                receiver: "sender".into(),
+            }
+            .execute(&Addr::unchecked("0xDEADC0DE"), &endpoint, tokens.clone())
+            .unwrap(),
+            vec![CosmosMsg::from(BankMsg::Send {
+                to_address: "sender".into(),
+                amount: vec![Coin::new(100u128, "muno")],
+            })]
+        );
+        assert_eq!(outstanding(&deps), 0u128.into());
+
+        // refunding again would release more than what was escrowed
+        assert!(matches!(
+            StatefulRefundTokens {
+                deps: deps.as_mut(),
+                block: mock_env().block,
+                receiver: "sender".into(),
+            }
+            .execute(&Addr::unchecked("0xDEADC0DE"), &endpoint, tokens),
+            Err(ContractError::InsufficientEscrow { .. })
+        ));
+        assert_eq!(outstanding(&deps), 0u128.into());
+    }
+
+    #[test]
+    fn refund_failure_restores_escrow() {
+        let mut deps = mock_dependencies();
+        let endpoint = IbcEndpoint {
+            port_id: "transfer".into(),
+            channel_id: "channel-0".into(),
+        };
+        let tokens = vec![
+            TransferToken {
+                denom: "muno".into(),
+                amount: Uint128::from(100u128),
+                fee: FeePerU128::zero(),
+            },
+            TransferToken {
+                denom: "transfer/channel-0/uatom".into(),
+                amount: Uint128::from(5u128),
+                fee: FeePerU128::zero(),
+            },
+        ];
+        let outstanding = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+            CHANNEL_STATE
+                .load(deps.as_ref().storage, ("channel-0", "muno"))
+                .unwrap()
+                .outstanding
+        };
+
+        StatefulSendTokens {
+            deps: deps.as_mut(),
+            block: mock_env().block,
+            contract_address: "0xDEADC0DE".into(),
+        }
+        .execute(&Addr::unchecked("0xDEADC0DE"), &endpoint, tokens.clone())
+        .unwrap();
+        assert_eq!(outstanding(&deps), 100u128.into());
+
+        // each token is refunded in its own submessage
+        let submessages = refund_tokens(
+            deps.as_mut(),
+            &mock_env().block,
+            &Addr::unchecked("0xDEADC0DE"),
+            &endpoint,
+            "sender",
+            tokens,
+        )
+        .unwrap();
+        assert_eq!(submessages.len(), 2);
+        assert!(submessages
+            .iter()
+            .all(|msg| msg.id == REFUND_REPLY_ID && msg.reply_on == ReplyOn::Error));
+        assert_eq!(outstanding(&deps), 0u128.into());
+
+        // a failed refund of escrowed tokens restores the escrow
+        let refund = from_json::<Refund>(&submessages[0].payload).unwrap();
+        assert!(refund.escrowed);
+        let response =
+            refund_failed(deps.as_mut(), &mock_env().block, refund, "error".into()).unwrap();
+        assert_eq!(response.events[0].ty, REFUND_FAILURE_EVENT);
+        assert_eq!(outstanding(&deps), 100u128.into());
+
+        // minted tokens were never escrowed
+        assert!(
+            !from_json::<Refund>(&submessages[1].payload)
+                .unwrap()
+                .escrowed
+        );
+    }
+
+    #[test]
+    fn normalize_rejects_invalid_factory_denom() {
+        for denom in [
+            "factory/0xDEADC0DE/not-base58!",
+            "factory/0xDEADC0DE/3mJr7AoUXx2Wqd",
+        ] {
+            let res = normalize_for_ibc_transfer(
+                |_| Ok(None),
+                "0xDEADC0DE",
+                &IbcEndpoint {
+                    port_id: "transfer".into(),
+                    channel_id: "channel-332".into(),
+                },
+                TransferToken {
+                    denom: denom.into(),
+                    amount: Uint128::MAX,
+                    fee: FeePerU128::zero(),
+                },
+            );
+            assert!(
+                matches!(res, Err(ContractError::InvalidFactoryDenom { denom: d }) if d == denom)
+            );
+        }
+    }
+
     #[test]
     fn normalize_identity() {
         assert_eq!(
@@ -1791,4 +2752,513 @@ mod tests {
             }
         );
     }
+
+    #[allow(deprecated)]
+    fn ibc_send_reply(sequence: u64) -> Reply {
+        Reply {
+            id: IBC_SEND_ID,
+            payload: Binary::default(),
+            gas_used: 0,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+                msg_responses: vec![MsgResponse {
+                    type_url: MsgIbcSendResponse::type_url(),
+                    value: MsgIbcSendResponse { sequence }.encode_to_vec().into(),
+                }],
+            }),
+        }
+    }
+
+    fn ics20_protocol(deps: DepsMut) -> Ics20Protocol<'_> {
+        let endpoint = IbcEndpoint {
+            port_id: "transfer".into(),
+            channel_id: "channel-0".into(),
+        };
+        Ics20Protocol {
+            common: super::ProtocolCommon {
+                deps,
+                env: mock_env(),
+                info: message_info(&Addr::unchecked("relayer"), &[]),
+                channel: IbcChannel::new(
+                    endpoint.clone(),
+                    endpoint,
+                    cosmwasm_std::IbcOrder::Unordered,
+                    Ics20Protocol::VERSION,
+                    "connection-0",
+                ),
+            },
+        }
+    }
+
+    /// Send a transfer of 100 muno from "sender" and handle its send reply, returning the packet
+    /// as it is later acknowledged or timed out.
+    fn send_transfer(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        sequence: u64,
+        fee: PacketFee,
+    ) -> IbcPacket {
+        let input = TransferInput {
+            current_time: mock_env().block.time,
+            timeout_delta: Some(1),
+            clock_skew: 0,
+            timeout_height: None,
+            sender: Addr::unchecked("sender"),
+            receiver: "receiver".into(),
+            tokens: vec![TransferToken {
+                denom: "muno".into(),
+                amount: Uint128::new(100),
+                fee: FeePerU128::zero(),
+            }],
+        };
+        let response = ics20_protocol(deps.as_mut())
+            .send_with_fee(input, String::new(), fee)
+            .unwrap();
+        let Some(CosmosMsg::Ibc(IbcMsg::SendPacket { data, timeout, .. })) =
+            response.messages.last().map(|sub| sub.msg.clone())
+        else {
+            panic!("the packet is sent last");
+        };
+
+        reply(deps.as_mut(), mock_env(), ibc_send_reply(sequence)).unwrap();
+
+        let endpoint = IbcEndpoint {
+            port_id: "transfer".into(),
+            channel_id: "channel-0".into(),
+        };
+        IbcPacket::new(data, endpoint.clone(), endpoint, sequence, timeout)
+    }
+
+    fn ack_success(packet: IbcPacket) -> IbcPacketAckMsg {
+        IbcPacketAckMsg::new(
+            IbcAcknowledgement::new(Ics20Protocol::ack_success().encode()),
+            packet,
+            Addr::unchecked("relayer"),
+        )
+    }
+
+    #[test]
+    fn packet_fee_released_on_ack_and_timeout() {
+        let mut deps = mock_dependencies();
+        let fee = PacketFee {
+            recv_fee: vec![Coin::new(1u128, "muno")],
+            ack_fee: vec![Coin::new(2u128, "muno")],
+            timeout_fee: vec![Coin::new(4u128, "muno")],
+        };
+
+        // identical transfers, only told apart by their sequence
+        let acked = send_transfer(&mut deps, 1, fee.clone());
+        let timed_out = send_transfer(&mut deps, 2, fee);
+        assert_eq!(acked.data, timed_out.data);
+
+        let response = ics20_protocol(deps.as_mut())
+            .send_ack(ack_success(acked.clone()))
+            .unwrap();
+        assert_eq!(
+            response
+                .messages
+                .into_iter()
+                .map(|sub| sub.msg)
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::<TokenFactoryMsg>::from(BankMsg::Send {
+                    to_address: "relayer".into(),
+                    amount: vec![Coin::new(3u128, "muno")],
+                }),
+                CosmosMsg::<TokenFactoryMsg>::from(BankMsg::Send {
+                    to_address: "sender".into(),
+                    amount: vec![Coin::new(4u128, "muno")],
+                }),
+            ]
+        );
+        // The fee is only released once
+        assert_eq!(
+            ics20_protocol(deps.as_mut())
+                .send_ack(ack_success(acked))
+                .unwrap()
+                .messages,
+            vec![]
+        );
+
+        // the refund of the transfer is followed by the fee
+        let response = ics20_protocol(deps.as_mut())
+            .send_timeout(timed_out)
+            .unwrap();
+        assert_eq!(
+            response
+                .messages
+                .into_iter()
+                .skip(1)
+                .map(|sub| sub.msg)
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::<TokenFactoryMsg>::from(BankMsg::Send {
+                    to_address: "relayer".into(),
+                    amount: vec![Coin::new(4u128, "muno")],
+                }),
+                CosmosMsg::<TokenFactoryMsg>::from(BankMsg::Send {
+                    to_address: "sender".into(),
+                    amount: vec![Coin::new(3u128, "muno")],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn pending_packet_cleared_on_ack_or_timeout() {
+        let mut deps = mock_dependencies();
+        let pending_packets = |deps: Deps| {
+            query_pending_packets(deps, "channel-0".into(), None, None)
+                .unwrap()
+                .packets
+                .into_iter()
+                .map(|(_, packet)| packet)
+                .collect::<Vec<_>>()
+        };
+
+        let acked = send_transfer(&mut deps, 1, PacketFee::default());
+        let timed_out = send_transfer(&mut deps, 2, PacketFee::default());
+        let pending = PendingPacket {
+            sender: Addr::unchecked("sender"),
+            receiver: "receiver".into(),
+            tokens: vec![Coin::new(100u128, "muno")],
+            timeout: acked.timeout.clone(),
+            sent_at: mock_env().block.time,
+        };
+        assert_eq!(
+            pending_packets(deps.as_ref()),
+            vec![pending.clone(), pending.clone()]
+        );
+        assert_eq!(
+            query_pending_packets(deps.as_ref(), "channel-1".into(), None, None)
+                .unwrap()
+                .packets,
+            vec![]
+        );
+
+        ics20_protocol(deps.as_mut())
+            .send_ack(ack_success(acked))
+            .unwrap();
+        assert_eq!(pending_packets(deps.as_ref()), vec![pending]);
+
+        ics20_protocol(deps.as_mut())
+            .send_timeout(timed_out)
+            .unwrap();
+        assert_eq!(pending_packets(deps.as_ref()), vec![]);
+    }
+
+    #[test]
+    fn async_ack_written_once() {
+        let mut deps = mock_dependencies();
+        let endpoint = IbcEndpoint {
+            port_id: "transfer".into(),
+            channel_id: "channel-0".into(),
+        };
+        let packet = IbcPacket::new(
+            b"packet".to_vec(),
+            endpoint.clone(),
+            endpoint,
+            1,
+            IbcTimeout::with_timestamp(Timestamp::from_seconds(1)),
+        );
+        let ack = Binary::from(Ics20Protocol::ack_success().encode());
+
+        let mut protocol = ics20_protocol(deps.as_mut());
+        // only deferred acknowledgements can be written asynchronously
+        assert!(matches!(
+            protocol.write_async_ack(&packet, ack.clone()),
+            Err(ContractError::NoDeferredAck)
+        ));
+
+        protocol.record_deferred_ack(&packet).unwrap();
+        assert_eq!(
+            protocol.write_async_ack(&packet, ack.clone()).unwrap(),
+            CosmosMsg::Ibc(IbcMsg::WriteAcknowledgement {
+                channel_id: "channel-0".into(),
+                packet_sequence: 1,
+                ack: IbcAcknowledgement::new(ack.clone()),
+            })
+        );
+        assert!(matches!(
+            protocol.write_async_ack(&packet, ack.clone()),
+            Err(ContractError::NoDeferredAck)
+        ));
+
+        // a reverted receive writes the failure acknowledgement through its reply instead
+        protocol.record_deferred_ack(&packet).unwrap();
+        let response = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: Ics20Protocol::RECEIVE_REPLY_ID,
+                payload: to_json_binary(&packet).unwrap(),
+                gas_used: 0,
+                result: SubMsgResult::Err("out of gas".into()),
+            },
+        )
+        .unwrap();
+        assert!(response.data.is_some());
+        assert!(matches!(
+            ics20_protocol(deps.as_mut()).write_async_ack(&packet, ack),
+            Err(ContractError::NoDeferredAck)
+        ));
+    }
+
+    #[test]
+    fn receive_replies_on_success_if_configured() {
+        let mut deps = mock_dependencies();
+        let receiver = deps.api.addr_make("receiver");
+        let endpoint = IbcEndpoint {
+            port_id: "transfer".into(),
+            channel_id: "channel-0".into(),
+        };
+        let packet = IbcPacket::new(
+            serde_json_wasm::to_vec(&Ics20Packet {
+                denom: "uatom".into(),
+                amount: Uint128::new(100),
+                sender: "sender".into(),
+                receiver: receiver.to_string(),
+                memo: String::new(),
+            })
+            .unwrap(),
+            endpoint.clone(),
+            endpoint,
+            1,
+            IbcTimeout::with_timestamp(Timestamp::from_seconds(1)),
+        );
+        let receive = |deps: DepsMut, reply_on_receive_success| {
+            CONFIG
+                .save(
+                    deps.storage,
+                    &Config {
+                        default_timeout: 100,
+                        ibc_host: Addr::unchecked("ibc_host"),
+                        max_timeout: None,
+                        paused: false,
+                        reply_on_receive_success,
+                    },
+                )
+                .unwrap();
+            ics20_protocol(deps).receive(packet.clone())
+        };
+
+        let response = receive(deps.as_mut(), false);
+        assert!(response.acknowledgement.is_some());
+        assert_eq!(response.messages[0].reply_on, ReplyOn::Error);
+
+        let response = receive(deps.as_mut(), true);
+        assert!(response.acknowledgement.is_some());
+        assert_eq!(response.messages[0].reply_on, ReplyOn::Always);
+
+        // the success reply doesn't overwrite the acknowledgement written by the receive
+        #[allow(deprecated)]
+        let response = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: Ics20Protocol::RECEIVE_REPLY_ID,
+                payload: response.messages[0].payload.clone(),
+                gas_used: 0,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                    msg_responses: vec![],
+                }),
+            },
+        )
+        .unwrap();
+        assert_eq!(response.data, None);
+        assert_eq!(response.events[0].ty, "receive_success");
+    }
+
+    fn ics20_in_flight_packet(denom: &str) -> InFlightPfmPacket {
+        let origin_packet = IbcPacket::new(
+            serde_json_wasm::to_vec(&Ics20Packet {
+                denom: denom.into(),
+                amount: Uint128::new(100),
+                sender: "sender".into(),
+                receiver: "receiver".into(),
+                memo: String::new(),
+            })
+            .unwrap(),
+            IbcEndpoint {
+                port_id: "transfer".into(),
+                channel_id: "channel-1".into(),
+            },
+            IbcEndpoint {
+                port_id: "wasm.0xDEADC0DE".into(),
+                channel_id: "channel-0".into(),
+            },
+            1,
+            IbcTimeout::with_timestamp(Timestamp::from_seconds(1)),
+        );
+        InFlightPfmPacket {
+            origin_sender_addr: Addr::unchecked("relayer"),
+            origin_protocol_version: Ics20Protocol::VERSION.into(),
+            forward_packet: origin_packet.clone(),
+            origin_packet,
+        }
+    }
+
+    #[test]
+    fn migrate_backfills_deferred_acks_of_in_flight_packets() {
+        let mut deps = mock_dependencies();
+        let in_flight_packet = ics20_in_flight_packet("uatom");
+        let ack = Binary::from(Ics20Protocol::ack_success().encode());
+
+        // packets forwarded before the upgrade have no deferred acknowledgement recorded
+        IN_FLIGHT_PFM_PACKETS
+            .save(
+                deps.as_mut().storage,
+                packet_key(&in_flight_packet.forward_packet),
+                &in_flight_packet,
+            )
+            .unwrap();
+        assert!(matches!(
+            ics20_protocol(deps.as_mut())
+                .write_async_ack(&in_flight_packet.origin_packet, ack.clone()),
+            Err(ContractError::NoDeferredAck)
+        ));
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        ics20_protocol(deps.as_mut())
+            .write_async_ack(&in_flight_packet.origin_packet, ack)
+            .unwrap();
+    }
+
+    #[test]
+    fn revert_pfm_receive_burns_vouchers() {
+        let mut deps = mock_dependencies();
+        assert_eq!(
+            revert_pfm_receive(
+                deps.as_mut(),
+                &mock_env().block,
+                &Addr::unchecked("0xDEADC0DE"),
+                &ics20_in_flight_packet("uatom"),
+            )
+            .unwrap(),
+            vec![CosmosMsg::from(TokenFactoryMsg::BurnTokens {
+                denom: make_factory_denom(
+                    &Addr::unchecked("0xDEADC0DE"),
+                    &encode_denom_hash(hash_denom("wasm.0xDEADC0DE/channel-0/uatom"))
+                ),
+                amount: Uint128::new(100),
+                burn_from_address: "0xDEADC0DE".into(),
+            })]
+        );
+    }
+
+    #[test]
+    fn revert_pfm_receive_escrows_local() {
+        let mut deps = mock_dependencies();
+        assert!(revert_pfm_receive(
+            deps.as_mut(),
+            &mock_env().block,
+            &Addr::unchecked("0xDEADC0DE"),
+            &ics20_in_flight_packet("transfer/channel-1/muno"),
+        )
+        .unwrap()
+        .is_empty());
+        assert_eq!(
+            CHANNEL_STATE
+                .load(deps.as_ref().storage, ("channel-0", "muno"))
+                .unwrap()
+                .outstanding,
+            100u128.into()
+        );
+    }
+
+    #[test]
+    fn rate_limit_rolls_over_windows() {
+        let mut deps = mock_dependencies();
+        let start = Timestamp::from_seconds(100);
+        RATE_LIMITS
+            .save(
+                deps.as_mut().storage,
+                ("channel-0", "muno"),
+                &RateLimitState {
+                    limit: RateLimit {
+                        amount: Uint128::new(100),
+                        window: 10,
+                    },
+                    window_start: start,
+                    inflow: Uint128::zero(),
+                    outflow: Uint128::zero(),
+                    previous_inflow: Uint128::zero(),
+                    previous_outflow: Uint128::zero(),
+                },
+            )
+            .unwrap();
+        fn consume(
+            deps: DepsMut,
+            now: Timestamp,
+            amount: u128,
+            flow: Flow,
+        ) -> Result<(), ContractError> {
+            consume_rate_limit(deps, now, "channel-0", "muno", Uint128::new(amount), flow)
+        }
+
+        consume(deps.as_mut(), start, 60, Flow::Outflow).unwrap();
+        // Inflow and outflow are accounted separately
+        consume(deps.as_mut(), start, 100, Flow::Inflow).unwrap();
+        assert!(matches!(
+            consume(deps.as_mut(), start.plus_seconds(9), 41, Flow::Outflow),
+            Err(ContractError::RateLimitExceeded { .. })
+        ));
+        consume(deps.as_mut(), start.plus_seconds(9), 40, Flow::Outflow).unwrap();
+        // The previous window is entirely covered by the rolling window at its end
+        assert!(matches!(
+            consume(deps.as_mut(), start.plus_seconds(10), 1, Flow::Outflow),
+            Err(ContractError::RateLimitExceeded { .. })
+        ));
+        // Half of the previous window usage is left in the rolling window
+        assert!(matches!(
+            consume(deps.as_mut(), start.plus_seconds(15), 51, Flow::Outflow),
+            Err(ContractError::RateLimitExceeded { .. })
+        ));
+        consume(deps.as_mut(), start.plus_seconds(15), 50, Flow::Outflow).unwrap();
+        // Only the usage of the window preceding the current one is carried over
+        assert!(matches!(
+            consume(deps.as_mut(), start.plus_seconds(25), 76, Flow::Outflow),
+            Err(ContractError::RateLimitExceeded { .. })
+        ));
+        consume(deps.as_mut(), start.plus_seconds(25), 75, Flow::Outflow).unwrap();
+        // Nothing is left once a whole window elapsed without transfers
+        consume(deps.as_mut(), start.plus_seconds(50), 100, Flow::Outflow).unwrap();
+        // Denoms without a rate limit are not limited
+        consume_rate_limit(
+            deps.as_mut(),
+            start,
+            "channel-0",
+            "uatom",
+            Uint128::MAX,
+            Flow::Outflow,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reply_ids_do_not_overlap() {
+        assert!(reply_ids_unique(&REPLY_IDS));
+        assert!(!reply_ids_unique(&[(1, "a"), (2, "b"), (1, "c")]));
+        assert_eq!(
+            reply_id_handler(Ics20Protocol::RECEIVE_REPLY_ID),
+            Some(ReplyHandler::Ics20Receive)
+        );
+        assert_eq!(reply_id_handler(u64::MAX), None);
+        // replies are dispatched through the registry, unregistered ids are rejected
+        assert!(matches!(
+            reply(
+                mock_dependencies().as_mut(),
+                mock_env(),
+                Reply {
+                    id: u64::MAX,
+                    payload: Binary::default(),
+                    gas_used: 0,
+                    result: SubMsgResult::Err("error".into()),
+                },
+            ),
+            Err(ContractError::UnknownReply { id: u64::MAX, .. })
+        ));
+    }
 }
@@ -1,23 +1,67 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, IbcEndpoint, Uint512};
+use cosmwasm_std::{
+    Addr, Binary, Coin, Empty, IbcEndpoint, IbcTimeout, Timestamp, Uint128, Uint512,
+};
 use cw_controllers::Admin;
 use cw_storage_plus::{Item, KeyDeserialize, Map, Prefixer, PrimaryKey};
 use serde::{Deserialize, Serialize};
-use ucs01_relay_api::middleware::InFlightPfmPacket;
+use ucs01_relay_api::{middleware::InFlightPfmPacket, types::PacketFee};
 use unionlabs::primitives::H256;
 
+use crate::msg::{DenomFilter, RateLimit};
+
 pub const ADMIN: Admin = Admin::new("admin");
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// Denoms that can be sent, all denoms are allowed if not set
+pub const DENOM_FILTER: Item<DenomFilter> = Item::new("denom_filter");
+
 /// indexed by (channel_id, denom) maintaining the balance of the channel in that currency
 pub const CHANNEL_STATE: Map<(&str, &str), ChannelState> = Map::new("channel_state");
 
+/// indexed by (channel_id, index), an append-only log of the changes to the balances of the channel
+pub const CHANNEL_HISTORY: Map<(&str, u64), ChannelBalanceChange> = Map::new("channel_history");
+
+/// indexed by channel_id, the index of the next entry of the channel in `CHANNEL_HISTORY`
+pub const NEXT_CHANNEL_HISTORY_INDEX: Map<&str, u64> = Map::new("next_channel_history_index");
+
+/// indexed by channel_id, channels closed by the admin
+pub const CLOSED_CHANNELS: Map<&str, Empty> = Map::new("closed_channels");
+
+/// indexed by channel_id, channels on which transfers are paused, in addition to the global pause
+pub const PAUSED_CHANNELS: Map<&str, Empty> = Map::new("paused_channels");
+
 /// In flight PFM packets, stored for refund information.
 /// Indexed by `PfmRefundPacketKey` (channel_id, port_id, sequence).
 pub const IN_FLIGHT_PFM_PACKETS: Map<PfmRefundPacketKey, InFlightPfmPacket> =
     Map::new("in_flight_pfm_packets");
 
+/// Relayer fees escrowed for packets in flight, indexed by `PacketFeeKey`.
+pub const PACKET_FEES: Map<PacketFeeKey, EscrowedPacketFee> = Map::new("packet_fees");
+
+/// indexed by (channel_id, packet key), packets sent on the channel that were neither acknowledged
+/// nor timed out yet. The packet key is the one of `PACKET_FEES`.
+pub const PENDING_PACKETS: Map<(&str, &[u8]), PendingPacket> = Map::new("pending_packets");
+
+/// The fee and pending record of the packet being sent by the current transfer. The sequence of a
+/// packet is not known until it is sent, they are moved to `PACKET_FEES` and `PENDING_PACKETS` once
+/// the sequence is read from the send reply.
+pub const STAGED_SEND: Item<StagedSend> = Item::new("staged_send");
+
+/// indexed by packet key, received packets whose acknowledgement is deferred and not written yet
+pub const DEFERRED_ACKS: Map<PfmRefundPacketKey, Empty> = Map::new("deferred_acks");
+
+/// indexed by local denom, the minimum amount of the denom that can be transferred
+pub const MIN_TRANSFER_AMOUNTS: Map<&str, Uint128> = Map::new("min_transfer_amounts");
+
+/// indexed by (channel_id, denom) maintaining the rate limit of the channel in that currency
+pub const RATE_LIMITS: Map<(&str, &str), RateLimitState> = Map::new("rate_limits");
+
+/// indexed by channel_id, the number of seconds the counterparty clock is ahead of ours (negative
+/// if behind), applied to the timeout timestamp of outgoing packets
+pub const CLOCK_SKEWS: Map<&str, i64> = Map::new("clock_skews");
+
 pub const MAX_SUBDENOM_LENGTH: usize = 44;
 
 /// Used for indexing in flight packets for refunds and acknowledgements.
@@ -50,6 +94,35 @@ impl<'a> PrimaryKey<'a> for PfmRefundPacketKey {
     }
 }
 
+/// Used for indexing escrowed packet fees and pending packets.
+///
+/// The key commits to the packet sequence, such that identical transfers sent on the same channel
+/// are tracked independently. Packets sent through the union IBC stack don't have a sequence and
+/// are instead unique by content.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(transparent)]
+pub struct PacketFeeKey(pub(crate) H256);
+
+impl From<H256> for PacketFeeKey {
+    fn from(value: H256) -> Self {
+        Self(value)
+    }
+}
+
+impl<'a> PrimaryKey<'a> for PacketFeeKey {
+    type Prefix = <[u8; 32] as PrimaryKey<'a>>::Prefix;
+
+    type SubPrefix = <[u8; 32] as PrimaryKey<'a>>::SubPrefix;
+
+    type Suffix = <[u8; 32] as PrimaryKey<'a>>::Suffix;
+
+    type SuperSuffix = <[u8; 32] as PrimaryKey<'a>>::SuperSuffix;
+
+    fn key(&self) -> Vec<cw_storage_plus::Key> {
+        self.0.get().key()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IbcEndpointKey(IbcEndpoint);
 
@@ -132,8 +205,70 @@ pub struct ChannelState {
     pub outstanding: Uint512,
 }
 
+#[cw_serde]
+pub struct ChannelBalanceChange {
+    pub denom: String,
+    /// The outstanding balance of the denom on the channel before the change
+    pub previous: Uint512,
+    /// The outstanding balance of the denom on the channel after the change
+    pub outstanding: Uint512,
+    /// The height of the block at which the change happened
+    pub height: u64,
+    /// The time of the block at which the change happened
+    pub time: Timestamp,
+}
+
+#[cw_serde]
+pub struct RateLimitState {
+    pub limit: RateLimit,
+    /// Start of the current fixed window, the windows are aligned on the time the limit was set
+    pub window_start: Timestamp,
+    pub inflow: Uint128,
+    pub outflow: Uint128,
+    /// Usage of the fixed window preceding the current one
+    #[serde(default)]
+    pub previous_inflow: Uint128,
+    #[serde(default)]
+    pub previous_outflow: Uint128,
+}
+
+#[cw_serde]
+pub struct EscrowedPacketFee {
+    pub payer: Addr,
+    pub fee: PacketFee,
+}
+
+#[cw_serde]
+pub struct PendingPacket {
+    pub sender: Addr,
+    pub receiver: String,
+    /// The tokens sent, fees included, in local denoms
+    pub tokens: Vec<Coin>,
+    pub timeout: IbcTimeout,
+    /// The time at which the packet was sent
+    pub sent_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct StagedSend {
+    pub channel_id: String,
+    pub data: Binary,
+    pub timeout: IbcTimeout,
+    pub fee: Option<EscrowedPacketFee>,
+    pub packet: Option<PendingPacket>,
+}
+
 #[cw_serde]
 pub struct Config {
     pub default_timeout: u64,
     pub ibc_host: Addr,
+    /// Maximum timeout, in seconds, a transfer can request
+    #[serde(default)]
+    pub max_timeout: Option<u64>,
+    /// When set, transfers are rejected in both directions
+    #[serde(default)]
+    pub paused: bool,
+    /// When set, the submessages of received transfers also reply on success
+    #[serde(default)]
+    pub reply_on_receive_success: bool,
 }
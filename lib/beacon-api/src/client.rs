@@ -118,7 +118,17 @@ impl BeaconApiClient {
         Ok(height)
     }
 
-    pub async fn bootstrap_for_slot(&self, slot: Slot) -> Result<Response<LightClientBootstrap>> {
+    /// Fetch the light client bootstrap for the sync committee period containing `slot`.
+    ///
+    /// The slot is floored to the start of its sync committee period, and then walked backwards
+    /// until a slot is found that both has a block and a bootstrap available. At most
+    /// `max_slots_back` slots will be tried before returning [`Error::BootstrapNotFound`];
+    /// defaults to one sync committee period if not set.
+    pub async fn bootstrap_for_slot(
+        &self,
+        slot: Slot,
+        max_slots_back: Option<u64>,
+    ) -> Result<Response<LightClientBootstrap>> {
         // NOTE(benluelo): While this is technically two actions, I consider it to be one
         // action - if the beacon chain doesn't have the header, it won't have the bootstrap
         // either. It would be nice if the beacon chain exposed "fetch bootstrap by slot"
@@ -128,17 +138,32 @@ impl BeaconApiClient {
 
         let spec = self.spec().await?.data;
 
+        let slots_per_sync_committee_period =
+            spec.slots_per_epoch.get() * spec.epochs_per_sync_committee_period;
+
         let floored_slot = Slot::new(
-            slot.get() / (spec.slots_per_epoch.get() * spec.epochs_per_sync_committee_period)
-                * (spec.slots_per_epoch.get() * spec.epochs_per_sync_committee_period),
+            slot.get() / slots_per_sync_committee_period * slots_per_sync_committee_period,
         );
 
+        // can't look back further than genesis
+        let max_slots_back = max_slots_back
+            .unwrap_or(slots_per_sync_committee_period)
+            .min(floored_slot.get());
+
         info!("fetching bootstrap at {}", floored_slot);
 
         loop {
-            let header_response = self
-                .header(BlockId::Slot(floored_slot - amount_of_slots_back))
-                .await;
+            if amount_of_slots_back.get() > max_slots_back {
+                return Err(Error::BootstrapNotFound {
+                    slot: floored_slot.get(),
+                    last_tried_slot: floored_slot.get() - max_slots_back,
+                    max_slots_back,
+                });
+            }
+
+            let current_slot = floored_slot - amount_of_slots_back;
+
+            let header_response = self.header(BlockId::Slot(current_slot)).await;
 
             let header = match header_response {
                 Ok(header) => header,
@@ -147,6 +172,11 @@ impl BeaconApiClient {
                     error: _,
                     message,
                 })) if message.starts_with("No block found for id") => {
+                    debug!(
+                        %current_slot,
+                        %amount_of_slots_back,
+                        "no block found at slot, trying previous slot"
+                    );
                     amount_of_slots_back = Slot::new(amount_of_slots_back.get() + 1);
                     continue;
                 }
@@ -164,6 +194,11 @@ impl BeaconApiClient {
                         error: _,
                         message,
                     }) if message.starts_with("syncCommitteeWitness not available") => {
+                        debug!(
+                            %current_slot,
+                            %amount_of_slots_back,
+                            "sync committee witness not available at slot, trying previous slot"
+                        );
                         amount_of_slots_back = Slot::new(amount_of_slots_back.get() + 1);
                     }
                     _ => return Err(err),
@@ -14,6 +14,15 @@ pub enum Error {
     Json(#[from] serde_json::Error),
     #[error("unknown error ({code}): {text}")]
     Other { code: StatusCode, text: String },
+    #[error(
+        "no bootstrap found for slot {slot} after looking back {max_slots_back} slots \
+        (last tried slot {last_tried_slot})"
+    )]
+    BootstrapNotFound {
+        slot: u64,
+        last_tried_slot: u64,
+        max_slots_back: u64,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, thiserror::Error)]
@@ -578,6 +578,24 @@ pub mod cosmos_sdk_error {
                         std::convert::identity,
                     )
                 }
+
+                /// Parse an error from the message of a failed query or simulation, which only
+                /// contains the (possibly wrapped) error message and not the code and codespace.
+                /// Wrapped errors are of the form `context: context: error`, so the message is
+                /// searched from the end.
+                pub fn parse_from_error_message(message: &str) -> Option<Self> {
+                    message.rsplit(": ").find_map(|segment| {
+                        $(
+                            $(
+                                if segment.starts_with($msg) {
+                                    return Some($Module::$Err.into());
+                                }
+                            )+
+                        )+
+
+                        None
+                    })
+                }
             }
 
             $(
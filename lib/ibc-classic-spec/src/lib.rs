@@ -15,7 +15,8 @@ use unionlabs::{
             msg_recv_packet::MsgRecvPacket, msg_timeout::MsgTimeout, order::Order,
         },
         client::{
-            height::Height, msg_create_client::MsgCreateClient, msg_update_client::MsgUpdateClient,
+            height::Height, msg_create_client::MsgCreateClient,
+            msg_submit_misbehaviour::MsgSubmitMisbehaviour, msg_update_client::MsgUpdateClient,
         },
         connection::{
             connection_end::ConnectionEnd, msg_connection_open_ack::MsgConnectionOpenAck,
@@ -298,6 +299,7 @@ pub enum PathParseError {
 pub enum Datagram {
     CreateClient(MsgCreateClientData),
     UpdateClient(MsgUpdateClient),
+    SubmitMisbehaviour(MsgSubmitMisbehaviour),
 
     ConnectionOpenInit(MsgConnectionOpenInit),
     ConnectionOpenTry(MsgConnectionOpenTry),
@@ -321,6 +323,7 @@ impl Datagram {
         match self {
             Datagram::CreateClient(_) => None,
             Datagram::UpdateClient(_) => None,
+            Datagram::SubmitMisbehaviour(_) => None,
             Datagram::ConnectionOpenInit(_) => None,
             Datagram::ConnectionOpenTry(msg) => Some(msg.proof_height),
             Datagram::ConnectionOpenAck(msg) => Some(msg.proof_height),
@@ -339,6 +342,7 @@ impl Datagram {
         match self {
             Datagram::CreateClient(_) => "create_client",
             Datagram::UpdateClient(_) => "update_client",
+            Datagram::SubmitMisbehaviour(_) => "submit_misbehaviour",
             Datagram::ConnectionOpenInit(_) => "connection_open_init",
             Datagram::ConnectionOpenTry(_) => "connection_open_try",
             Datagram::ConnectionOpenAck(_) => "connection_open_ack",
@@ -368,6 +372,17 @@ pub struct UpdateClient {
     pub consensus_heights: Vec<Height>,
 }
 
+/// Emitted when misbehaviour is submitted for a client, freezing it.
+///
+/// This only records that the client was frozen, the misbehaviour itself is not included. As
+/// such, it can't be used to freeze other clients tracking the same chain.
+#[model]
+pub struct ClientMisbehaviour {
+    pub client_id: ClientId,
+    pub client_type: ClientType,
+    pub consensus_height: Height,
+}
+
 #[model]
 pub struct ConnectionOpenInit {
     pub connection_id: ConnectionId,
@@ -517,6 +532,7 @@ pub struct ConnectionMetadata {
 pub enum FullEvent {
     CreateClient(CreateClient),
     UpdateClient(UpdateClient),
+    ClientMisbehaviour(ClientMisbehaviour),
 
     ConnectionOpenInit(ConnectionOpenInit),
     ConnectionOpenTry(ConnectionOpenTry),
@@ -540,6 +556,7 @@ impl FullEvent {
         match self {
             Self::CreateClient(ref event) => &event.client_id,
             Self::UpdateClient(ref event) => &event.client_id,
+            Self::ClientMisbehaviour(ref event) => &event.client_id,
             Self::ConnectionOpenInit(ref event) => &event.client_id,
             Self::ConnectionOpenTry(ref event) => &event.client_id,
             Self::ConnectionOpenAck(ref event) => &event.client_id,
@@ -559,8 +576,8 @@ impl FullEvent {
     }
 
     /// Returns the counterparty client id of this ibc event, if there is a
-    /// counterparty. This will return `None` for `UpdateClient` and
-    /// `CreateClient`.
+    /// counterparty. This will return `None` for `UpdateClient`,
+    /// `ClientMisbehaviour` and `CreateClient`.
     pub fn counterparty_client_id(&self) -> Option<&ClientId> {
         match self {
             Self::ConnectionOpenInit(ref event) => Some(&event.counterparty_client_id),
@@ -771,6 +788,12 @@ pub fn log_msg(chain_id: &str, effect: &Datagram) {
                 %message.client_id,
             )
         }
+        Datagram::SubmitMisbehaviour(message) => {
+            info!(
+                %chain_id,
+                %message.client_id,
+            )
+        }
     }
 }
 
@@ -29,7 +29,7 @@ maybe_sol_attr! {
             mapping(bytes32 => bytes32) public commitments;
 
             /// ClientType -> Address
-            mapping(bytes32 => address) public clientRegistry;
+            mapping(string => address) public clientRegistry;
             /// ClientId -> ClientType
             mapping(uint32 => string) public clientTypes;
             /// ClientId -> Address
@@ -41,7 +41,7 @@ maybe_sol_attr! {
             /// ChannelId -> PortId
             mapping(uint32 => address) public channelOwner;
 
-            function registerClient(bytes32 clientType, address client) external;
+            function registerClient(string calldata clientType, address client) external;
 
             function createClient(
                 MsgCreateClient calldata msg_
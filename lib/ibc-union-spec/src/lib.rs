@@ -174,6 +174,12 @@ impl IbcStorePathKey for ChannelPath {
     type Value = Option<ibc_solidity::Channel>;
 }
 
+/// The path of the receipt (acknowledgement commitment) of a batch of packets on the
+/// destination chain.
+///
+/// The receipt of a single packet is stored under the batch hash of that packet alone. A proof
+/// that this path is *not* set is what is required to time out a packet (see
+/// [`MsgPacketTimeout`]).
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BatchReceiptsPath {
     pub channel_id: u32,
@@ -265,7 +271,7 @@ impl Datagram {
             Self::ChannelCloseConfirm(_msg) => todo!(),
             Self::PacketRecv(msg) => Some(Height::new(msg.proof_height)),
             Self::PacketAcknowledgement(msg) => Some(Height::new(msg.proof_height)),
-            Self::PacketTimeout(_msg) => todo!(),
+            Self::PacketTimeout(msg) => Some(Height::new(msg.proof_height)),
             Self::IntentPacketRecv(_msg) => todo!(),
             Self::BatchSend(_msg) => todo!(),
             Self::BatchAcks(_msg) => todo!(),
@@ -395,7 +401,13 @@ pub struct MsgPacketAcknowledgement {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct MsgPacketTimeout {}
+pub struct MsgPacketTimeout {
+    pub packet: Packet,
+    /// Proof of the absence of the packet receipt on the destination chain, i.e. a
+    /// non-membership proof of the [`BatchReceiptsPath`] for this packet.
+    pub proof: Bytes,
+    pub proof_height: u64,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MsgIntentPacketRecv {}
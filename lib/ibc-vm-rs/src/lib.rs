@@ -47,6 +47,9 @@ pub enum IbcError {
     #[error("connection ({0}) not found")]
     ConnectionNotFound(String),
 
+    #[error("channels must have exactly one connection hop, found {0}")]
+    InvalidConnectionHops(usize),
+
     #[error("connection state is {0} while {1} is expected")]
     IncorrectConnectionState(connection::state::State, connection::state::State),
 
@@ -63,6 +63,10 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenInit {
                 },
                 &[IbcResponse::Empty],
             ) => {
+                if connection_hops.len() != 1 {
+                    return Err(IbcError::InvalidConnectionHops(connection_hops.len()).into());
+                }
+
                 let connection: ConnectionEnd = host
                     .read(
                         &ConnectionPath {
@@ -255,6 +259,10 @@ impl<T: IbcHost> Runnable<T> for ChannelOpenTry {
                 },
                 &[IbcResponse::Empty],
             ) => {
+                if connection_hops.len() != 1 {
+                    return Err(IbcError::InvalidConnectionHops(connection_hops.len()).into());
+                }
+
                 let connection: ConnectionEnd = host
                     .read(
                         &ConnectionPath {
@@ -1,15 +1,20 @@
 use std::{
-    borrow::Borrow, cmp::Eq, collections::HashMap, future::Future, hash::Hash, marker::PhantomData,
+    borrow::Borrow,
+    cmp::Eq,
+    collections::{HashMap, HashSet},
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
     time::Duration,
 };
 
 use frame_support_procedural::{CloneNoBound, DebugNoBound};
-use futures_util::TryStreamExt;
+use futures_util::{StreamExt, TryStreamExt};
 use itertools::Itertools;
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, prelude::FromRow, types::Json, Either, Executor, PgPool};
-use tracing::{debug, debug_span, info_span, instrument, trace, Instrument};
+use tracing::{debug, debug_span, info_span, instrument, trace, warn, Instrument};
 use voyager_vm::{
     filter::{FilterResult, InterestFilter},
     pass::{Pass, PassResult},
@@ -28,11 +33,20 @@ pub mod metrics;
 /// id SERIAL AUTO INCREMENT
 /// status 0..2
 /// item JSONB
+/// item_cbor BYTEA
+/// item_hash BYTEA
 /// error TEXT
 /// ```
+///
+/// Exactly one of `item` and `item_cbor` is set for each row, depending on the [`PayloadFormat`]
+/// the row was written with. Rows are always readable regardless of the currently configured
+/// format, so the format can be changed without migrating existing rows.
 #[derive(DebugNoBound, CloneNoBound)]
 pub struct PgQueue<T> {
     client: PgPool,
+    payload_format: PayloadFormat,
+    deduplicate: bool,
+    compact_aggregations_interval: Option<Duration>,
     __marker: PhantomData<fn() -> T>,
 }
 
@@ -44,17 +58,88 @@ pub struct PgQueueConfig {
     pub min_connections: Option<u32>,
     pub idle_timeout: Option<Duration>,
     pub max_lifetime: Option<Duration>,
+    /// The format to store new items in. Defaults to [`PayloadFormat::Json`].
+    #[serde(default)]
+    pub payload_format: PayloadFormat,
+    /// Skip inserting items that are identical to an item that is already pending, including the
+    /// items produced by processing and optimizing other items. Items are compared by the hash of
+    /// their stored payload.
+    #[serde(default)]
+    pub deduplicate: bool,
+    /// How many times to retry connecting to the database before giving up. Useful when the
+    /// database may not be ready yet on startup. Defaults to no retries.
+    #[serde(default)]
+    pub connect_retries: u32,
+    /// The delay before the first connection retry. The delay doubles after every failed attempt.
+    /// Defaults to 1 second.
+    #[serde(default)]
+    pub connect_backoff: Option<Duration>,
+    /// If set, periodically run [`PgQueue::compact_aggregations`] at this interval.
+    #[serde(default)]
+    pub compact_aggregations_interval: Option<Duration>,
+}
+
+/// The serialization format used to store items in the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadFormat {
+    /// Store items as `JSONB` in the `item` column. This allows for inspecting and filtering
+    /// items directly in the database.
+    #[default]
+    Json,
+    /// Store items as [CBOR](https://cbor.io) in the `item_cbor` column. This is significantly
+    /// more compact than JSON, at the cost of not being human-readable.
+    Cbor,
+}
+
+impl PayloadFormat {
+    /// Encode `item` into the `(item, item_cbor)` column pair.
+    fn encode<T: Serialize>(self, item: T) -> (Option<Json<T>>, Option<Vec<u8>>) {
+        match self {
+            PayloadFormat::Json => (Some(Json(item)), None),
+            PayloadFormat::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(&item, &mut bytes)
+                    .expect("serialization is infallible; qed;");
+                (None, Some(bytes))
+            }
+        }
+    }
 }
 
 impl PgQueueConfig {
+    /// Connect to the database, retrying up to [`Self::connect_retries`] times with exponential
+    /// backoff.
     pub async fn into_pg_pool(self) -> sqlx::Result<PgPool> {
-        PgPoolOptions::new()
-            .max_connections(self.max_connections.unwrap_or(10))
-            .min_connections(self.min_connections.unwrap_or(0))
-            .idle_timeout(self.idle_timeout)
-            .max_lifetime(self.max_lifetime)
-            .connect(&self.database_url)
-            .await
+        let mut backoff = self.connect_backoff.unwrap_or(Duration::from_secs(1));
+        let mut attempt = 0;
+
+        loop {
+            match PgPoolOptions::new()
+                .max_connections(self.max_connections.unwrap_or(10))
+                .min_connections(self.min_connections.unwrap_or(0))
+                .idle_timeout(self.idle_timeout)
+                .max_lifetime(self.max_lifetime)
+                .connect(&self.database_url)
+                .await
+            {
+                Ok(pool) => return Ok(pool),
+                Err(err) if attempt < self.connect_retries => {
+                    attempt += 1;
+
+                    warn!(
+                        %err,
+                        attempt,
+                        max_attempts = self.connect_retries,
+                        "unable to connect to the database, retrying in {backoff:?}"
+                    );
+
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                Err(err) => return Err(err),
+            }
+        }
     }
 }
 
@@ -67,11 +152,63 @@ struct Id {
 struct Record {
     id: i64,
     parents: Vec<i64>,
-    item: String,
+    correlation_id: Option<i64>,
+    item: Option<String>,
+    item_cbor: Option<Vec<u8>>,
     created_at: sqlx::types::time::OffsetDateTime,
 }
 
-#[derive(Debug, FromRow, Serialize)]
+impl Record {
+    /// The id of the top-level item this item descends from. Top-level items are their own
+    /// correlation id.
+    fn correlation_id(&self) -> i64 {
+        self.correlation_id.unwrap_or(self.id)
+    }
+
+    fn decode<T: DeserializeOwned>(&self) -> Result<T, sqlx::Error> {
+        decode_item(self.item.as_deref(), self.item_cbor.as_deref())
+    }
+
+    /// Insert this record into the `poison` table as part of `tx`, along with the error that
+    /// occurred while decoding it. The record must already have been removed from its table.
+    async fn poison_in(
+        &self,
+        tx: &mut sqlx::Transaction<'static, sqlx::Postgres>,
+        error: &sqlx::Error,
+    ) -> Result<(), sqlx::Error> {
+        warn!(id = self.id, %error, "unable to decode item, moving it to the poison table");
+
+        sqlx::query(
+            r#"
+            INSERT INTO
+            poison (id, parents, correlation_id, item,      item_cbor, created_at, message)
+            VALUES ($1, $2,      $3,             $4::JSONB, $5,        $6,         $7     )
+            "#,
+        )
+        .bind(self.id)
+        .bind(&self.parents)
+        .bind(self.correlation_id)
+        .bind(&self.item)
+        .bind(&self.item_cbor)
+        .bind(self.created_at)
+        .bind(error.to_string())
+        .execute(tx.as_mut())
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct FailedRow {
+    id: i64,
+    parents: Vec<i64>,
+    item: Option<String>,
+    item_cbor: Option<Vec<u8>>,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
 #[serde(bound(serialize = ""))]
 pub struct FailedRecord<T: QueueMessage> {
     pub id: i64,
@@ -81,7 +218,40 @@ pub struct FailedRecord<T: QueueMessage> {
     // pub created_at: sqlx::types::time::OffsetDateTime,
 }
 
+#[derive(Debug, FromRow)]
+struct QueueRow {
+    id: i64,
+    parents: Vec<i64>,
+    item: Option<String>,
+    item_cbor: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(bound(serialize = ""))]
+pub struct AggregationRecord<T: QueueMessage> {
+    pub id: i64,
+    pub parents: Vec<i64>,
+    pub item: Json<Op<T>>,
+}
+
+impl<T: QueueMessage> TryFrom<FailedRow> for FailedRecord<T> {
+    type Error = sqlx::Error;
+
+    fn try_from(row: FailedRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            item: Json(decode_item(row.item.as_deref(), row.item_cbor.as_deref())?),
+            id: row.id,
+            parents: row.parents,
+            message: row.message,
+        })
+    }
+}
+
 impl<T: QueueMessage> PgQueue<T> {
+    /// Query the failed items in the queue.
+    ///
+    /// Note that `item_filters` are only applied to items stored as JSON; items stored as CBOR
+    /// will only match the default all-inclusive filter.
     pub async fn query_failed(
         &self,
         page: i64,
@@ -103,12 +273,13 @@ impl<T: QueueMessage> PgQueue<T> {
             SELECT
                 id,
                 parents,
-                item,
+                item::TEXT,
+                item_cbor,
                 message
             FROM
                 failed 
             WHERE
-                item::TEXT LIKE ANY($1) 
+                COALESCE(item::TEXT, '') LIKE ANY($1) 
                 AND message LIKE ANY($2) 
             ORDER BY
                 id DESC
@@ -122,13 +293,223 @@ impl<T: QueueMessage> PgQueue<T> {
         .bind(message_filters)
         .bind(per_page)
         .bind((page - 1) * per_page)
-        .map(|row| FailedRecord::<T>::from_row(&row))
+        .try_map(|row| FailedRow::from_row(&row))
         .fetch_all(&self.client)
         .await?
         .into_iter()
+        .map(FailedRecord::try_from)
         .collect()
     }
 
+    /// Query the pending items (in both the `queue` and `optimize` tables) that contain an
+    /// in-progress aggregation (an [`Op::Promise`]) anywhere within them, along with the data
+    /// collected so far.
+    ///
+    /// Items stored as JSON are filtered in the database. Items stored as CBOR are narrowed down to
+    /// those containing an encoded promise type tag in the database, and are then decoded and
+    /// filtered as they are fetched, before the page is taken.
+    pub async fn query_aggregations(
+        &self,
+        page: i64,
+        per_page: i64,
+    ) -> Result<Vec<AggregationRecord<T>>, sqlx::Error> {
+        sqlx::query(
+            r#"
+            SELECT
+                id,
+                parents,
+                item::TEXT,
+                item_cbor
+            FROM
+                (
+                    SELECT id, parents, item, item_cbor FROM queue
+                    UNION ALL
+                    SELECT id, parents, item, item_cbor FROM optimize
+                ) AS pending
+            WHERE
+                jsonb_path_exists(item, 'strict $.** ? (@."@type" == "promise")')
+                OR position($1 in item_cbor) > 0
+            ORDER BY
+                id ASC
+            "#,
+        )
+        .bind(CBOR_PROMISE_TAG)
+        .try_map(|row| QueueRow::from_row(&row))
+        .fetch(&self.client)
+        .try_filter_map(|row| async move {
+            let item = decode_item::<Op<T>>(row.item.as_deref(), row.item_cbor.as_deref())?;
+
+            Ok(contains_promise(&item).then(|| AggregationRecord {
+                item: Json(item),
+                id: row.id,
+                parents: row.parents,
+            }))
+        })
+        .skip(usize::try_from((page - 1) * per_page).unwrap_or_default())
+        .take(usize::try_from(per_page).unwrap_or_default())
+        .try_collect()
+        .await
+    }
+
+    /// Remove all pending items that contain `matching` anywhere within them, following the
+    /// semantics of the postgres `@>` operator checked against every nested value of the item.
+    /// Returns the number of items removed.
+    ///
+    /// Items stored as JSON are matched in the database. Items stored as CBOR are decoded and
+    /// matched after being fetched, skipping the ones that can't be decoded.
+    pub async fn remove_matching(&self, matching: &serde_json::Value) -> Result<u64, sqlx::Error> {
+        let mut tx = self.client.begin().await?;
+
+        let mut removed = 0;
+
+        for table in ["queue", "optimize"] {
+            removed += sqlx::query(&format!(
+                r#"
+                DELETE FROM
+                  {table}
+                WHERE
+                  id = ANY(
+                    SELECT
+                      id
+                    FROM
+                      {table}
+                    WHERE
+                      EXISTS (
+                        SELECT
+                        FROM
+                          jsonb_path_query(item, 'strict $.**') AS v(value)
+                        WHERE
+                          v.value @> $1::JSONB
+                      )
+                    FOR UPDATE
+                      SKIP LOCKED
+                  )
+                "#
+            ))
+            .bind(Json(matching))
+            .execute(tx.as_mut())
+            .await?
+            .rows_affected();
+
+            let records = sqlx::query(&format!(
+                r#"
+                SELECT
+                  id,
+                  parents,
+                  correlation_id,
+                  item::text,
+                  item_cbor,
+                  created_at
+                FROM
+                  {table}
+                WHERE
+                  item_cbor IS NOT NULL
+                FOR UPDATE
+                  SKIP LOCKED
+                "#
+            ))
+            .try_map(|x| Record::from_row(&x))
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            removed += delete_matching(&mut tx, table, records, |op: &Op<T>| {
+                json_contains(
+                    &serde_json::to_value(op).expect("serialization is infallible; qed;"),
+                    matching,
+                )
+            })
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(removed)
+    }
+
+    /// The interval at which [`Self::compact_aggregations`] should be run, if configured.
+    pub fn compact_aggregations_interval(&self) -> Option<Duration> {
+        self.compact_aggregations_interval
+    }
+
+    /// Remove duplicate in-progress aggregations from the `queue` and `optimize` tables.
+    ///
+    /// Pending [`Op::Promise`]s that are entirely equal (the same receiver, queue and collected
+    /// data) are considered to be the same aggregation, which can happen if the same aggregation
+    /// is enqueued multiple times. Only the oldest one is kept. Items that can't be decoded are
+    /// moved to the poison table, and items that are currently being processed are skipped.
+    /// Returns the amount of duplicate items that were removed.
+    pub async fn compact_aggregations(&self) -> Result<u64, sqlx::Error> {
+        let mut tx = self.client.begin().await?;
+
+        let mut promises = vec![];
+
+        for table in ["queue", "optimize"] {
+            // CBOR items can't be inspected in the database, but an encoded promise always
+            // contains the encoded `promise` type tag
+            let records = sqlx::query(&format!(
+                r#"
+                SELECT
+                  id,
+                  parents,
+                  correlation_id,
+                  item::text,
+                  item_cbor,
+                  created_at
+                FROM
+                  {table}
+                WHERE
+                  item->>'@type' = 'promise'
+                  OR position($1 in item_cbor) > 0
+                FOR UPDATE
+                  SKIP LOCKED
+                "#
+            ))
+            .bind(CBOR_PROMISE_TAG)
+            .try_map(|x| Record::from_row(&x))
+            .fetch_all(tx.as_mut())
+            .await?;
+
+            for record in records {
+                match record.decode::<Op<T>>() {
+                    Ok(Op::Promise(promise)) => promises.push((
+                        record.id,
+                        serde_json::to_string(&promise).expect("serialization is infallible; qed;"),
+                    )),
+                    Ok(_) => {}
+                    Err(err) => {
+                        sqlx::query(&format!("DELETE FROM {table} WHERE id = $1"))
+                            .bind(record.id)
+                            .execute(tx.as_mut())
+                            .await?;
+
+                        record.poison_in(&mut tx, &err).await?;
+                    }
+                }
+            }
+        }
+
+        let duplicates = duplicate_aggregations(promises);
+
+        let mut removed = 0;
+
+        if !duplicates.is_empty() {
+            debug!(ids = ?duplicates, "removing duplicate aggregations");
+
+            // ids are unique across both tables
+            for table in ["queue", "optimize"] {
+                removed += sqlx::query(&format!("DELETE FROM {table} WHERE id = ANY($1)"))
+                    .bind(&duplicates)
+                    .execute(tx.as_mut())
+                    .await?
+                    .rows_affected();
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(removed)
+    }
+
     pub async fn query_failed_by_id(
         &self,
         id: i64,
@@ -138,7 +519,8 @@ impl<T: QueueMessage> PgQueue<T> {
             SELECT
                id,
                parents,
-               item,
+               item::TEXT,
+               item_cbor,
                message
             FROM
                failed 
@@ -147,11 +529,107 @@ impl<T: QueueMessage> PgQueue<T> {
             "#,
         )
         .bind(id)
-        .map(|row| FailedRecord::<T>::from_row(&row))
+        .try_map(|row| FailedRow::from_row(&row))
         .fetch_optional(&self.client)
         .await?
+        .map(FailedRecord::try_from)
         .transpose()
     }
+
+    /// Normalize `op` and insert the resulting items into the `queue` and `optimize` tables as
+    /// part of `tx`.
+    async fn enqueue_in(
+        &self,
+        tx: &mut sqlx::Transaction<'static, sqlx::Postgres>,
+        op: Op<T>,
+        filter: &T::Filter,
+    ) -> Result<(), sqlx::Error> {
+        let (optimize, ready): (Vec<_>, Vec<_>) =
+            op.normalize()
+                .into_iter()
+                .partition_map(|op| match filter.check_interest(&op) {
+                    FilterResult::Interest(tag) => Either::Left((op, tag)),
+                    FilterResult::NoInterest => Either::Right(op),
+                });
+
+        let (ready_json, ready_cbor): (Vec<_>, Vec<_>) = ready
+            .into_iter()
+            .map(|op| self.payload_format.encode(op))
+            .unzip();
+
+        let ready_count = ready_json.len();
+
+        // if deduplication is enabled, items identical to an already pending item conflict on
+        // the unique item_hash index and are skipped
+        let ready_ids = sqlx::query(
+            "
+            INSERT INTO queue (item, item_cbor, item_hash)
+            SELECT
+                item,
+                item_cbor,
+                CASE WHEN $3 THEN sha256(COALESCE(convert_to(item::TEXT, 'UTF8'), item_cbor)) END
+            FROM UNNEST($1::JSONB[], $2::BYTEA[]) AS t(item, item_cbor)
+            ON CONFLICT DO NOTHING
+            RETURNING id
+            ",
+        )
+        .bind(ready_json)
+        .bind(ready_cbor)
+        .bind(self.deduplicate)
+        .try_map(|x| Id::from_row(&x))
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        if ready_ids.len() < ready_count {
+            debug!(
+                skipped = ready_count - ready_ids.len(),
+                "skipped duplicate ready items"
+            );
+        }
+
+        for ready in ready_ids {
+            debug!(id = ready.id, "enqueued ready item");
+        }
+
+        let (optimize_json, optimize_cbor): (Vec<_>, Vec<_>) = optimize
+            .iter()
+            .map(|(op, _)| self.payload_format.encode(op))
+            .unzip();
+
+        let optimize_further_ids = sqlx::query(
+            "
+            INSERT INTO optimize (item, item_cbor, tag, item_hash)
+            SELECT
+                item,
+                item_cbor,
+                tag,
+                CASE WHEN $4 THEN sha256(COALESCE(convert_to(item::TEXT, 'UTF8'), item_cbor)) END
+            FROM UNNEST($1::JSONB[], $2::BYTEA[], $3::TEXT[]) AS t(item, item_cbor, tag)
+            ON CONFLICT DO NOTHING
+            RETURNING id
+            ",
+        )
+        .bind(optimize_json)
+        .bind(optimize_cbor)
+        .bind(optimize.iter().map(|x| x.1).collect::<Vec<_>>())
+        .bind(self.deduplicate)
+        .try_map(|x| Id::from_row(&x))
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        if optimize_further_ids.len() < optimize.len() {
+            debug!(
+                skipped = optimize.len() - optimize_further_ids.len(),
+                "skipped duplicate optimize items"
+            );
+        }
+
+        for ready in optimize_further_ids {
+            debug!(id = ready.id, "enqueued optimize item");
+        }
+
+        Ok(())
+    }
 }
 
 impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
@@ -171,13 +649,18 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
         //     }
         // });
 
+        let payload_format = config.payload_format;
+        let deduplicate = config.deduplicate;
+        let compact_aggregations_interval = config.compact_aggregations_interval;
+
         let pool = config.into_pg_pool().await?;
 
         pool.execute_many(
             r#"
             CREATE TABLE IF NOT EXISTS queue(
                 id BIGSERIAL PRIMARY KEY,
-                item JSONB NOT NULL,
+                item JSONB,
+                item_cbor BYTEA,
                 parents BIGINT[] DEFAULT '{}',
                 created_at timestamptz NOT NULL DEFAULT now()
             );
@@ -185,7 +668,8 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
             CREATE TABLE IF NOT EXISTS optimize(
                 -- TODO: Figure out how to do this properly
                 id BIGINT PRIMARY KEY DEFAULT nextval('queue_id_seq'::regclass),
-                item JSONB NOT NULL,
+                item JSONB,
+                item_cbor BYTEA,
                 tag text NOT NULL,
                 parents BIGINT[] DEFAULT '{}',
                 created_at timestamptz NOT NULL DEFAULT now()
@@ -193,7 +677,8 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
 
             CREATE TABLE IF NOT EXISTS done(
                 id BIGINT,
-                item JSONB NOT NULL,
+                item JSONB,
+                item_cbor BYTEA,
                 parents BIGINT[] DEFAULT '{}',
                 created_at timestamptz NOT NULL DEFAULT now(),
                 PRIMARY KEY (id, created_at)
@@ -201,13 +686,54 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
 
             CREATE TABLE IF NOT EXISTS failed(
                 id BIGINT PRIMARY KEY,
-                item JSONB NOT NULL,
+                item JSONB,
+                item_cbor BYTEA,
                 parents BIGINT[] DEFAULT '{}',
                 message TEXT,
                 created_at timestamptz NOT NULL DEFAULT now()
             );
 
             CREATE INDEX IF NOT EXISTS index_queue_id ON queue(id);
+
+            -- migrate tables created before item_cbor was introduced
+            ALTER TABLE queue ADD COLUMN IF NOT EXISTS item_cbor BYTEA;
+            ALTER TABLE queue ALTER COLUMN item DROP NOT NULL;
+            ALTER TABLE optimize ADD COLUMN IF NOT EXISTS item_cbor BYTEA;
+            ALTER TABLE optimize ALTER COLUMN item DROP NOT NULL;
+            ALTER TABLE done ADD COLUMN IF NOT EXISTS item_cbor BYTEA;
+            ALTER TABLE done ALTER COLUMN item DROP NOT NULL;
+            ALTER TABLE failed ADD COLUMN IF NOT EXISTS item_cbor BYTEA;
+            ALTER TABLE failed ALTER COLUMN item DROP NOT NULL;
+
+            -- the hash of the payload of deduplicated items, null for all other items
+            ALTER TABLE queue ADD COLUMN IF NOT EXISTS item_hash BYTEA;
+            ALTER TABLE optimize ADD COLUMN IF NOT EXISTS item_hash BYTEA;
+            CREATE UNIQUE INDEX IF NOT EXISTS index_queue_item_hash ON queue(item_hash);
+            CREATE UNIQUE INDEX IF NOT EXISTS index_optimize_item_hash ON optimize(item_hash);
+
+            -- the id of the top-level item an item descends from, null for top-level items
+            ALTER TABLE queue ADD COLUMN IF NOT EXISTS correlation_id BIGINT;
+            ALTER TABLE optimize ADD COLUMN IF NOT EXISTS correlation_id BIGINT;
+            ALTER TABLE done ADD COLUMN IF NOT EXISTS correlation_id BIGINT;
+            ALTER TABLE failed ADD COLUMN IF NOT EXISTS correlation_id BIGINT;
+
+            -- idempotency keys of items enqueued with enqueue_idempotent
+            CREATE TABLE IF NOT EXISTS enqueue_key(
+                key TEXT PRIMARY KEY,
+                created_at timestamptz NOT NULL DEFAULT now()
+            );
+
+            -- items that could not be decoded (for example after an upgrade that changed the
+            -- message format), moved out of the queue so that they don't block processing
+            CREATE TABLE IF NOT EXISTS poison(
+                id BIGINT PRIMARY KEY,
+                item JSONB,
+                item_cbor BYTEA,
+                parents BIGINT[] DEFAULT '{}',
+                correlation_id BIGINT,
+                message TEXT,
+                created_at timestamptz NOT NULL DEFAULT now()
+            );
             "#,
         )
         .try_for_each(|result| async move {
@@ -219,6 +745,9 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
 
         Ok(Self {
             client: pool,
+            payload_format,
+            deduplicate,
+            compact_aggregations_interval,
             __marker: PhantomData,
         })
     }
@@ -226,55 +755,41 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
     async fn enqueue<'a>(&'a self, op: Op<T>, filter: &'a T::Filter) -> Result<(), Self::Error> {
         trace!("enqueue");
 
-        let (optimize, ready): (Vec<_>, Vec<_>) =
-            op.normalize()
-                .into_iter()
-                .partition_map(|op| match filter.check_interest(&op) {
-                    FilterResult::Interest(tag) => Either::Left((op, tag)),
-                    FilterResult::NoInterest => Either::Right(op),
-                });
-
         let mut tx = self.client.begin().await?;
 
-        let ready_ids = sqlx::query(
-            "
-            INSERT INTO queue (item)
-            SELECT * FROM UNNEST($1::JSONB[])
-            RETURNING id
-            ",
-        )
-        .bind(ready.into_iter().map(Json).collect::<Vec<_>>())
-        .try_map(|x| Id::from_row(&x))
-        .fetch_all(tx.as_mut())
-        .await?;
+        self.enqueue_in(&mut tx, op, filter).await?;
 
-        for ready in ready_ids {
-            debug!(id = ready.id, "enqueued ready item");
-        }
+        tx.commit().await?;
 
-        let optimize_further_ids = sqlx::query(
-            "
-            INSERT INTO optimize (item, tag)
-            SELECT * FROM UNNEST($1::JSONB[], $2::TEXT[])
-            RETURNING id
-            ",
-        )
-        .bind(
-            optimize
-                .clone()
-                .into_iter()
-                .map(|x| Json(x.0))
-                .collect::<Vec<_>>(),
-        )
-        .bind(optimize.into_iter().map(|x| x.1).collect::<Vec<_>>())
-        .try_map(|x| Id::from_row(&x))
-        .fetch_all(tx.as_mut())
-        .await?;
+        Ok(())
+    }
 
-        for ready in optimize_further_ids {
-            debug!(id = ready.id, "enqueued optimize item");
+    async fn enqueue_idempotent<'a>(
+        &'a self,
+        key: &'a str,
+        op: Op<T>,
+        filter: &'a T::Filter,
+    ) -> Result<(), Self::Error> {
+        trace!(%key, "enqueue idempotent");
+
+        let mut tx = self.client.begin().await?;
+
+        // the key is inserted in the same transaction as the item, so either both or neither are
+        // committed
+        let inserted =
+            sqlx::query("INSERT INTO enqueue_key (key) VALUES ($1) ON CONFLICT DO NOTHING")
+                .bind(key)
+                .execute(tx.as_mut())
+                .await?
+                .rows_affected();
+
+        if inserted == 0 {
+            debug!(%key, "item with this idempotency key has already been enqueued");
+            return Ok(());
         }
 
+        self.enqueue_in(&mut tx, op, filter).await?;
+
         tx.commit().await?;
 
         Ok(())
@@ -313,7 +828,9 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
             RETURNING
               id,
               parents,
+              correlation_id,
               item::text,
+              item_cbor,
               created_at
             "#,
         )
@@ -323,12 +840,19 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
 
         match row {
             Some(row) => {
-                let span = info_span!("processing item", id = row.id);
+                let correlation_id = row.correlation_id();
+                let span = info_span!("processing item", id = row.id, correlation_id);
 
-                trace!(%row.item);
+                trace!(item = ?row.item);
 
-                // really don't feel like defining a new error type right now
-                let op = de(&row.item).map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                let op = match row.decode() {
+                    Ok(op) => op,
+                    Err(err) => {
+                        row.poison_in(&mut tx, &err).await?;
+                        tx.commit().await?;
+                        return Ok(None);
+                    }
+                };
 
                 let timer = ITEM_PROCESSING_DURATION.start_timer();
                 let (r, res) = f(op, ItemId::new(row.id).unwrap()).instrument(span).await;
@@ -340,13 +864,15 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                         sqlx::query(
                             r#"
                             INSERT INTO
-                            failed (id, parents, item,      created_at, message)
-                            VALUES ($1, $2,      $3::JSONB, $4,         $5     )
+                            failed (id, parents, correlation_id, item,      item_cbor, created_at, message)
+                            VALUES ($1, $2,      $3,             $4::JSONB, $5,        $6,         $7     )
                             "#,
                         )
                         .bind(row.id)
                         .bind(row.parents)
+                        .bind(correlation_id)
                         .bind(row.item)
+                        .bind(row.item_cbor)
                         .bind(row.created_at)
                         .bind(error)
                         .execute(tx.as_mut())
@@ -359,13 +885,15 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                             sqlx::query(
                                 "
                                 INSERT INTO
-                                done   (id, parents, item,      created_at)
-                                VALUES ($1, $2,      $3::JSONB, $4        )
+                                done   (id, parents, correlation_id, item,      item_cbor, created_at)
+                                VALUES ($1, $2,      $3,             $4::JSONB, $5,        $6        )
                                 ",
                             )
                             .bind(row.id)
                             .bind(row.parents)
+                            .bind(correlation_id)
                             .bind(row.item)
+                            .bind(row.item_cbor)
                             .bind(row.created_at)
                             .execute(tx.as_mut())
                             .await?;
@@ -382,24 +910,53 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                                     FilterResult::NoInterest => Either::Right(op),
                                 });
 
+                            let (ready_json, ready_cbor): (Vec<_>, Vec<_>) = ready
+                                .into_iter()
+                                .map(|op| self.payload_format.encode(op))
+                                .unzip();
+
                             sqlx::query(
                                 "
-                                INSERT INTO queue (item)
-                                SELECT * FROM UNNEST($1::JSONB[])
+                                INSERT INTO queue (item, item_cbor, correlation_id, item_hash)
+                                SELECT
+                                    item,
+                                    item_cbor,
+                                    $3,
+                                    CASE WHEN $4 THEN sha256(COALESCE(convert_to(item::TEXT, 'UTF8'), item_cbor)) END
+                                FROM UNNEST($1::JSONB[], $2::BYTEA[]) AS t(item, item_cbor)
+                                ON CONFLICT DO NOTHING
                                 ",
                             )
-                            .bind(ready.into_iter().map(Json).collect::<Vec<_>>())
+                            .bind(ready_json)
+                            .bind(ready_cbor)
+                            .bind(correlation_id)
+                            .bind(self.deduplicate)
                             .execute(tx.as_mut())
                             .await?;
 
+                            let (optimize_json, optimize_cbor): (Vec<_>, Vec<_>) = optimize
+                                .iter()
+                                .map(|(op, _)| self.payload_format.encode(op))
+                                .unzip();
+
                             sqlx::query(
                                 "
-                                INSERT INTO optimize (item, tag)
-                                SELECT * FROM UNNEST($1::JSONB[], $2::TEXT[])
+                                INSERT INTO optimize (item, item_cbor, tag, correlation_id, item_hash)
+                                SELECT
+                                    item,
+                                    item_cbor,
+                                    tag,
+                                    $4,
+                                    CASE WHEN $5 THEN sha256(COALESCE(convert_to(item::TEXT, 'UTF8'), item_cbor)) END
+                                FROM UNNEST($1::JSONB[], $2::BYTEA[], $3::TEXT[]) AS t(item, item_cbor, tag)
+                                ON CONFLICT DO NOTHING
                                 ",
                             )
-                            .bind(optimize.iter().map(|(op, _)| Json(op)).collect::<Vec<_>>())
+                            .bind(optimize_json)
+                            .bind(optimize_cbor)
                             .bind(optimize.iter().map(|(_, tag)| *tag).collect::<Vec<_>>())
+                            .bind(correlation_id)
+                            .bind(self.deduplicate)
                             .execute(tx.as_mut())
                             .await?;
                         }
@@ -455,7 +1012,9 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
             RETURNING
               id,
               parents,
+              correlation_id,
               item::text,
+              item_cbor,
               created_at
             "#,
         )
@@ -471,16 +1030,24 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
             return Ok(());
         }
 
-        let (ids, msgs) = msgs
-            .into_iter()
-            .map(|r| {
-                Ok((
-                    r.id,
-                    de(&r.item).map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
-                ))
-            })
-            .collect::<Result<(Vec<_>, Vec<_>), sqlx::Error>>()
-            .map_err(Either::Left)?;
+        let mut decoded = vec![];
+        for record in msgs {
+            match record.decode() {
+                Ok(msg) => decoded.push((record.id, record.correlation_id(), msg)),
+                Err(err) => record
+                    .poison_in(&mut tx, &err)
+                    .await
+                    .map_err(Either::Left)?,
+            }
+        }
+
+        if decoded.is_empty() {
+            tx.commit().await.map_err(Either::Left)?;
+            return Ok(());
+        }
+
+        let (ids, correlation_ids, msgs) =
+            decoded.into_iter().collect::<(Vec<_>, Vec<_>, Vec<_>)>();
 
         OPTIMIZE_ITEM_COUNT.observe(msgs.len() as f64);
         let timer = OPTIMIZE_PROCESSING_DURATION.start_timer();
@@ -515,55 +1082,184 @@ impl<T: QueueMessage> voyager_vm::Queue<T> for PgQueue<T> {
                 .collect::<Vec<_>>()
         };
 
+        // items created from several parents are attributed to the first one
+        let get_correlation_id =
+            |parent_idxs: &[usize]| parent_idxs.first().map(|&idx| correlation_ids[idx]);
+
         for (parent_idxs, new_msg, tag) in optimize_further {
             let parents = get_parent_ids(&parent_idxs);
             trace!(parent_idxs = ?&parent_idxs, parents = ?&parents);
 
+            let (item, item_cbor) = self.payload_format.encode(new_msg);
+
             let new_row = sqlx::query(
                 "
-                INSERT INTO optimize (item, parents, tag)
+                INSERT INTO optimize (item, item_cbor, parents, tag, correlation_id, item_hash)
                 VALUES
-                    ($1::JSONB, $2, $3)
+                    (
+                        $1::JSONB,
+                        $2,
+                        $3,
+                        $4,
+                        $5,
+                        CASE WHEN $6 THEN sha256(COALESCE(convert_to($1::JSONB::TEXT, 'UTF8'), $2)) END
+                    )
+                ON CONFLICT DO NOTHING
                 RETURNING id
                 ",
             )
-            .bind(Json(new_msg))
+            .bind(item)
+            .bind(item_cbor)
             .bind(&parents)
             .bind(tag)
+            .bind(get_correlation_id(&parent_idxs))
+            .bind(self.deduplicate)
             .try_map(|row| Id::from_row(&row))
-            .fetch_one(tx.as_mut())
+            .fetch_optional(tx.as_mut())
             .await
             .map_err(Either::Left)?;
 
-            debug!(id = new_row.id, "inserted new optimizer message");
+            match new_row {
+                Some(new_row) => debug!(id = new_row.id, "inserted new optimizer message"),
+                None => debug!("skipped duplicate optimizer message"),
+            }
         }
 
         for (parent_idxs, new_msg) in ready {
             let parents = get_parent_ids(&parent_idxs);
             trace!(parent_idxs = ?&parent_idxs, parents = ?&parents);
 
+            let (item, item_cbor) = self.payload_format.encode(new_msg);
+
             let new_row = sqlx::query(
                 "
-                INSERT INTO queue (item, parents)
+                INSERT INTO queue (item, item_cbor, parents, correlation_id, item_hash)
                 VALUES
-                    ($1::JSONB, $2)
+                    (
+                        $1::JSONB,
+                        $2,
+                        $3,
+                        $4,
+                        CASE WHEN $5 THEN sha256(COALESCE(convert_to($1::JSONB::TEXT, 'UTF8'), $2)) END
+                    )
+                ON CONFLICT DO NOTHING
                 RETURNING id
                 ",
             )
-            .bind(Json(new_msg))
+            .bind(item)
+            .bind(item_cbor)
             .bind(&parents)
+            .bind(get_correlation_id(&parent_idxs))
+            .bind(self.deduplicate)
             .try_map(|x| Id::from_row(&x))
-            .fetch_one(tx.as_mut())
+            .fetch_optional(tx.as_mut())
             .await
             .map_err(Either::Left)?;
 
-            debug!(id = new_row.id, "inserted new message");
+            match new_row {
+                Some(new_row) => debug!(id = new_row.id, "inserted new message"),
+                None => debug!("skipped duplicate message"),
+            }
         }
 
         tx.commit().await.map_err(Either::Left)?;
 
         Ok(())
     }
+
+    async fn remove<'a, P>(&'a self, predicate: P) -> Result<u64, Self::Error>
+    where
+        P: Fn(&Op<T>) -> bool + Send + Sync + 'a,
+    {
+        let mut removed = 0;
+
+        // the predicate can't be checked in the database, so the items are fetched and checked in
+        // batches of REMOVE_BATCH_SIZE, each in their own transaction. items that are locked
+        // (i.e. currently being processed) are skipped and will not be removed.
+        for table in ["queue", "optimize"] {
+            let mut after_id = 0;
+
+            loop {
+                let mut tx = self.client.begin().await?;
+
+                let records = sqlx::query(&format!(
+                    r#"
+                    SELECT
+                      id,
+                      parents,
+                      correlation_id,
+                      item::text,
+                      item_cbor,
+                      created_at
+                    FROM
+                      {table}
+                    WHERE
+                      id > $1
+                    ORDER BY
+                      id ASC
+                    LIMIT
+                      $2
+                    FOR UPDATE
+                      SKIP LOCKED
+                    "#
+                ))
+                .bind(after_id)
+                .bind(REMOVE_BATCH_SIZE)
+                .try_map(|x| Record::from_row(&x))
+                .fetch_all(tx.as_mut())
+                .await?;
+
+                let Some(last) = records.last() else {
+                    break;
+                };
+
+                after_id = last.id;
+
+                removed += delete_matching(&mut tx, table, records, &predicate).await?;
+
+                tx.commit().await?;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// The amount of items that are checked at once by [`voyager_vm::Queue::remove`].
+const REMOVE_BATCH_SIZE: i64 = 1000;
+
+/// Delete the `records` of `table` that match `predicate` as part of `tx`, returning the number of
+/// items removed. Records that can't be decoded are skipped.
+async fn delete_matching<T: QueueMessage>(
+    tx: &mut sqlx::Transaction<'static, sqlx::Postgres>,
+    table: &str,
+    records: Vec<Record>,
+    predicate: impl Fn(&Op<T>) -> bool,
+) -> Result<u64, sqlx::Error> {
+    let ids = records
+        .into_iter()
+        .filter_map(|record| match record.decode() {
+            Ok(op) => predicate(&op).then_some(record.id),
+            Err(err) => {
+                warn!(id = record.id, %err, "unable to decode item, skipping it");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    debug!(%table, ?ids, "removing items");
+
+    Ok(
+        sqlx::query(&format!("DELETE FROM {table} WHERE id = ANY($1)"))
+            .bind(&ids)
+            .execute(tx.as_mut())
+            .await?
+            .rows_affected(),
+    )
 }
 
 #[derive(sqlx::Type)]
@@ -573,6 +1269,72 @@ pub enum EnqueueStatus {
     Optimize,
 }
 
+/// The CBOR encoding of the `promise` type tag of [`Op::Promise`] (a text string of length 7).
+const CBOR_PROMISE_TAG: &[u8] = b"\x67promise";
+
+/// The ids of the aggregations in `promises` that are equal to an older aggregation, where each
+/// aggregation is given as `(id, serialized promise)`.
+fn duplicate_aggregations(mut promises: Vec<(i64, String)>) -> Vec<i64> {
+    promises.sort_unstable_by_key(|(id, _)| *id);
+
+    let mut seen = HashSet::new();
+
+    promises
+        .into_iter()
+        .filter_map(|(id, promise)| (!seen.insert(promise)).then_some(id))
+        .collect()
+}
+
+fn contains_promise<T: QueueMessage>(op: &Op<T>) -> bool {
+    match op {
+        Op::Promise(_) => true,
+        Op::Seq(ops) | Op::Conc(ops) => ops.iter().any(contains_promise),
+        Op::Void(op) => contains_promise(op),
+        Op::Data(_) | Op::Call(_) | Op::Defer { .. } | Op::Noop => false,
+    }
+}
+
+/// Check if `value` or any value nested within it contains `needle`, following the semantics of
+/// the postgres `@>` jsonb containment operator.
+fn json_contains(value: &serde_json::Value, needle: &serde_json::Value) -> bool {
+    fn contains_at(value: &serde_json::Value, needle: &serde_json::Value) -> bool {
+        match (value, needle) {
+            (serde_json::Value::Object(value), serde_json::Value::Object(needle)) => needle
+                .iter()
+                .all(|(k, needle)| value.get(k).is_some_and(|value| contains_at(value, needle))),
+            (serde_json::Value::Array(value), serde_json::Value::Array(needle)) => needle
+                .iter()
+                .all(|needle| value.iter().any(|value| contains_at(value, needle))),
+            (value, needle) => value == needle,
+        }
+    }
+
+    contains_at(value, needle)
+        || match value {
+            serde_json::Value::Object(map) => map.values().any(|v| json_contains(v, needle)),
+            serde_json::Value::Array(arr) => arr.iter().any(|v| json_contains(v, needle)),
+            _ => false,
+        }
+}
+
+/// Decode an item from the `(item, item_cbor)` column pair of a row.
+fn decode_item<T: DeserializeOwned>(
+    item: Option<&str>,
+    item_cbor: Option<&[u8]>,
+) -> Result<T, sqlx::Error> {
+    // really don't feel like defining a new error type right now
+    match (item, item_cbor) {
+        (Some(item), _) => de(item).map_err(|e| sqlx::Error::Decode(Box::new(e))),
+        (None, Some(item_cbor)) => {
+            ciborium::from_reader_with_recursion_limit(item_cbor, usize::MAX)
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+        }
+        (None, None) => Err(sqlx::Error::Decode(
+            "row has neither item nor item_cbor set".into(),
+        )),
+    }
+}
+
 fn de<T: DeserializeOwned>(s: &str) -> Result<T, serde_json::Error> {
     let mut deserializer = serde_json::Deserializer::from_str(s);
     deserializer.disable_recursion_limit();
@@ -603,3 +1365,49 @@ impl<K: Hash + Eq, V> MapExt<K, V> for HashMap<K, V> {
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_aggregations_keeps_oldest() {
+        // duplicates within the queue table, and between the queue and optimize tables, which
+        // are returned in table order rather than id order
+        let promises = vec![
+            (3, "a".to_owned()),
+            (5, "b".to_owned()),
+            (1, "a".to_owned()),
+            (2, "b".to_owned()),
+            (4, "a".to_owned()),
+            (6, "c".to_owned()),
+        ];
+
+        assert_eq!(duplicate_aggregations(promises), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn cbor_promise_tag() {
+        let encode = |value: serde_json::Value| {
+            let mut bytes = vec![];
+            ciborium::into_writer(&value, &mut bytes).unwrap();
+            bytes
+        };
+
+        let contains_tag = |bytes: &[u8]| {
+            bytes
+                .windows(CBOR_PROMISE_TAG.len())
+                .any(|w| w == CBOR_PROMISE_TAG)
+        };
+
+        assert!(contains_tag(&encode(serde_json::json!({
+            "@type": "promise",
+            "@value": {}
+        }))));
+
+        assert!(!contains_tag(&encode(serde_json::json!({
+            "@type": "call",
+            "@value": {}
+        }))));
+    }
+}
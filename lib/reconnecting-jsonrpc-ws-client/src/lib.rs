@@ -13,6 +13,9 @@ use tokio::time::sleep;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, debug_span, error, instrument, trace, Instrument};
 
+/// How long a request waits for the client to (re)connect before failing.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone)]
 pub struct Client {
     inner: Arc<ClientInner>,
@@ -145,6 +148,34 @@ impl Client {
         .map_err(|_| ConnectionTimeoutError { timeout })
     }
 
+    /// Returns the current connection. If the client is disconnected, this waits up to
+    /// [`CONNECTION_TIMEOUT`] for it to reconnect such that requests made while the connection is
+    /// being re-established don't fail needlessly.
+    async fn connection(
+        &self,
+        request: &str,
+    ) -> Result<Arc<jsonrpsee::core::client::Client>, jsonrpsee::core::client::Error> {
+        if let Some(client) = self.inner.client.load_full() {
+            return Ok(client);
+        }
+
+        debug!(%request, "client not connected, waiting for it to reconnect");
+
+        self.wait_until_connected(CONNECTION_TIMEOUT)
+            .await
+            .map_err(|e| {
+                jsonrpsee::core::client::Error::Custom(format!(
+                    "not yet connected (request: {request}): {e}"
+                ))
+            })?;
+
+        self.inner.client.load_full().ok_or_else(|| {
+            jsonrpsee::core::client::Error::Custom(format!(
+                "not yet connected (request: {request})"
+            ))
+        })
+    }
+
     pub fn shutdown(&self) {
         self.inner.cancellation_token.cancel();
         // self.handle.abort()
@@ -175,15 +206,8 @@ impl ClientT for Client {
     where
         Params: ToRpcParams + Send,
     {
-        self.inner
-            .client
-            .load_full()
-            .as_deref()
-            .ok_or_else(|| {
-                jsonrpsee::core::client::Error::Custom(format!(
-                    "not yet connected (request: {method})",
-                ))
-            })?
+        self.connection(method)
+            .await?
             .notification(method, params)
             .await
     }
@@ -197,17 +221,7 @@ impl ClientT for Client {
         R: DeserializeOwned,
         Params: ToRpcParams + Send,
     {
-        self.inner
-            .client
-            .load_full()
-            .as_deref()
-            .ok_or_else(|| {
-                jsonrpsee::core::client::Error::Custom(format!(
-                    "not yet connected (request: {method})",
-                ))
-            })?
-            .request(method, params)
-            .await
+        self.connection(method).await?.request(method, params).await
     }
 
     async fn batch_request<'a, R>(
@@ -217,13 +231,8 @@ impl ClientT for Client {
     where
         R: DeserializeOwned + fmt::Debug + 'a,
     {
-        self.inner
-            .client
-            .load_full()
-            .as_deref()
-            .ok_or(jsonrpsee::core::client::Error::Custom(
-                "not yet connected (batch request)".to_owned(),
-            ))?
+        self.connection("batch request")
+            .await?
             .batch_request(batch)
             .await
     }
@@ -1,3 +1,4 @@
+use hex_literal::hex;
 use sha2::Digest;
 use sha3::Keccak256;
 
@@ -14,19 +15,60 @@ pub fn keccak256(bytes: impl AsRef<[u8]>) -> H256 {
     Keccak256::new().chain_update(bytes).finalize().into()
 }
 
+/// The code hash of an account without code, i.e. `keccak256([])`.
+pub const EMPTY_CODE_HASH: H256 = H256::new(hex!(
+    "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+));
+
+/// Whether an account, as returned by `eth_getProof`, is a deployed contract.
+///
+/// An account without code means that the contract is not deployed at the queried height (or
+/// that the node is not synced to it), in which case a proof of its storage is useless. Note that
+/// geth reports an account that does not exist at all with a code hash of zero and a non-empty
+/// (absence) account proof, so checking the proof and [`EMPTY_CODE_HASH`] alone is not enough.
+#[must_use]
+pub fn is_deployed_contract(account_proof: &[impl AsRef<[u8]>], code_hash: H256) -> bool {
+    !account_proof.is_empty() && code_hash != EMPTY_CODE_HASH && code_hash != H256::default()
+}
+
 /// The slot of the `mapping(bytes32 => bytes32) public commitments` mapping in the `IBCStore` contract.
 pub const IBC_HANDLER_COMMITMENTS_SLOT: U256 = U256::from_limbs([0, 0, 0, 0]);
 
-/// Calculates the slot for a `path` at saved in the commitment map in `slot`
+/// Calculates the slot for a `path` at saved in the commitment map in [`IBC_HANDLER_COMMITMENTS_SLOT`].
 ///
 /// key: `keccak256(keccak256(abi.encode_packed(path)) || slot)`
 #[must_use = "calculating the commitment key has no effect"]
 pub fn ibc_commitment_key(path: H256) -> U256 {
-    Slot::Mapping(
-        &Slot::Offset(IBC_HANDLER_COMMITMENTS_SLOT),
-        MappingKey::Bytes32(path),
-    )
-    .slot()
+    ibc_commitment_key_at_slot(path, IBC_HANDLER_COMMITMENTS_SLOT)
+}
+
+/// Calculates the slot for a `path` at saved in the commitment map in `commitments_slot`.
+///
+/// This is only required for `IBCStore` deployments with a non-standard storage layout, prefer
+/// [`ibc_commitment_key`] otherwise.
+#[must_use = "calculating the commitment key has no effect"]
+pub fn ibc_commitment_key_at_slot(path: H256, commitments_slot: U256) -> U256 {
+    Slot::Mapping(&Slot::Offset(commitments_slot), MappingKey::Bytes32(path)).slot()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_code_hash() {
+        assert_eq!(keccak256([]), EMPTY_CODE_HASH);
+    }
+
+    #[test]
+    fn is_deployed_contract_rejects_missing_accounts() {
+        let proof = [vec![1_u8]];
+
+        assert!(is_deployed_contract(&proof, keccak256([1])));
+        assert!(!is_deployed_contract(&proof, EMPTY_CODE_HASH));
+        assert!(!is_deployed_contract(&proof, H256::default()));
+        assert!(!is_deployed_contract(&[] as &[Vec<u8>], keccak256([1])));
+    }
 }
 
 // #[cfg(test)]
@@ -66,6 +66,14 @@ impl From<protos::google::protobuf::Any> for RawAny {
     }
 }
 
+impl Decode<Proto> for RawAny {
+    type Error = prost::DecodeError;
+
+    fn decode(bytes: &[u8]) -> Result<Self, Self::Error> {
+        <protos::google::protobuf::Any as ::prost::Message>::decode(bytes).map(Into::into)
+    }
+}
+
 impl From<RawAny> for protos::google::protobuf::Any {
     fn from(value: RawAny) -> Self {
         Self {
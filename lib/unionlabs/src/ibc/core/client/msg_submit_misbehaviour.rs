@@ -0,0 +1,9 @@
+use macros::model;
+
+use crate::{id::ClientId, primitives::Bytes};
+
+#[model(proto(raw(protos::ibc::core::client::v1::MsgSubmitMisbehaviour)))]
+pub struct MsgSubmitMisbehaviour {
+    pub client_id: ClientId,
+    pub misbehaviour: Bytes,
+}
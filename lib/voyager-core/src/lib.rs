@@ -355,6 +355,13 @@ impl Timestamp {
         Timestamp(secs * 1_000_000_000)
     }
 
+    /// Construct a timestamp from a number of seconds, returning `None` if it is not representable
+    /// in nanoseconds.
+    #[must_use = "constructing a Timestamp has no effect"]
+    pub fn try_from_secs(secs: u64) -> Option<Self> {
+        secs.checked_mul(1_000_000_000).map(Timestamp)
+    }
+
     #[must_use = "accessing the inner value has no effect"]
     pub fn as_nanos(&self) -> u64 {
         self.0
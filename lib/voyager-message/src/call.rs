@@ -1,14 +1,19 @@
 use enumorph::Enumorph;
 use macros::model;
 use serde::de::DeserializeOwned;
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 use unionlabs::{ibc::core::client::height::Height, traits::Member};
 use voyager_core::{ClientType, IbcSpecId, QueryHeight, Timestamp};
-use voyager_vm::{call, defer, noop, now, seq, CallT, Op, QueueError};
+use voyager_vm::{
+    call, defer,
+    metrics::{record_dropped, DropReason},
+    noop, now, seq, CallT, Op, QueueError,
+};
 
 use crate::{
-    core::ChainId, data::IbcDatagram, error_object_to_queue_error, json_rpc_error_to_queue_error,
-    module::PluginClient, Context, PluginMessage, RawClientId, VoyagerMessage,
+    core::ChainId, data::IbcDatagram, error_object_to_queue_error, into_value,
+    json_rpc_error_to_queue_error, module::PluginClient, Context, PluginMessage, RawClientId,
+    VoyagerMessage,
 };
 
 #[model]
@@ -76,7 +81,9 @@ pub struct FetchBlocks {
 ///
 /// # Implementor's Note
 ///
-/// The returned [`Op`] ***MUST*** resolve to an [`OrderedHeaders`] data.
+/// The returned [`Op`] ***MUST*** resolve to an [`OrderedHeaders`] data. If
+/// the update is too large to fetch at once, it may instead resolve to
+/// multiple [`OrderedHeaders`], which together must reach `update_to`.
 /// This is the entrypoint called when a client update is requested, and
 /// is intended to be called in the queue of an
 /// [`AggregateMsgUpdateClientsFromOrderedHeaders`] message, which will
@@ -128,6 +135,9 @@ pub struct WaitForTimestamp {
 
 /// Wait for the client `.client_id` on `.chain_id` to trust a height >=
 /// `.height`.
+///
+/// If `.deadline` (a unix timestamp, in seconds) is set and passes before the client reaches the
+/// height, the wait fails instead of waiting forever for a client that is never updated.
 #[model]
 pub struct WaitForTrustedHeight {
     pub chain_id: ChainId,
@@ -135,6 +145,13 @@ pub struct WaitForTrustedHeight {
     pub client_id: RawClientId,
     pub height: Height,
     pub finalized: bool,
+    #[serde(default)]
+    pub deadline: Option<u64>,
+}
+
+/// Whether the `deadline` of a [`WaitForTrustedHeight`] has passed at `now`.
+fn deadline_passed(deadline: Option<u64>, now: u64) -> bool {
+    deadline.is_some_and(|deadline| now >= deadline)
 }
 
 impl CallT<VoyagerMessage> for Call {
@@ -177,6 +194,19 @@ impl CallT<VoyagerMessage> for Call {
                 Err(QueueError::Fatal(message.into()))
             }
 
+            Call::SubmitTx(SubmitTx {
+                chain_id,
+                datagrams,
+            }) if ctx.dry_run => {
+                info!(
+                    %chain_id,
+                    datagrams = %into_value(&datagrams),
+                    "dry run, not submitting transaction"
+                );
+
+                Ok(noop())
+            }
+
             Call::SubmitTx(SubmitTx { chain_id, .. }) => {
                 let message = format!(
                     "transaction submission request received for chain {chain_id} but \
@@ -217,7 +247,7 @@ impl CallT<VoyagerMessage> for Call {
                     Ok(noop())
                 } else {
                     Ok(seq([
-                        defer(now() + 1),
+                        defer(now() + block_time(&ctx, &chain_id)?),
                         call(WaitForHeight {
                             chain_id,
                             height,
@@ -245,9 +275,7 @@ impl CallT<VoyagerMessage> for Call {
                 } else {
                     debug!(%chain_id, %timestamp, %chain_timestamp, "timestamp not yet reached");
                     Ok(seq([
-                        // REVIEW: Defer until `now + chain.block_time()`? Would require a new
-                        // method on chain
-                        defer(now() + 1),
+                        defer(now() + block_time(&ctx, &chain_id)?),
                         call(WaitForTimestamp {
                             chain_id,
                             timestamp,
@@ -263,6 +291,7 @@ impl CallT<VoyagerMessage> for Call {
                 client_id,
                 height,
                 finalized,
+                deadline,
             }) => {
                 let trusted_client_state_meta = ctx
                     .rpc_server
@@ -287,17 +316,34 @@ impl CallT<VoyagerMessage> for Call {
                     );
 
                     Ok(noop())
+                } else if deadline_passed(deadline, now()) {
+                    warn!(
+                        %chain_id,
+                        %client_id,
+                        %height,
+                        trusted_height = %trusted_client_state_meta.counterparty_height,
+                        "deadline passed while waiting for trusted height"
+                    );
+                    record_dropped(DropReason::DeadlineExceeded);
+
+                    Err(QueueError::Fatal(
+                        format!(
+                            "client {client_id} on {chain_id} did not reach height {height} \
+                            before the deadline, trusted height is {}",
+                            trusted_client_state_meta.counterparty_height
+                        )
+                        .into(),
+                    ))
                 } else {
                     Ok(seq([
-                        // REVIEW: Defer until `now + counterparty_chain.block_time()`? Would
-                        // require a new method on chain
-                        defer(now() + 1),
+                        defer(now() + block_time(&ctx, &trusted_client_state_meta.chain_id)?),
                         call(WaitForTrustedHeight {
                             chain_id,
                             ibc_spec_id,
                             client_id,
                             height,
                             finalized,
+                            deadline,
                         }),
                     ]))
                 }
@@ -310,3 +356,27 @@ impl CallT<VoyagerMessage> for Call {
         }
     }
 }
+
+/// The interval to wait between polls of `chain_id`, based on its configured block time.
+fn block_time(ctx: &Context, chain_id: &ChainId) -> Result<u64, QueueError> {
+    Ok(ctx
+        .rpc_server
+        .modules()
+        .map_err(error_object_to_queue_error)?
+        .chain_block_time(chain_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_for_trusted_height_deadline() {
+        // no deadline waits forever
+        assert!(!deadline_passed(None, u64::MAX));
+
+        assert!(!deadline_passed(Some(100), 99));
+        assert!(deadline_passed(Some(100), 100));
+        assert!(deadline_passed(Some(100), 101));
+    }
+}
@@ -41,6 +41,24 @@ impl Callback {
 }
 
 impl CallbackT<VoyagerMessage> for Callback {
+    fn name(&self) -> String {
+        match self {
+            Callback::AggregateMsgUpdateClientsFromOrderedHeaders(_) => {
+                "aggregate_msg_update_clients_from_ordered_headers".to_owned()
+            }
+            Callback::Plugin(PluginMessage { plugin, .. }) => plugin.clone(),
+        }
+    }
+
+    fn chain_id(&self) -> Option<String> {
+        match self {
+            Callback::AggregateMsgUpdateClientsFromOrderedHeaders(
+                AggregateMsgUpdateClientsFromOrderedHeaders { chain_id, .. },
+            ) => Some(chain_id.to_string()),
+            Callback::Plugin(_) => None,
+        }
+    }
+
     #[instrument(skip_all, fields(id = ctx.id().raw()))]
     async fn process(
         self,
@@ -55,13 +73,20 @@ impl CallbackT<VoyagerMessage> for Callback {
                     client_id,
                 },
             ) => {
-                let OrderedHeaders { headers } = data
-                    .into_iter()
-                    .exactly_one()
-                    .map_err(|found| serde_json::to_string(&found.collect::<Vec<_>>()).unwrap())
-                    .and_then(|d| {
-                        d.try_into()
-                            .map_err(|found| serde_json::to_string(&found).unwrap())
+                // client update plugins may return the headers in multiple chunks (for example if
+                // the client is far behind), which are concatenated in the order they were returned
+                let headers = (!data.is_empty())
+                    .then_some(data)
+                    .ok_or_else(|| "[]".to_owned())
+                    .and_then(|data| {
+                        data.into_iter()
+                            .map(|d| {
+                                OrderedHeaders::try_from(d)
+                                    .map(|OrderedHeaders { headers }| headers)
+                                    .map_err(|found| serde_json::to_string(&found).unwrap())
+                            })
+                            .flatten_ok()
+                            .collect::<Result<Vec<_>, _>>()
                     })
                     .map_err(|found| {
                         QueueError::Fatal(
@@ -97,8 +122,7 @@ impl CallbackT<VoyagerMessage> for Callback {
                     .modules()
                     .map_err(error_object_to_queue_error)?
                     .ibc_spec_handlers
-                    .get(&ibc_spec_id)
-                    .map_err(error_object_to_queue_error)?;
+                    .get(&ibc_spec_id)?;
 
                 Ok(voyager_vm::call(SubmitTx {
                     chain_id,
@@ -139,7 +163,7 @@ impl CallbackT<VoyagerMessage> for Callback {
     }
 }
 
-/// Required data: [`OrderedHeaders`]
+/// Required data: one or more [`OrderedHeaders`]
 #[model]
 pub struct AggregateMsgUpdateClientsFromOrderedHeaders {
     pub ibc_spec_id: IbcSpecId,
@@ -41,8 +41,8 @@ use crate::{
     core::{ChainId, ClientType, IbcInterface, IbcSpec},
     into_value,
     module::{
-        ClientBootstrapModuleInfo, ClientModuleInfo, ConsensusModuleInfo, PluginClient, PluginInfo,
-        ProofModuleInfo, StateModuleInfo,
+        default_block_time, default_request_timeout, ClientBootstrapModuleInfo, ClientModuleInfo,
+        ConsensusModuleInfo, PluginClient, PluginInfo, ProofModuleInfo, StateModuleInfo,
     },
     rpc::{server::Server, VoyagerRpcServer},
     IdThreadClient, ParamsWithItemId, RawClientId, FATAL_JSONRPC_ERROR_CODE,
@@ -60,6 +60,11 @@ pub struct Context {
     interest_filters: HashMap<String, String>,
 
     pub cancellation_token: CancellationToken,
+
+    /// If set, [`SubmitTx`] calls are logged instead of being submitted.
+    ///
+    /// [`SubmitTx`]: crate::call::SubmitTx
+    pub dry_run: bool,
 }
 
 #[derive(macros::Debug)]
@@ -76,6 +81,12 @@ pub struct Modules {
 
     chain_consensus_types: HashMap<ChainId, ConsensusType>,
 
+    /// map of chain id to the configured block time of that chain, in seconds.
+    chain_block_times: HashMap<ChainId, u64>,
+
+    /// map of chain id to the configured request timeout of that chain, in seconds.
+    chain_request_timeouts: HashMap<ChainId, u64>,
+
     client_consensus_types: HashMap<ClientType, ConsensusType>,
 
     // ibc version id => handler
@@ -99,14 +110,10 @@ impl IbcSpecHandlers {
         self.handlers.insert(S::ID, IbcSpecHandler::new::<S>());
     }
 
-    pub fn get(&self, ibc_spec_id: &IbcSpecId) -> RpcResult<&IbcSpecHandler> {
-        self.handlers.get(ibc_spec_id).ok_or_else(|| {
-            ErrorObject::owned(
-                FATAL_JSONRPC_ERROR_CODE,
-                format!("unknown IBC spec `{ibc_spec_id}`"),
-                None::<()>,
-            )
-        })
+    pub fn get(&self, ibc_spec_id: &IbcSpecId) -> Result<&IbcSpecHandler, IbcSpecNotFound> {
+        self.handlers
+            .get(ibc_spec_id)
+            .ok_or_else(|| IbcSpecNotFound(ibc_spec_id.clone()))
     }
 }
 
@@ -344,6 +351,8 @@ impl Context {
             client_bootstrap_modules: Default::default(),
             consensus_modules: Default::default(),
             chain_consensus_types: Default::default(),
+            chain_block_times: Default::default(),
+            chain_request_timeouts: Default::default(),
             client_consensus_types: Default::default(),
             ibc_spec_handlers,
         };
@@ -482,6 +491,8 @@ impl Context {
             |ConsensusModuleInfo {
                  chain_id,
                  consensus_type,
+                 block_time,
+                 request_timeout,
              },
              rpc_client| {
                 let prev = modules
@@ -502,6 +513,14 @@ impl Context {
                     unreachable!()
                 };
 
+                modules
+                    .chain_block_times
+                    .insert(chain_id.clone(), *block_time);
+
+                modules
+                    .chain_request_timeouts
+                    .insert(chain_id.clone(), *request_timeout);
+
                 Ok(())
             },
         )
@@ -641,6 +660,7 @@ impl Context {
             plugins,
             interest_filters,
             cancellation_token,
+            dry_run: false,
         })
     }
 
@@ -710,6 +730,8 @@ impl Modules {
             .cloned()
             .map(|chain_id| ConsensusModuleInfo {
                 consensus_type: self.chain_consensus_types[&chain_id].clone(),
+                block_time: self.chain_block_times[&chain_id],
+                request_timeout: self.chain_request_timeouts[&chain_id],
                 chain_id,
             })
             .collect();
@@ -754,6 +776,35 @@ impl Modules {
             .ok_or_else(|| ConsensusModuleNotFound(chain_id.clone()))
     }
 
+    /// The configured block time of the chain, in seconds. See
+    /// [`ConsensusModuleInfo::block_time`]. Chains without a consensus module use
+    /// [`default_block_time`].
+    pub fn chain_block_time(&self, chain_id: &ChainId) -> u64 {
+        self.chain_block_times
+            .get(chain_id)
+            .copied()
+            .unwrap_or_else(|| {
+                debug!(
+                    %chain_id,
+                    "no consensus module loaded for chain, using the default block time"
+                );
+
+                default_block_time()
+            })
+    }
+
+    /// The configured request timeout of the chain. See
+    /// [`ConsensusModuleInfo::request_timeout`]. Chains without a consensus module use
+    /// [`default_request_timeout`].
+    pub fn chain_request_timeout(&self, chain_id: &ChainId) -> Duration {
+        Duration::from_secs(
+            self.chain_request_timeouts
+                .get(chain_id)
+                .copied()
+                .unwrap_or_else(default_request_timeout),
+        )
+    }
+
     pub fn client_consensus_type<'a, 'b, 'c: 'a>(
         &'a self,
         client_type: &ClientType,
@@ -1037,6 +1088,12 @@ pub struct ConsensusModuleNotFound(pub ChainId);
 
 module_error!(ConsensusModuleNotFound);
 
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("IBC version `{0}` is not supported in this build of voyager")]
+pub struct IbcSpecNotFound(pub IbcSpecId);
+
+module_error!(IbcSpecNotFound);
+
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
 #[error("no module loaded for client bootstrapping on chain `{chain_id}` for client type `{client_type}`")]
 pub struct ClientBootstrapModuleNotFound {
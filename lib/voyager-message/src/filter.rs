@@ -9,11 +9,15 @@ use voyager_vm::{
     Op,
 };
 
-use crate::{module::PluginInfo, VoyagerMessage};
+use crate::{call::Call, module::PluginInfo, VoyagerMessage};
 
 #[derive(Debug, Clone)]
 pub struct JaqInterestFilter {
     pub filters: Vec<(Filter, String)>,
+    /// If set, [`SubmitTx`] calls are never routed to a plugin. See [`Context::dry_run`].
+    ///
+    /// [`Context::dry_run`]: crate::context::Context::dry_run
+    pub dry_run: bool,
 }
 
 impl JaqInterestFilter {
@@ -23,8 +27,15 @@ impl JaqInterestFilter {
                 .into_iter()
                 .map(make_filter)
                 .collect::<anyhow::Result<_>>()?,
+            dry_run: false,
         })
     }
+
+    #[must_use]
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
 }
 
 pub fn make_filter(
@@ -78,6 +89,10 @@ pub fn make_filter(
 
 impl InterestFilter<VoyagerMessage> for JaqInterestFilter {
     fn check_interest<'a>(&'a self, op: &Op<VoyagerMessage>) -> FilterResult<'a> {
+        if self.dry_run && matches!(op, Op::Call(Call::SubmitTx(_))) {
+            return FilterResult::NoInterest;
+        }
+
         let msg_json = Val::from(serde_json::to_value(op.clone()).unwrap());
 
         for (filter, plugin_name) in &self.filters {
@@ -72,6 +72,8 @@ pub mod pass;
 
 pub mod hook;
 
+pub mod metrics;
+
 pub mod rpc;
 
 pub use reconnecting_jsonrpc_ws_client;
@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+
+use axum::{http::StatusCode, routing::get};
+use chain_utils::BoxDynError;
+use prometheus::TextEncoder;
+use tracing::{error, info};
+
+/// Serve the metrics registered in the default prometheus registry on `laddr`, at `/metrics`.
+///
+/// This is intended to be called from [`Plugin::new`](crate::Plugin::new), such that a failure to
+/// bind to `laddr` is reported as a startup error of the plugin.
+pub fn serve(laddr: SocketAddr) -> Result<(), BoxDynError> {
+    let server = axum::Server::try_bind(&laddr)
+        .map_err(|err| format!("unable to bind metrics server to {laddr}: {err}"))?;
+
+    let app = axum::Router::new().route("/metrics", get(metrics));
+
+    info!(%laddr, "serving metrics");
+
+    tokio::spawn(async move {
+        if let Err(err) = server.serve(app.into_make_service()).await {
+            error!(%laddr, %err, "metrics server exited");
+        }
+    });
+
+    Ok(())
+}
+
+async fn metrics() -> Result<String, StatusCode> {
+    TextEncoder::new()
+        .encode_to_string(&prometheus::gather())
+        .map_err(|err| {
+            error!(?err, "could not gather metrics");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
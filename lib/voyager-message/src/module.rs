@@ -81,12 +81,38 @@ pub struct ConsensusModuleInfo {
     pub chain_id: ChainId,
     #[arg(value_parser(|s: &str| ok(ConsensusType::new(s.to_owned()))))]
     pub consensus_type: ConsensusType,
+    /// The approximate block time of this chain, in seconds.
+    ///
+    /// This is used as the interval between polls when waiting for a height or timestamp to be
+    /// reached on this chain.
+    #[arg(long, default_value_t = default_block_time())]
+    #[serde(default = "default_block_time")]
+    pub block_time: u64,
+    /// The maximum time to wait for a response to a request to this chain, in seconds.
+    ///
+    /// Requests that time out are retried, such that an unresponsive endpoint does not block
+    /// processing indefinitely.
+    #[arg(long, default_value_t = default_request_timeout())]
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: u64,
     // REVIEW: Maybe we need this? Do different client types for a single consensus necessarily have the same client and consensus state types?
     // /// The type of client this consensus module provides state for.
     // #[arg(value_parser(|s: &str| ok(ClientType::new(s.to_owned()))))]
     // pub client_type: ClientType,
 }
 
+#[must_use]
+#[inline]
+pub const fn default_block_time() -> u64 {
+    1
+}
+
+#[must_use]
+#[inline]
+pub const fn default_request_timeout() -> u64 {
+    60
+}
+
 impl ConsensusModuleInfo {
     pub fn id(&self) -> String {
         format!("consensus/{}/{}", self.chain_id, self.consensus_type)
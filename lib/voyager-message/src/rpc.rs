@@ -16,6 +16,7 @@ use crate::{
     RawClientId, FATAL_JSONRPC_ERROR_CODE,
 };
 
+pub mod cache;
 pub mod server;
 
 #[rpc(
@@ -0,0 +1,78 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    sync::Mutex,
+};
+
+/// A bounded cache that evicts the least recently used entry once it is full. Only suitable for
+/// data that never changes once inserted, as entries are never invalidated.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    inner: Mutex<LruCacheInner<K, V>>,
+}
+
+#[derive(Debug)]
+struct LruCacheInner<K, V> {
+    capacity: usize,
+    entries: HashMap<K, V>,
+    /// The keys of `entries`, least recently used first.
+    order: VecDeque<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    /// Create a new cache holding at most `capacity` entries. A capacity of 0 disables the cache.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(LruCacheInner {
+                capacity,
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Change the capacity of the cache, evicting the least recently used entries if necessary.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.inner.lock().expect("mutex is poisoned");
+        inner.capacity = capacity;
+        inner.evict();
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut inner = self.inner.lock().expect("mutex is poisoned");
+        let value = inner.entries.get(key)?.clone();
+        inner.touch(key);
+        Some(value)
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut inner = self.inner.lock().expect("mutex is poisoned");
+        if inner.capacity == 0 {
+            return;
+        }
+        if inner.entries.insert(key.clone(), value).is_some() {
+            inner.touch(&key);
+        } else {
+            inner.order.push_back(key);
+            inner.evict();
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> LruCacheInner<K, V> {
+    /// Mark `key` as the most recently used entry.
+    fn touch(&mut self, key: &K) {
+        if let Some(idx) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(idx).expect("index is in bounds; qed;");
+            self.order.push_back(key);
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.order.len() > self.capacity {
+            let key = self.order.pop_front().expect("order is not empty; qed;");
+            self.entries.remove(&key);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     fmt::Debug,
+    future::Future,
     sync::{Arc, OnceLock},
 };
 
@@ -24,8 +25,8 @@ use crate::{
         RawProofModuleClient, RawStateModuleClient,
     },
     rpc::{
-        json_rpc_error_to_error_object, IbcProof, IbcState, SelfClientState, SelfConsensusState,
-        VoyagerRpcServer,
+        cache::LruCache, json_rpc_error_to_error_object, IbcProof, IbcState, SelfClientState,
+        SelfConsensusState, VoyagerRpcServer,
     },
     IbcSpec, IbcStorePathKey, RawClientId, FATAL_JSONRPC_ERROR_CODE,
 };
@@ -36,9 +37,18 @@ pub struct Server {
     item_id: Option<ItemId>,
 }
 
-#[derive(Debug, Clone)]
+/// The default number of self client and consensus states cached by the [`Server`].
+pub const DEFAULT_SELF_STATE_CACHE_SIZE: usize = 1000;
+
+/// The key of a cached self client or consensus state. These are immutable for a given height, and
+/// as such never need to be invalidated.
+type SelfStateCacheKey = (ChainId, ClientType, Height);
+
+#[derive(Debug)]
 pub struct ServerInner {
     modules: OnceLock<Arc<Modules>>,
+    self_client_state_cache: LruCache<SelfStateCacheKey, Value>,
+    self_consensus_state_cache: LruCache<SelfStateCacheKey, Value>,
 }
 
 impl Server {
@@ -47,11 +57,20 @@ impl Server {
         Server {
             inner: Arc::new(ServerInner {
                 modules: OnceLock::new(),
+                self_client_state_cache: LruCache::new(DEFAULT_SELF_STATE_CACHE_SIZE),
+                self_consensus_state_cache: LruCache::new(DEFAULT_SELF_STATE_CACHE_SIZE),
             }),
             item_id: None,
         }
     }
 
+    /// Set the number of self client and consensus states that are cached, each. A size of 0
+    /// disables the cache.
+    pub fn set_self_state_cache_size(&self, size: usize) {
+        self.inner.self_client_state_cache.set_capacity(size);
+        self.inner.self_consensus_state_cache.set_capacity(size);
+    }
+
     pub fn start(&self, modules: Arc<Modules>) {
         let was_not_already_started = self.inner.modules.set(modules).is_ok();
 
@@ -111,6 +130,30 @@ impl Server {
             QueryHeight::Specific(height) => Ok(height),
         }
     }
+
+    /// Run `fut`, failing with a non-fatal error if it does not complete within the configured
+    /// request timeout of `chain_id`. This ensures that an unresponsive chain results in the
+    /// request being retried, instead of blocking the caller indefinitely.
+    async fn with_timeout<T>(
+        &self,
+        chain_id: &ChainId,
+        fut: impl Future<Output = RpcResult<T>>,
+    ) -> RpcResult<T> {
+        let timeout = self.modules()?.chain_request_timeout(chain_id);
+
+        tokio::time::timeout(timeout, fut)
+            .await
+            .unwrap_or_else(|_| {
+                Err(ErrorObject::owned(
+                    -1,
+                    format!(
+                        "request to chain {chain_id} timed out after {}s",
+                        timeout.as_secs()
+                    ),
+                    None::<()>,
+                ))
+            })
+    }
 }
 
 impl ServerInner {
@@ -131,25 +174,27 @@ impl Server {
         finalized: bool,
     ) -> RpcResult<Height> {
         self.span()
-            .in_scope(|| async {
-                trace!("querying latest height");
-
-                let latest_height = self
-                    .inner
-                    .modules()?
-                    .consensus_module(chain_id)
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id)
-                    .query_latest_height(finalized)
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
-
-                trace!(
-                    %latest_height,
-                    "queried latest height"
-                );
-
-                Ok(latest_height)
+            .in_scope(|| {
+                self.with_timeout(chain_id, async {
+                    trace!("querying latest height");
+
+                    let latest_height = self
+                        .inner
+                        .modules()?
+                        .consensus_module(chain_id)
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id)
+                        .query_latest_height(finalized)
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
+
+                    trace!(
+                        %latest_height,
+                        "queried latest height"
+                    );
+
+                    Ok(latest_height)
+                })
             })
             .await
     }
@@ -161,25 +206,27 @@ impl Server {
         finalized: bool,
     ) -> RpcResult<Timestamp> {
         self.span()
-            .in_scope(|| async {
-                trace!("querying latest timestamp");
-
-                let latest_timestamp = self
-                    .inner
-                    .modules()?
-                    .consensus_module(chain_id)
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id)
-                    .query_latest_timestamp(finalized)
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
-
-                trace!(
-                    latest_timestamp = latest_timestamp.as_nanos(),
-                    "queried latest timestamp"
-                );
-
-                Ok(latest_timestamp)
+            .in_scope(|| {
+                self.with_timeout(chain_id, async {
+                    trace!("querying latest timestamp");
+
+                    let latest_timestamp = self
+                        .inner
+                        .modules()?
+                        .consensus_module(chain_id)
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id)
+                        .query_latest_timestamp(finalized)
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
+
+                    trace!(
+                        latest_timestamp = latest_timestamp.as_nanos(),
+                        "queried latest timestamp"
+                    );
+
+                    Ok(latest_timestamp)
+                })
             })
             .await
     }
@@ -192,26 +239,28 @@ impl Server {
         client_id: RawClientId,
     ) -> RpcResult<ClientInfo> {
         self.span()
-            .in_scope(|| async {
-                trace!("fetching client info");
-
-                let client_info = self
-                    .inner
-                    .modules()?
-                    .state_module(chain_id, ibc_spec_id)
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id)
-                    .client_info_raw(client_id.clone())
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
-
-                trace!(
-                    %client_info.ibc_interface,
-                    %client_info.client_type,
-                    "fetched client info"
-                );
-
-                Ok(client_info)
+            .in_scope(|| {
+                self.with_timeout(chain_id, async {
+                    trace!("fetching client info");
+
+                    let client_info = self
+                        .inner
+                        .modules()?
+                        .state_module(chain_id, ibc_spec_id)
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id)
+                        .client_info_raw(client_id.clone())
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
+
+                    trace!(
+                        %client_info.ibc_interface,
+                        %client_info.client_type,
+                        "fetched client info"
+                    );
+
+                    Ok(client_info)
+                })
             })
             .await
     }
@@ -225,60 +274,65 @@ impl Server {
         client_id: RawClientId,
     ) -> RpcResult<ClientStateMeta> {
         self.span()
-            .in_scope(|| async {
-                trace!("fetching client meta");
+            .in_scope(|| {
+                self.with_timeout(chain_id, async {
+                    trace!("fetching client meta");
 
-                let height = self.query_height(chain_id, at).await?;
+                    let height = self.query_height(chain_id, at).await?;
 
-                let modules = self.inner.modules()?;
+                    let modules = self.inner.modules()?;
 
-                let state_module = modules
-                    .state_module(chain_id, ibc_spec_id)?
-                    .with_id(self.item_id);
+                    let state_module = modules
+                        .state_module(chain_id, ibc_spec_id)?
+                        .with_id(self.item_id);
 
-                let client_info = state_module
-                    .client_info_raw(client_id.clone())
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
+                    let client_info = state_module
+                        .client_info_raw(client_id.clone())
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
 
-                let client_state = state_module
-                    .query_ibc_state_raw(
-                        height,
-                        (self
-                            .modules()?
+                    let client_state_path =
+                        (modules
                             .ibc_spec_handlers
-                            .handlers
-                            .get(ibc_spec_id)
-                            .unwrap()
+                            .get(ibc_spec_id)?
                             .client_state_path)(client_id.clone())
-                        .unwrap(),
-                    )
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
-
-                trace!(%client_state);
-
-                let meta = modules
-                    .client_module(
-                        &client_info.client_type,
-                        &client_info.ibc_interface,
-                        ibc_spec_id,
-                    )
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id)
-                    .decode_client_state_meta(client_state.as_str().unwrap().parse().unwrap())
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
-
-                trace!(
-                    client_state_meta.height = %meta.counterparty_height,
-                    client_state_meta.chain_id = %meta.chain_id,
-                    %client_info.ibc_interface,
-                    %client_info.client_type,
-                    "fetched client meta"
-                );
-
-                Ok(meta)
+                        .map_err(|e| {
+                            ErrorObject::owned(
+                                FATAL_JSONRPC_ERROR_CODE,
+                                format!("invalid client id `{}`: {e:#}", client_id.0),
+                                None::<()>,
+                            )
+                        })?;
+
+                    let client_state = state_module
+                        .query_ibc_state_raw(height, client_state_path)
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
+
+                    trace!(%client_state);
+
+                    let meta = modules
+                        .client_module(
+                            &client_info.client_type,
+                            &client_info.ibc_interface,
+                            ibc_spec_id,
+                        )
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id)
+                        .decode_client_state_meta(client_state.as_str().unwrap().parse().unwrap())
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
+
+                    trace!(
+                        client_state_meta.height = %meta.counterparty_height,
+                        client_state_meta.chain_id = %meta.chain_id,
+                        %client_info.ibc_interface,
+                        %client_info.client_type,
+                        "fetched client meta"
+                    );
+
+                    Ok(meta)
+                })
             })
             .await
     }
@@ -291,27 +345,29 @@ impl Server {
         path: <P::Spec as IbcSpec>::StorePath,
     ) -> RpcResult<IbcState<P::Value>> {
         self.span()
-            .in_scope(|| async {
-                trace!("fetching ibc state");
-
-                let state_module = self
-                    .inner
-                    .modules()?
-                    .state_module(chain_id, &P::Spec::ID)
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id);
-
-                let state = state_module
-                    .query_ibc_state_raw(height, into_value(path.clone()))
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
-
-                // TODO: Use valuable here
-                trace!(%state, "fetched ibc state");
-
-                Ok(IbcState {
-                    height,
-                    state: serde_json::from_value(state).unwrap(),
+            .in_scope(|| {
+                self.with_timeout(chain_id, async {
+                    trace!("fetching ibc state");
+
+                    let state_module = self
+                        .inner
+                        .modules()?
+                        .state_module(chain_id, &P::Spec::ID)
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id);
+
+                    let state = state_module
+                        .query_ibc_state_raw(height, into_value(path.clone()))
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
+
+                    // TODO: Use valuable here
+                    trace!(%state, "fetched ibc state");
+
+                    Ok(IbcState {
+                        height,
+                        state: serde_json::from_value(state).unwrap(),
+                    })
                 })
             })
             .await
@@ -333,25 +389,27 @@ impl Server {
         path: <P::Spec as IbcSpec>::StorePath,
     ) -> RpcResult<IbcProof> {
         self.span()
-            .in_scope(|| async {
-                trace!("fetching ibc state");
-
-                let proof_module = self
-                    .inner
-                    .modules()?
-                    .proof_module(chain_id, &P::Spec::ID)
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id);
-
-                let proof = proof_module
-                    .query_ibc_proof_raw(height, into_value(path.clone()))
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
-
-                // TODO: Use valuable here
-                trace!(%proof, "fetched ibc proof");
-
-                Ok(IbcProof { height, proof })
+            .in_scope(|| {
+                self.with_timeout(chain_id, async {
+                    trace!("fetching ibc state");
+
+                    let proof_module = self
+                        .inner
+                        .modules()?
+                        .proof_module(chain_id, &P::Spec::ID)
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id);
+
+                    let proof = proof_module
+                        .query_ibc_proof_raw(height, into_value(path.clone()))
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
+
+                    // TODO: Use valuable here
+                    trace!(%proof, "fetched ibc proof");
+
+                    Ok(IbcProof { height, proof })
+                })
             })
             .await
     }
@@ -364,25 +422,39 @@ impl Server {
         height: Height,
     ) -> RpcResult<SelfClientState> {
         self.span()
-            .in_scope(|| async {
-                trace!("querying self client state");
+            .in_scope(|| {
+                self.with_timeout(&chain_id, async {
+                    trace!("querying self client state");
 
-                let client_bootstrap_module = self
-                    .inner
-                    .modules()?
-                    .client_bootstrap_module(&chain_id, &client_type)
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id);
+                    let cache_key = (chain_id.clone(), client_type.clone(), height);
 
-                let state = client_bootstrap_module
-                    .self_client_state(height)
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
+                    if let Some(state) = self.inner.self_client_state_cache.get(&cache_key) {
+                        trace!("self client state cache hit");
+
+                        return Ok(SelfClientState { height, state });
+                    }
+
+                    let client_bootstrap_module = self
+                        .inner
+                        .modules()?
+                        .client_bootstrap_module(&chain_id, &client_type)
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id);
 
-                // TODO: Use valuable here
-                trace!(%state, "fetched self client state");
+                    let state = client_bootstrap_module
+                        .self_client_state(height)
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
 
-                Ok(SelfClientState { height, state })
+                    // TODO: Use valuable here
+                    trace!(%state, "fetched self client state");
+
+                    self.inner
+                        .self_client_state_cache
+                        .insert(cache_key, state.clone());
+
+                    Ok(SelfClientState { height, state })
+                })
             })
             .await
     }
@@ -395,27 +467,41 @@ impl Server {
         height: QueryHeight,
     ) -> RpcResult<SelfConsensusState> {
         self.span()
-            .in_scope(|| async {
-                trace!("querying self consensus state");
+            .in_scope(|| {
+                self.with_timeout(&chain_id, async {
+                    trace!("querying self consensus state");
 
-                let client_bootstrap_module = self
-                    .inner
-                    .modules()?
-                    .client_bootstrap_module(&chain_id, &client_type)
-                    .map_err(fatal_error)?
-                    .with_id(self.item_id);
+                    let client_bootstrap_module = self
+                        .inner
+                        .modules()?
+                        .client_bootstrap_module(&chain_id, &client_type)
+                        .map_err(fatal_error)?
+                        .with_id(self.item_id);
 
-                let height = self.query_height(&chain_id, height).await?;
+                    let height = self.query_height(&chain_id, height).await?;
 
-                let state = client_bootstrap_module
-                    .self_consensus_state(height)
-                    .await
-                    .map_err(json_rpc_error_to_error_object)?;
+                    let cache_key = (chain_id.clone(), client_type.clone(), height);
+
+                    if let Some(state) = self.inner.self_consensus_state_cache.get(&cache_key) {
+                        trace!("self consensus state cache hit");
+
+                        return Ok(SelfConsensusState { height, state });
+                    }
 
-                // TODO: Use valuable here
-                trace!(%state, "fetched self consensus state");
+                    let state = client_bootstrap_module
+                        .self_consensus_state(height)
+                        .await
+                        .map_err(json_rpc_error_to_error_object)?;
 
-                Ok(SelfConsensusState { height, state })
+                    // TODO: Use valuable here
+                    trace!(%state, "fetched self consensus state");
+
+                    self.inner
+                        .self_consensus_state_cache
+                        .insert(cache_key, state.clone());
+
+                    Ok(SelfConsensusState { height, state })
+                })
             })
             .await
     }
@@ -5,12 +5,17 @@ use tokio::time::sleep;
 use tracing::error;
 use unionlabs::ErrorReporter;
 
-use crate::{defer, now, seq, BoxDynError, Captures, Context, Queue, QueueError, QueueMessage};
+use crate::{
+    defer,
+    metrics::{record_dropped, DropReason},
+    now, seq, BoxDynError, Captures, Context, Queue, QueueError, QueueMessage, DEFAULT_MAX_DEPTH,
+};
 
 pub struct Engine<'a, T: QueueMessage, Q: Queue<T>> {
     store: &'a T::Context,
     queue: &'a Q,
     optimizer: &'a T::Filter,
+    max_depth: usize,
 }
 
 impl<'a, T: QueueMessage, Q: Queue<T>> Engine<'a, T, Q> {
@@ -19,9 +24,18 @@ impl<'a, T: QueueMessage, Q: Queue<T>> Engine<'a, T, Q> {
             store,
             queue,
             optimizer: filter,
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 
+    /// Set the maximum nesting depth of ops processed by this engine. See
+    /// [`Context::with_max_depth`].
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
     pub fn run(self) -> impl Stream<Item = Result<T::Data, BoxDynError>> + Send + Captures<'a> {
         futures::stream::try_unfold(self, |this| async move {
             sleep(Duration::from_millis(10)).await;
@@ -42,12 +56,16 @@ impl<'a, T: QueueMessage, Q: Queue<T>> Engine<'a, T, Q> {
             self.queue
                 .process::<_, _, Option<T::Data>>(self.optimizer, |op, id| {
                     op.clone()
-                        .process(Context::new(id, self.store), 0)
+                        .process(
+                            Context::new(id, self.store).with_max_depth(self.max_depth),
+                            0,
+                        )
                         .map(|res| match res {
                             Ok(op) => (None, Ok(op.into_iter().collect())),
                             Err(QueueError::Fatal(fatal)) => {
                                 let full_err = ErrorReporter(&*fatal);
                                 error!(error = %full_err, "fatal error");
+                                record_dropped(DropReason::Fatal);
                                 (None, Err(full_err.to_string()))
                             }
                             Err(QueueError::Retry(retry)) => {
@@ -1,5 +1,5 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashSet},
     future::Future,
     sync::{
         atomic::{AtomicU32, Ordering},
@@ -19,30 +19,65 @@ use crate::{
 
 #[derive(DebugNoBound, CloneNoBound)]
 pub struct InMemoryQueue<T: QueueMessage> {
+    deduplicate: bool,
     idx: Arc<AtomicU32>,
     ready: Arc<Mutex<BTreeMap<u32, Item<T>>>>,
     done: Arc<Mutex<BTreeMap<u32, Item<T>>>>,
     #[allow(clippy::type_complexity)]
     optimizer_queue: Arc<Mutex<BTreeMap<String, BTreeMap<u32, Item<T>>>>>,
+    /// The serialized ops of all pending items, only maintained if `deduplicate` is set.
+    pending: Arc<Mutex<HashSet<String>>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InMemoryQueueConfig {
+    /// Skip inserting items that are identical to an item that is already pending, including the
+    /// items produced by processing and optimizing other items.
+    pub deduplicate: bool,
 }
 
 #[derive(DebugNoBound, CloneNoBound)]
 pub(crate) struct Item<T: QueueMessage> {
     #[allow(dead_code)] // used in debug
     parents: Vec<u32>,
+    /// The id of the top-level item this item descends from.
+    correlation_id: u32,
     op: Op<T>,
 }
 
+impl<T: QueueMessage> InMemoryQueue<T> {
+    fn pending_key(&self, op: &Op<T>) -> Option<String> {
+        self.deduplicate
+            .then(|| serde_json::to_string(op).expect("serialization is infallible; qed;"))
+    }
+
+    /// Mark `op` as pending, returning `false` if an identical item is already pending.
+    fn insert_pending(&self, pending: &mut HashSet<String>, op: &Op<T>) -> bool {
+        match self.pending_key(op) {
+            Some(key) => pending.insert(key),
+            None => true,
+        }
+    }
+
+    fn remove_pending(&self, pending: &mut HashSet<String>, op: &Op<T>) {
+        if let Some(key) = self.pending_key(op) {
+            pending.remove(&key);
+        }
+    }
+}
+
 impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
     type Error = std::convert::Infallible;
-    type Config = ();
+    type Config = InMemoryQueueConfig;
 
-    fn new(_cfg: Self::Config) -> impl Future<Output = Result<Self, Self::Error>> {
+    fn new(cfg: Self::Config) -> impl Future<Output = Result<Self, Self::Error>> {
         futures::future::ok(Self {
+            deduplicate: cfg.deduplicate,
             idx: Arc::new(AtomicU32::default()),
             done: Arc::new(Mutex::new(BTreeMap::default())),
             ready: Arc::new(Mutex::new(BTreeMap::default())),
             optimizer_queue: Arc::new(Mutex::new(BTreeMap::default())),
+            pending: Arc::new(Mutex::new(HashSet::default())),
         })
     }
 
@@ -55,26 +90,30 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
 
         let mut optimizer_queue = self.optimizer_queue.lock().expect("mutex is poisoned");
         let mut ready = self.ready.lock().expect("mutex is poisoned");
+        let mut pending = self.pending.lock().expect("mutex is poisoned");
 
         for op in op.normalize() {
-            match filter.check_interest(&op) {
+            if !self.insert_pending(&mut pending, &op) {
+                debug!("identical item is already pending, skipping");
+                continue;
+            }
+
+            let id = self.idx.fetch_add(1, Ordering::SeqCst);
+            let item = Item {
+                parents: vec![],
+                correlation_id: id,
+                op,
+            };
+
+            match filter.check_interest(&item.op) {
                 FilterResult::Interest(tag) => {
-                    optimizer_queue.entry(tag.to_owned()).or_default().insert(
-                        self.idx.fetch_add(1, Ordering::SeqCst),
-                        Item {
-                            parents: vec![],
-                            op,
-                        },
-                    );
+                    optimizer_queue
+                        .entry(tag.to_owned())
+                        .or_default()
+                        .insert(id, item);
                 }
                 FilterResult::NoInterest => {
-                    ready.insert(
-                        self.idx.fetch_add(1, Ordering::SeqCst),
-                        Item {
-                            parents: vec![],
-                            op,
-                        },
-                    );
+                    ready.insert(id, item);
                 }
             }
         }
@@ -98,6 +137,13 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
             let mut queue = self.ready.lock().expect("mutex is poisoned");
             let op = queue.pop_first();
 
+            if let Some((_, item)) = &op {
+                self.remove_pending(
+                    &mut self.pending.lock().expect("mutex is poisoned"),
+                    &item.op,
+                );
+            }
+
             drop(queue);
 
             op
@@ -105,7 +151,7 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
 
         match op {
             Some((id, item)) => {
-                let span = info_span!("processing item", %id);
+                let span = info_span!("processing item", %id, correlation_id = item.correlation_id);
 
                 self.done
                     .lock()
@@ -123,14 +169,21 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
                         let mut optimizer_queue =
                             self.optimizer_queue.lock().expect("mutex is poisoned");
                         let mut ready = self.ready.lock().expect("mutex is poisoned");
+                        let mut pending = self.pending.lock().expect("mutex is poisoned");
 
                         for op in ops.into_iter().flat_map(Op::normalize) {
+                            if !self.insert_pending(&mut pending, &op) {
+                                debug!("identical item is already pending, skipping");
+                                continue;
+                            }
+
                             match filter.check_interest(&op) {
                                 FilterResult::Interest(tag) => {
                                     optimizer_queue.entry(tag.to_owned()).or_default().insert(
                                         self.idx.fetch_add(1, Ordering::SeqCst),
                                         Item {
                                             parents: vec![id],
+                                            correlation_id: item.correlation_id,
                                             op,
                                         },
                                     );
@@ -140,6 +193,7 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
                                         self.idx.fetch_add(1, Ordering::SeqCst),
                                         Item {
                                             parents: vec![id],
+                                            correlation_id: item.correlation_id,
                                             op,
                                         },
                                     );
@@ -176,6 +230,12 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
                     return Ok(());
                 };
 
+                let mut pending = self.pending.lock().expect("poisoned");
+                for item in tagged_optimizer_queue.values() {
+                    self.remove_pending(&mut pending, &item.op);
+                }
+
+                drop(pending);
                 drop(optimizer_queue);
 
                 tagged_optimizer_queue
@@ -183,6 +243,15 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
 
             let (ids, ops): (Vec<_>, Vec<_>) = tagged_optimizer_queue.clone().into_iter().unzip();
 
+            // items created from several parents are attributed to the first one
+            let correlation_ids = ops
+                .iter()
+                .map(|item| item.correlation_id)
+                .collect::<Vec<_>>();
+            let get_correlation_id = |id: u32, parents_idxs: &[usize]| {
+                parents_idxs.first().map_or(id, |&i| correlation_ids[i])
+            };
+
             let res = optimizer
                 .run_pass(ops.into_iter().map(|item| item.op).collect())
                 .await
@@ -191,24 +260,39 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
             let mut optimizer_queue = self.optimizer_queue.lock().expect("poisoned");
             let mut ready = self.ready.lock().expect("poisoned");
             let mut done = self.done.lock().expect("poisoned");
+            let mut pending = self.pending.lock().expect("poisoned");
 
             done.append(&mut tagged_optimizer_queue.clone());
 
             for (parents_idxs, op) in res.ready {
+                if !self.insert_pending(&mut pending, &op) {
+                    debug!("identical item is already pending, skipping");
+                    continue;
+                }
+
+                let id = self.idx.fetch_add(1, Ordering::SeqCst);
                 ready.insert(
-                    self.idx.fetch_add(1, Ordering::SeqCst),
+                    id,
                     Item {
                         parents: parents_idxs.iter().map(|&i| &ids[i]).copied().collect(),
+                        correlation_id: get_correlation_id(id, &parents_idxs),
                         op,
                     },
                 );
             }
 
             for (parents_idxs, op, tag) in res.optimize_further {
+                if !self.insert_pending(&mut pending, &op) {
+                    debug!("identical item is already pending, skipping");
+                    continue;
+                }
+
+                let id = self.idx.fetch_add(1, Ordering::SeqCst);
                 optimizer_queue.entry(tag.clone()).or_default().insert(
-                    self.idx.fetch_add(1, Ordering::SeqCst),
+                    id,
                     Item {
                         parents: parents_idxs.iter().map(|&i| &ids[i]).copied().collect(),
+                        correlation_id: get_correlation_id(id, &parents_idxs),
                         op,
                     },
                 );
@@ -217,4 +301,35 @@ impl<T: QueueMessage> Queue<T> for InMemoryQueue<T> {
             Ok(())
         }
     }
+
+    fn remove<'a, P>(
+        &'a self,
+        predicate: P,
+    ) -> impl Future<Output = Result<u64, Self::Error>> + Send + 'a
+    where
+        P: Fn(&Op<T>) -> bool + Send + Sync + 'a,
+    {
+        let mut optimizer_queue = self.optimizer_queue.lock().expect("mutex is poisoned");
+        let mut ready = self.ready.lock().expect("mutex is poisoned");
+        let mut pending = self.pending.lock().expect("mutex is poisoned");
+
+        let mut removed = 0;
+
+        for items in optimizer_queue.values_mut().chain([&mut *ready]) {
+            items.retain(|_, item| {
+                let matches = predicate(&item.op);
+                if matches {
+                    self.remove_pending(&mut pending, &item.op);
+                    removed += 1;
+                }
+                !matches
+            });
+        }
+
+        optimizer_queue.retain(|_, items| !items.is_empty());
+
+        debug!(%removed, "removed items");
+
+        futures::future::ok(removed)
+    }
 }
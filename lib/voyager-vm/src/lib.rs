@@ -24,11 +24,16 @@ use unionlabs::{
     never::Never,
 };
 
-use crate::{filter::InterestFilter, pass::Pass};
+use crate::{
+    filter::InterestFilter,
+    metrics::{record_dropped, DropReason},
+    pass::Pass,
+};
 
 pub mod engine;
 pub mod filter;
 pub mod in_memory;
+pub mod metrics;
 pub mod pass;
 
 #[cfg(test)]
@@ -52,6 +57,21 @@ pub trait Queue<T: QueueMessage>: Debug + Clone + Send + Sync + Sized + 'static
         filter: &'a T::Filter,
     ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'a;
 
+    /// Enqueue an item into the queue at most once per idempotency `key`. If an item has already
+    /// been enqueued with `key`, this is a no-op. This allows for safely retrying an enqueue when
+    /// it is unknown whether a previous attempt was committed.
+    ///
+    /// The default implementation does not track keys, and falls back to [`Queue::enqueue`].
+    fn enqueue_idempotent<'a>(
+        &'a self,
+        key: &'a str,
+        item: Op<T>,
+        filter: &'a T::Filter,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'a {
+        let _ = key;
+        self.enqueue(item, filter)
+    }
+
     /// Process the item at the front of the queue, if there is one. New items will be pre-processed by `filter` before being reenqueued.
     ///
     /// All items will be enqueued to be optimized, unless marked as ready by `filter`.
@@ -70,6 +90,18 @@ pub trait Queue<T: QueueMessage>: Debug + Clone + Send + Sync + Sized + 'static
         tag: &'a str,
         optimizer: &'a O,
     ) -> impl Future<Output = Result<(), Either<Self::Error, O::Error>>> + Send + 'a;
+
+    /// Remove all pending items matching `predicate` from the queue, returning the number of
+    /// items removed. This is intended for dropping known-bad items (such as a message that
+    /// fails forever) without having to touch the underlying storage directly.
+    ///
+    /// Items that are currently being processed are not removed.
+    fn remove<'a, P>(
+        &'a self,
+        predicate: P,
+    ) -> impl Future<Output = Result<u64, Self::Error>> + Send + 'a
+    where
+        P: Fn(&Op<T>) -> bool + Send + Sync + 'a;
 }
 
 /// The ID of an item in the queue.
@@ -209,8 +241,13 @@ pub trait ContextT: Send + Sync {}
 
 impl ContextT for () {}
 
+/// The default maximum nesting depth of an [`Op`] that will be processed. See
+/// [`Context::with_max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
 pub struct Context<T> {
     id: ItemId,
+    max_depth: usize,
     inner: T,
 }
 
@@ -224,12 +261,28 @@ impl<T> Deref for Context<T> {
 
 impl<T> Context<T> {
     pub fn new(id: ItemId, inner: T) -> Self {
-        Self { id, inner }
+        Self {
+            id,
+            max_depth: DEFAULT_MAX_DEPTH,
+            inner,
+        }
+    }
+
+    /// Set the maximum nesting depth of ops processed with this context. Ops nested deeper than
+    /// this will be dropped instead of being processed.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
     }
 
     pub fn id(&self) -> ItemId {
         self.id
     }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
 }
 
 pub type BoxDynError = Box<dyn Error + Send + Sync + 'static>;
@@ -245,6 +298,17 @@ impl<T: QueueMessage> Op<T> {
         trace!(%depth, "handling message");
 
         let fut = async move {
+            if depth > store.max_depth() {
+                error!(
+                    %depth,
+                    max_depth = store.max_depth(),
+                    op = %serde_json::to_string(&self).expect("serialization is infallible; qed;"),
+                    "max op depth exceeded, dropping op"
+                );
+                record_dropped(DropReason::MaxDepthExceeded);
+                return Ok(None);
+            }
+
             match self {
                 Op::Data(data) => {
                     // TODO: Use valuable here
@@ -252,6 +316,7 @@ impl<T: QueueMessage> Op<T> {
                         data = %serde_json::to_string(&data).expect("serialization is infallible; qed;"),
                         "received data outside of an aggregation"
                     );
+                    record_dropped(DropReason::DataOutsideAggregation);
                     Ok(None)
                 }
 
@@ -304,6 +369,9 @@ impl<T: QueueMessage> Op<T> {
                     mut data,
                     receiver,
                 }) => {
+                    let callback = receiver.name();
+                    let chain_id = receiver.chain_id();
+
                     if let Some(op) = queue.pop_front() {
                         match op {
                             Op::Data(d) => {
@@ -325,8 +393,24 @@ impl<T: QueueMessage> Op<T> {
                             }
                         }
 
+                        debug!(
+                            %callback,
+                            chain_id = chain_id.as_deref(),
+                            remaining = queue.len(),
+                            collected = data.len(),
+                            "aggregate {callback} waiting on {} data items",
+                            queue.len()
+                        );
+
                         Ok(Some(promise(queue, data, receiver)))
                     } else {
+                        info!(
+                            %callback,
+                            chain_id = chain_id.as_deref(),
+                            collected = data.len(),
+                            "aggregate {callback} ready"
+                        );
+
                         // queue is empty, handle op
                         receiver.process(store, data).await.map(Some)
                     }
@@ -351,7 +435,31 @@ impl<T: QueueMessage> Op<T> {
         Box::pin(fut)
     }
 
+    /// Normalize this op into a list of ops that can be processed independently, simplifying the
+    /// op graph in the process:
+    ///
+    /// - nested [`Op::Seq`]s and [`Op::Conc`]s are flattened, and empty ones are removed
+    /// - [`Op::Data`] at the front of a [`Op::Seq`] or anywhere in a [`Op::Conc`] is extracted
+    /// - consecutive [`Op::Defer`]s in a [`Op::Seq`] are collapsed into the latest one
+    /// - [`Op::Noop`]s are removed
     pub fn normalize(self) -> Vec<Op<T>> {
+        /// Collapse consecutive [`Op::Defer`]s into the latest one, as deferring until `a` and then
+        /// until `b` is equivalent to deferring until `max(a, b)`.
+        fn collapse_defers<T: QueueMessage>(ops: impl IntoIterator<Item = Op<T>>) -> Vec<Op<T>> {
+            let mut collapsed: Vec<Op<T>> = vec![];
+
+            for op in ops {
+                match (collapsed.last_mut(), op) {
+                    (Some(Op::Defer { until }), Op::Defer { until: next }) => {
+                        *until = (*until).max(next);
+                    }
+                    (_, op) => collapsed.push(op),
+                }
+            }
+
+            collapsed
+        }
+
         pub fn go<T: QueueMessage>(op: Op<T>) -> Vec<Op<T>> {
             match op {
                 Op::Data(data) => vec![Op::Data(data)],
@@ -373,22 +481,20 @@ impl<T: QueueMessage> Op<T> {
                             let non_data_ops = ops.split_off(first_non_data_op_idx);
                             let data_ops = ops;
 
-                            if non_data_ops.is_empty() {
-                                data_ops
-                            } else {
-                                data_ops
-                                    .into_iter()
-                                    .chain([Op::Seq(
-                                        non_data_ops
-                                            .into_iter()
-                                            .flat_map(|op| match op {
-                                                Op::Seq(seq) => seq.into(),
-                                                op => vec![op],
-                                            })
-                                            .collect(),
-                                    )])
-                                    .collect()
-                            }
+                            let mut non_data_ops =
+                                collapse_defers(non_data_ops.into_iter().flat_map(|op| match op {
+                                    Op::Seq(seq) => seq.into(),
+                                    op => vec![op],
+                                }));
+
+                            data_ops
+                                .into_iter()
+                                .chain(match non_data_ops.len() {
+                                    0 => None,
+                                    1 => non_data_ops.pop(),
+                                    2.. => Some(Op::Seq(non_data_ops.into())),
+                                })
+                                .collect()
                         }
                     }
                 }
@@ -468,6 +574,16 @@ pub trait CallT<T: QueueMessage> {
 }
 
 pub trait CallbackT<T: QueueMessage> {
+    /// A short name identifying this callback in the aggregation events.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_owned()
+    }
+
+    /// The chain this callback relates to, if any.
+    fn chain_id(&self) -> Option<String> {
+        None
+    }
+
     fn process(
         self,
         ctx: Context<&T::Context>,
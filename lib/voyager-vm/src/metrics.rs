@@ -0,0 +1,52 @@
+use std::sync::LazyLock;
+
+use prometheus::{register_int_counter_vec, IntCounterVec};
+
+pub static DROPPED_MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "voyager_dropped_messages_total",
+        "The amount of messages that were dropped without being fully processed, by reason.",
+        &["reason"],
+    )
+    .unwrap()
+});
+
+/// The reason a message was dropped by the queue.
+///
+/// This is used as the `reason` label of [`DROPPED_MESSAGES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    /// The message returned a fatal error and will not be retried.
+    Fatal,
+    /// An [`Op::Data`](crate::Op::Data) bubbled up to the top level without being used in an
+    /// aggregation.
+    DataOutsideAggregation,
+    /// The message was nested deeper than the configured maximum depth.
+    MaxDepthExceeded,
+    /// A packet timed out before it could be relayed.
+    PacketTimedOut,
+    /// An event was on a channel that the relayer is not configured to relay.
+    ChannelFiltered,
+    /// A message did not complete before its deadline. This is recorded in addition to
+    /// [`DropReason::Fatal`].
+    DeadlineExceeded,
+}
+
+impl DropReason {
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::Fatal => "fatal",
+            DropReason::DataOutsideAggregation => "data_outside_aggregation",
+            DropReason::MaxDepthExceeded => "max_depth_exceeded",
+            DropReason::PacketTimedOut => "packet_timed_out",
+            DropReason::ChannelFiltered => "channel_filtered",
+            DropReason::DeadlineExceeded => "deadline_exceeded",
+        }
+    }
+}
+
+/// Record that a message was dropped for the specified reason.
+pub fn record_dropped(reason: DropReason) {
+    DROPPED_MESSAGES.with_label_values(&[reason.as_str()]).inc();
+}
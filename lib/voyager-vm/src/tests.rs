@@ -1,9 +1,15 @@
 use macros::model;
 
 use crate::{
-    call, conc, data, defer, noop, now, promise, seq,
-    tests::utils::{BuildPrintAbc, DataA, DataB, DataC, FetchA, FetchB, PrintAbc, SimpleMessage},
-    CallT, CallbackT, Context, Op, QueueError, QueueMessage, VecDeque,
+    call, conc, data, defer,
+    in_memory::{InMemoryQueue, InMemoryQueueConfig},
+    noop, now, promise, seq,
+    tests::utils::{
+        run_to_completion, BuildPrintAbc, DataA, DataB, DataC, FetchA, FetchB, FetchC, MockChain,
+        MockData, MockMessage, MsgRecvPacket, PrintAbc, RelayPacket, SendPacket, SimpleData,
+        SimpleMessage,
+    },
+    CallT, CallbackT, Context, ItemId, Op, Queue, QueueError, QueueMessage, VecDeque,
 };
 
 pub mod utils;
@@ -52,10 +58,8 @@ fn flatten() {
         defer(5),
     ]);
 
-    assert_eq!(
-        op.normalize(),
-        vec![seq([defer(1), defer(2), defer(3), defer(4), defer(5)])]
-    );
+    // consecutive defers are collapsed into the latest one
+    assert_eq!(op.normalize(), vec![defer(5)]);
 
     let op = seq::<UnitMessage>([defer(1)]);
     assert_eq!(op.normalize(), vec![defer(1)]);
@@ -76,6 +80,41 @@ fn flatten() {
     assert_eq!(op.normalize(), vec![]);
 }
 
+#[test]
+fn collapse_consecutive_defers() {
+    // the latest defer is kept, regardless of order
+    let op = seq::<UnitMessage>([defer(3), seq([defer(1), defer(2)]), call(())]);
+    assert_eq!(op.normalize(), vec![seq([defer(3), call(())])]);
+
+    // defers separated by another op are not collapsed
+    let op = seq::<UnitMessage>([defer(1), call(()), defer(2), seq([defer(3)]), call(())]);
+    assert_eq!(
+        op.normalize(),
+        vec![seq([defer(1), call(()), defer(3), call(())])]
+    );
+
+    // empty seqs between defers are removed, making the defers consecutive
+    let op = seq::<UnitMessage>([defer(1), seq([]), seq([noop()]), defer(2), call(())]);
+    assert_eq!(op.normalize(), vec![seq([defer(2), call(())])]);
+
+    // defers in separate branches of a conc are independent
+    let op = conc::<UnitMessage>([
+        seq([defer(1), defer(2), call(())]),
+        seq([defer(3), call(())]),
+    ]);
+    assert_eq!(
+        op.normalize(),
+        vec![seq([defer(2), call(())]), seq([defer(3), call(())])]
+    );
+}
+
+#[test]
+fn seq_data_single_op() {
+    // a single op remaining after extracting data is not wrapped in a seq
+    let op = seq::<UnitMessage>([data(()), seq([defer(1), defer(2)])]);
+    assert_eq!(op.normalize(), vec![data(()), defer(2)]);
+}
+
 #[test]
 fn nested_seq_conc_single() {
     // any nesting level of seq and conc should be handled in a single pass
@@ -287,3 +326,162 @@ fn seq_conc_conc() {
 
     assert_eq!(op.normalize(), expected_output);
 }
+
+#[tokio::test]
+async fn max_depth_exceeded_drops_op() {
+    let op = seq::<UnitMessage>([seq([seq([call(())])])]);
+
+    // the call is at depth 3, and is dropped
+    let ctx = Context::new(ItemId::new(0).unwrap(), &()).with_max_depth(2);
+    assert_eq!(
+        op.clone().process(ctx, 0).await.unwrap(),
+        Some(seq([seq([seq([])])]))
+    );
+
+    // the call is processed
+    let ctx = Context::new(ItemId::new(0).unwrap(), &()).with_max_depth(3);
+    assert_eq!(
+        op.process(ctx, 0).await.unwrap(),
+        Some(seq([seq([seq([noop()])])]))
+    );
+}
+
+#[tokio::test]
+async fn in_memory_queue_deduplicates_pending_items() {
+    for (deduplicate, expected) in [(false, 3), (true, 2)] {
+        let queue = InMemoryQueue::<UnitMessage>::new(InMemoryQueueConfig { deduplicate })
+            .await
+            .unwrap();
+
+        for op in [defer(1), defer(1), defer(2)] {
+            queue.enqueue(op, &()).await.unwrap();
+        }
+
+        let mut processed = 0;
+        while queue
+            .process(&(), |_, _| async { ((), Ok(vec![])) })
+            .await
+            .unwrap()
+            .is_some()
+        {
+            processed += 1;
+        }
+
+        assert_eq!(processed, expected);
+    }
+}
+
+#[tokio::test]
+async fn in_memory_queue_deduplicates_child_items() {
+    let queue = InMemoryQueue::<UnitMessage>::new(InMemoryQueueConfig { deduplicate: true })
+        .await
+        .unwrap();
+
+    queue.enqueue(defer(1), &()).await.unwrap();
+
+    let mut processed = vec![];
+    while let Some(op) = queue
+        .process(&(), |op, _| async move {
+            let children = if op == defer(1) {
+                vec![defer(2), defer(2)]
+            } else {
+                vec![]
+            };
+            (op, Ok(children))
+        })
+        .await
+        .unwrap()
+    {
+        processed.push(op);
+    }
+
+    assert_eq!(processed, vec![defer(1), defer(2)]);
+
+    // processed items are no longer pending and can be enqueued again
+    queue.enqueue(defer(1), &()).await.unwrap();
+    assert!(queue
+        .process(&(), |_, _| async { ((), Ok(vec![])) })
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn in_memory_queue_removes_matching_items() {
+    let queue = InMemoryQueue::<UnitMessage>::new(InMemoryQueueConfig::default())
+        .await
+        .unwrap();
+
+    for op in [defer(1), defer(2), defer(1)] {
+        queue.enqueue(op, &()).await.unwrap();
+    }
+
+    let removed = queue.remove(|op| *op == defer(1)).await.unwrap();
+    assert_eq!(removed, 2);
+
+    let mut processed = vec![];
+    while let Some(op) = queue
+        .process(&(), |op, _| async move { (op, Ok(vec![])) })
+        .await
+        .unwrap()
+    {
+        processed.push(op);
+    }
+
+    assert_eq!(processed, vec![defer(2)]);
+}
+
+#[tokio::test]
+async fn run_to_completion_resolves_promises() {
+    let produced = run_to_completion::<SimpleMessage>(
+        &(),
+        &(),
+        [
+            promise(
+                [call(FetchA {}), call(FetchB {}), call(FetchC {})],
+                [],
+                BuildPrintAbc {},
+            ),
+            conc([call(FetchB {}), data(DataC {})]),
+        ],
+    )
+    .await;
+
+    assert_eq!(
+        produced,
+        vec![SimpleData::from(DataC {}), SimpleData::from(DataB {})]
+    );
+}
+
+#[tokio::test]
+async fn mock_chain_send_packet_yields_recv_packet() {
+    let packet = SendPacket {
+        channel_id: 1,
+        sequence: 7,
+        data: b"hello".to_vec(),
+    };
+
+    let chain = MockChain {
+        latest_height: 100,
+        ..Default::default()
+    }
+    .with_proof(packet.commitment_path(), b"proof".to_vec());
+
+    let produced = run_to_completion::<MockMessage>(
+        &chain,
+        &(),
+        [call(RelayPacket {
+            packet: packet.clone(),
+        })],
+    )
+    .await;
+
+    assert_eq!(
+        produced,
+        vec![MockData::from(MsgRecvPacket {
+            packet,
+            proof: b"proof".to_vec(),
+            proof_height: 100,
+        })]
+    );
+}
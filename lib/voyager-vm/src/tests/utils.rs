@@ -1,10 +1,56 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 
 use enumorph::Enumorph;
 use macros::model;
 use subset_of::SubsetOf;
+use unionlabs::ErrorReporter;
 
-use crate::{call, data, noop, CallT, CallbackT, Context, Op, QueueError, QueueMessage};
+use crate::{
+    call, data,
+    in_memory::{InMemoryQueue, InMemoryQueueConfig},
+    noop, promise, CallT, CallbackT, Context, ContextT, Op, Queue, QueueError, QueueMessage,
+};
+
+/// Process `ops` on an [`InMemoryQueue`] until it is drained, returning all of the data that was
+/// produced outside of an aggregation, in the order it was produced.
+///
+/// Items that the filter routes to the optimizer are never processed, and errors cause a panic.
+pub async fn run_to_completion<T: QueueMessage>(
+    ctx: &T::Context,
+    filter: &T::Filter,
+    ops: impl IntoIterator<Item = Op<T>>,
+) -> Vec<T::Data> {
+    let queue = InMemoryQueue::<T>::new(InMemoryQueueConfig::default())
+        .await
+        .expect("infallible");
+
+    for op in ops {
+        queue.enqueue(op, filter).await.expect("infallible");
+    }
+
+    let mut produced = vec![];
+
+    while let Some(data) = queue
+        .process(filter, |op, id| async move {
+            match op {
+                Op::Data(data) => (Some(data), Ok(vec![])),
+                op => (
+                    None,
+                    op.process(Context::new(id, ctx), 0)
+                        .await
+                        .map(|op| op.into_iter().collect())
+                        .map_err(|err| ErrorReporter(err).to_string()),
+                ),
+            }
+        })
+        .await
+        .expect("infallible")
+    {
+        produced.extend(data);
+    }
+
+    produced
+}
 
 pub enum SimpleMessage {}
 
@@ -127,3 +173,178 @@ fn find_in_vec<T, U>(v: &mut Vec<T>, mut predicate: impl FnMut(&T) -> Option<U>)
             u
         })
 }
+
+/// A mock chain, returning canned heights and proofs. This can be used as the
+/// [`QueueMessage::Context`] to test aggregation flows without a live chain.
+#[derive(Debug, Clone, Default)]
+pub struct MockChain {
+    pub latest_height: u64,
+    /// The proofs that this chain can provide, keyed by path. Querying a path that is not in
+    /// this map is a fatal error.
+    pub proofs: BTreeMap<String, Vec<u8>>,
+}
+
+impl ContextT for MockChain {}
+
+impl MockChain {
+    #[must_use]
+    pub fn with_proof(mut self, path: impl Into<String>, proof: impl Into<Vec<u8>>) -> Self {
+        self.proofs.insert(path.into(), proof.into());
+        self
+    }
+}
+
+pub enum MockMessage {}
+
+impl QueueMessage for MockMessage {
+    type Data = MockData;
+    type Call = MockCall;
+    type Callback = MockCallback;
+
+    type Filter = ();
+
+    type Context = MockChain;
+}
+
+impl CallT<MockMessage> for MockCall {
+    async fn process(self, ctx: Context<&MockChain>) -> Result<Op<MockMessage>, QueueError> {
+        Ok(match self {
+            MockCall::FetchLatestHeight(FetchLatestHeight {}) => data(Height {
+                height: ctx.latest_height,
+            }),
+            MockCall::FetchProof(FetchProof { height, path }) => {
+                let proof = ctx.proofs.get(&path).cloned().ok_or_else(|| {
+                    QueueError::Fatal(format!("no proof for {path} at {height}").into())
+                })?;
+
+                data(Proof {
+                    height,
+                    path,
+                    proof,
+                })
+            }
+            MockCall::RelayPacket(RelayPacket { packet }) => promise(
+                [call(FetchLatestHeight {})],
+                [],
+                FetchPacketProof { packet },
+            ),
+        })
+    }
+}
+
+impl CallbackT<MockMessage> for MockCallback {
+    async fn process(
+        self,
+        _: Context<&MockChain>,
+        data: VecDeque<MockData>,
+    ) -> Result<Op<MockMessage>, QueueError> {
+        let mut data = data.into_iter().collect();
+
+        Ok(match self {
+            Self::FetchPacketProof(FetchPacketProof { packet }) => {
+                let Height { height } =
+                    find_in_vec(&mut data, |d| d.clone().try_into().ok()).unwrap();
+
+                promise(
+                    [call(FetchProof {
+                        height,
+                        path: packet.commitment_path(),
+                    })],
+                    [],
+                    MakeMsgRecvPacket { packet },
+                )
+            }
+            Self::MakeMsgRecvPacket(MakeMsgRecvPacket { packet }) => {
+                let Proof { height, proof, .. } =
+                    find_in_vec(&mut data, |d| d.clone().try_into().ok()).unwrap();
+
+                crate::data(MsgRecvPacket {
+                    packet,
+                    proof,
+                    proof_height: height,
+                })
+            }
+        })
+    }
+}
+
+#[model]
+#[derive(Enumorph, SubsetOf)]
+pub enum MockData {
+    Height(Height),
+    Proof(Proof),
+    SendPacket(SendPacket),
+    MsgRecvPacket(MsgRecvPacket),
+}
+
+#[model]
+pub struct Height {
+    pub height: u64,
+}
+
+#[model]
+pub struct Proof {
+    pub height: u64,
+    pub path: String,
+    pub proof: Vec<u8>,
+}
+
+#[model]
+pub struct SendPacket {
+    pub channel_id: u32,
+    pub sequence: u64,
+    pub data: Vec<u8>,
+}
+
+impl SendPacket {
+    #[must_use]
+    pub fn commitment_path(&self) -> String {
+        format!("commitments/{}/{}", self.channel_id, self.sequence)
+    }
+}
+
+#[model]
+pub struct MsgRecvPacket {
+    pub packet: SendPacket,
+    pub proof: Vec<u8>,
+    pub proof_height: u64,
+}
+
+#[model]
+#[derive(Enumorph, SubsetOf)]
+pub enum MockCall {
+    FetchLatestHeight(FetchLatestHeight),
+    FetchProof(FetchProof),
+    RelayPacket(RelayPacket),
+}
+
+#[model]
+pub struct FetchLatestHeight {}
+
+#[model]
+pub struct FetchProof {
+    pub height: u64,
+    pub path: String,
+}
+
+#[model]
+pub struct RelayPacket {
+    pub packet: SendPacket,
+}
+
+#[model]
+#[derive(Enumorph)]
+pub enum MockCallback {
+    FetchPacketProof(FetchPacketProof),
+    MakeMsgRecvPacket(MakeMsgRecvPacket),
+}
+
+#[model]
+pub struct FetchPacketProof {
+    pub packet: SendPacket,
+}
+
+#[model]
+pub struct MakeMsgRecvPacket {
+    pub packet: SendPacket,
+}
@@ -5,7 +5,7 @@ use alloy::{
     rpc::types::BlockTransactionsKind,
     transports::BoxTransport,
 };
-use beacon_api::client::BeaconApiClient;
+use beacon_api::{client::BeaconApiClient, types::Spec};
 use beacon_api_types::{PresetBaseKind, Slot};
 use ethereum_light_client_types::{ClientState, ConsensusState};
 use jsonrpsee::{
@@ -107,6 +107,21 @@ impl Module {
 
         Ok(beacon_slot)
     }
+
+    async fn fetch_spec(&self) -> RpcResult<Spec> {
+        Ok(self
+            .beacon_api_client
+            .spec()
+            .await
+            .map_err(|e| {
+                ErrorObject::owned(
+                    -1,
+                    format!("error fetching beacon spec: {}", ErrorReporter(e)),
+                    None::<()>,
+                )
+            })?
+            .data)
+    }
 }
 
 impl ClientBootstrapModule for Module {
@@ -125,7 +140,7 @@ impl ClientBootstrapModule for Module {
 
         let beacon_api_client = BeaconApiClient::new(config.beacon_rpc_url).await?;
 
-        let spec = beacon_api_client.spec().await.unwrap().data;
+        let spec = beacon_api_client.spec().await?.data;
 
         if spec.preset_base != config.chain_spec {
             return Err(format!(
@@ -149,9 +164,20 @@ impl ClientBootstrapModule for Module {
 impl ClientBootstrapModuleServer for Module {
     #[instrument(skip_all, fields(chain_id = %self.chain_id, %height))]
     async fn self_client_state(&self, _: &Extensions, height: Height) -> RpcResult<Value> {
-        let genesis = self.beacon_api_client.genesis().await.unwrap().data;
+        let genesis = self
+            .beacon_api_client
+            .genesis()
+            .await
+            .map_err(|e| {
+                ErrorObject::owned(
+                    -1,
+                    format!("error fetching beacon genesis: {}", ErrorReporter(e)),
+                    None::<()>,
+                )
+            })?
+            .data;
 
-        let spec = self.beacon_api_client.spec().await.unwrap().data;
+        let spec = self.fetch_spec().await?;
 
         Ok(serde_json::to_value(ClientState {
             chain_id: self
@@ -203,7 +229,7 @@ impl ClientBootstrapModuleServer for Module {
             })?
             .data;
 
-        let spec = self.beacon_api_client.spec().await.unwrap().data;
+        let spec = self.fetch_spec().await?;
 
         assert_eq!(bootstrap.header.execution.block_number, height.height());
 
@@ -249,7 +275,13 @@ impl ClientBootstrapModuleServer for Module {
                 .get_proof(self.ibc_handler_address.into(), vec![])
                 .block_id(bootstrap.header.execution.block_number.into())
                 .await
-                .unwrap()
+                .map_err(|e| {
+                    ErrorObject::owned(
+                        -1,
+                        format!("error fetching ibc account proof: {}", ErrorReporter(e)),
+                        None::<()>,
+                    )
+                })?
                 .storage_hash
                 .0
                 .into(),
@@ -258,7 +290,13 @@ impl ClientBootstrapModuleServer for Module {
             // TODO(aeryz): can this be None?
             next_sync_committee: light_client_update
                 .next_sync_committee
-                .unwrap()
+                .ok_or_else(|| {
+                    ErrorObject::owned(
+                        -1,
+                        "light client update is missing the next sync committee",
+                        None::<()>,
+                    )
+                })?
                 .aggregate_pubkey,
         }))
     }
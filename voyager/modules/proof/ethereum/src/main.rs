@@ -1,10 +1,14 @@
 #![warn(clippy::unwrap_used)]
 
+use std::iter;
+
 use alloy::{
     providers::{Provider, ProviderBuilder, RootProvider},
-    transports::BoxTransport,
+    rpc::types::EIP1186AccountProofResponse,
+    transports::{BoxTransport, TransportError},
 };
 use ethereum_light_client_types::StorageProof;
+use evm_storage_verifier::{verify_storage_absence, verify_storage_proof};
 use ibc_union_spec::{IbcUnion, StorePath};
 use jsonrpsee::{
     core::{async_trait, RpcResult},
@@ -13,11 +17,11 @@ use jsonrpsee::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 use unionlabs::{
-    ethereum::ibc_commitment_key,
+    ethereum::{ibc_commitment_key_at_slot, IBC_HANDLER_COMMITMENTS_SLOT},
     ibc::core::client::height::Height,
-    primitives::{H160, U256},
+    primitives::{H160, H256, U256},
     ErrorReporter,
 };
 use voyager_message::{
@@ -39,7 +43,15 @@ pub struct Module {
 
     pub ibc_handler_address: H160,
 
+    /// The storage slot of the commitments mapping in the `IBCHandler` smart contract.
+    pub ibc_commitment_slot: U256,
+
     pub provider: RootProvider<BoxTransport>,
+
+    /// Providers queried in order if `provider` cannot be reached.
+    pub fallback_providers: Vec<RootProvider<BoxTransport>>,
+
+    pub verify_proofs: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,8 +60,32 @@ pub struct Config {
     /// The address of the `IBCHandler` smart contract.
     pub ibc_handler_address: H160,
 
+    /// The storage slot of the commitments mapping in the `IBCHandler` smart contract. Defaults
+    /// to [`IBC_HANDLER_COMMITMENTS_SLOT`], and only needs to be set if the contract has been
+    /// deployed with a different storage layout.
+    #[serde(default = "default_ibc_commitment_slot")]
+    pub ibc_commitment_slot: U256,
+
     /// The RPC endpoint for the execution chain.
     pub rpc_url: String,
+
+    /// Additional RPC endpoints for the execution chain, queried in order if `rpc_url` cannot be
+    /// reached. Endpoints that cannot be reached on startup are skipped.
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<String>,
+
+    /// Verify the storage proofs returned by `eth_getProof` against the storage root of the
+    /// `IBCHandler` before returning them. An invalid proof is returned as an error (and the query
+    /// is retried) instead of being submitted to the counterparty where it would fail
+    /// verification.
+    #[serde(default)]
+    pub verify_proofs: bool,
+}
+
+#[must_use]
+#[inline]
+pub const fn default_ibc_commitment_slot() -> U256 {
+    IBC_HANDLER_COMMITMENTS_SLOT
 }
 
 impl ProofModule<IbcUnion> for Module {
@@ -62,19 +98,153 @@ impl ProofModule<IbcUnion> for Module {
 
         info.ensure_chain_id(chain_id.to_string())?;
 
+        let mut fallback_providers = vec![];
+        for rpc_url in &config.fallback_rpc_urls {
+            // a fallback that is down should not prevent the module from starting
+            let (provider, chain_id) = match connect(rpc_url).await {
+                Ok(ok) => ok,
+                Err(err) => {
+                    warn!(
+                        %rpc_url,
+                        error = %ErrorReporter(&*err),
+                        "unable to connect to fallback rpc endpoint, skipping it"
+                    );
+                    continue;
+                }
+            };
+
+            info.ensure_chain_id(chain_id.to_string())?;
+
+            fallback_providers.push(provider);
+        }
+
         Ok(Module {
             chain_id: ChainId::new(chain_id.to_string()),
             ibc_handler_address: config.ibc_handler_address,
+            ibc_commitment_slot: config.ibc_commitment_slot,
             provider,
+            fallback_providers,
+            verify_proofs: config.verify_proofs,
         })
     }
 }
 
+async fn connect(rpc_url: &str) -> Result<(RootProvider<BoxTransport>, u64), BoxDynError> {
+    let provider = ProviderBuilder::new().on_builtin(rpc_url).await?;
+
+    let chain_id = provider.get_chain_id().await?;
+
+    Ok((provider, chain_id))
+}
+
 impl Module {
     #[must_use]
     pub fn make_height(&self, height: u64) -> Height {
         Height::new(height)
     }
+
+    /// Fetch the proof of `location` at `execution_height`, failing over to the fallback providers
+    /// in order if a provider cannot be reached. Any other error (such as an error response from
+    /// the node) is returned directly.
+    async fn get_proof(
+        &self,
+        location: U256,
+        execution_height: u64,
+    ) -> Result<EIP1186AccountProofResponse, TransportError> {
+        // try the primary provider first, failing over to the fallbacks in order
+        let mut providers = iter::once(&self.provider)
+            .chain(&self.fallback_providers)
+            .peekable();
+
+        loop {
+            let provider = providers
+                .next()
+                .expect("there is at least one provider; qed;");
+
+            match provider
+                .get_proof(
+                    self.ibc_handler_address.get().into(),
+                    vec![location.to_be_bytes().into()],
+                )
+                .block_id(execution_height.into())
+                .await
+            {
+                Ok(proof) => break Ok(proof),
+                Err(err @ TransportError::Transport(_)) if providers.peek().is_some() => {
+                    warn!(
+                        error = %ErrorReporter(err),
+                        "error querying proof, trying the next provider"
+                    );
+                }
+                Err(err) => break Err(err),
+            }
+        }
+    }
+
+    async fn fetch_proof(&self, location: U256, execution_height: u64) -> RpcResult<StorageProof> {
+        let proof = self
+            .get_proof(location, execution_height)
+            .await
+            .map_err(|e| {
+                ErrorObject::owned(
+                    -1,
+                    format!("error fetching proof: {}", ErrorReporter(e)),
+                    None::<()>,
+                )
+            })?;
+
+        let storage_root = H256::new(proof.storage_hash.0);
+
+        // an empty proof list is a malformed response (a slot that has never been written to
+        // still has a proof of its absence), which may succeed if retried against another node
+        if proof.storage_proof.is_empty() {
+            return Err(ErrorObject::owned(
+                -1,
+                format!(
+                    "received invalid response from eth_getProof, \
+                    no storage proofs returned for slot {location}"
+                ),
+                None::<()>,
+            ));
+        }
+
+        // only one slot was requested, but some nodes may return proofs for additional slots -
+        // pick out the one for the requested slot
+        let Some(proof) = proof
+            .storage_proof
+            .into_iter()
+            .map(|proof| StorageProof {
+                key: U256::from_be_bytes(proof.key.as_b256().0),
+                value: U256::from_be_bytes(proof.value.to_be_bytes()),
+                proof: proof.proof.into_iter().map(|bytes| bytes.into()).collect(),
+            })
+            .find(|proof| proof.key == location)
+        else {
+            return Err(ErrorObject::owned(
+                -1,
+                format!(
+                    "received invalid response from eth_getProof, \
+                    no storage proof found for slot {location}"
+                ),
+                None::<()>,
+            ));
+        };
+
+        if self.verify_proofs {
+            verify_proof(storage_root, &proof).map_err(|e| {
+                ErrorObject::owned(
+                    -1,
+                    format!(
+                        "received invalid response from eth_getProof, storage proof for slot \
+                        {location} does not verify against storage root {storage_root}: {e}"
+                    ),
+                    None::<()>,
+                )
+            })?;
+        }
+
+        Ok(proof)
+    }
 }
 
 #[async_trait]
@@ -86,7 +256,7 @@ impl ProofModuleServer<IbcUnion> for Module {
         at: Height,
         path: StorePath,
     ) -> RpcResult<Value> {
-        let location = ibc_commitment_key(path.key());
+        let location = ibc_commitment_key_at_slot(path.key(), self.ibc_commitment_slot);
 
         debug!(
             "querying proof for slot {location} for IBC handler contract {}",
@@ -95,35 +265,36 @@ impl ProofModuleServer<IbcUnion> for Module {
 
         let execution_height = at.height();
 
-        let proof = self
-            .provider
-            .get_proof(
-                self.ibc_handler_address.get().into(),
-                vec![location.to_be_bytes().into()],
-            )
-            .block_id(execution_height.into())
-            .await
-            .map_err(|e| {
-                ErrorObject::owned(
-                    -1,
-                    format!("error fetching proof: {}", ErrorReporter(e)),
-                    None::<()>,
-                )
-            })?;
+        let proof = self.fetch_proof(location, execution_height).await?;
 
-        let proof = match <[_; 1]>::try_from(proof.storage_proof) {
-            Ok([proof]) => proof,
-            Err(invalid) => {
-                panic!("received invalid response from eth_getProof, expected length of 1 but got `{invalid:#?}`");
-            }
-        };
-
-        let proof = StorageProof {
-            key: U256::from_be_bytes(proof.key.as_b256().0),
-            value: U256::from_be_bytes(proof.value.to_be_bytes()),
-            proof: proof.proof.into_iter().map(|bytes| bytes.into()).collect(),
-        };
+        // the storage proof is the same shape for both membership and non-membership proofs;
+        // the value of a slot that has never been written to is 0, in which case the proof is
+        // a proof of the absence of the key in the storage trie
+        debug!(
+            membership = proof.value != U256::ZERO,
+            "fetched proof for slot {location}"
+        );
 
         Ok(into_value(proof))
     }
 }
+
+/// Verify `proof` against `storage_root`, as a proof of the value of the slot if it is set and as
+/// a proof of its absence otherwise.
+fn verify_proof(storage_root: H256, proof: &StorageProof) -> Result<(), String> {
+    if proof.value == U256::ZERO {
+        match verify_storage_absence(storage_root, proof.key, &proof.proof) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("the slot is set".to_owned()),
+            Err(e) => Err(ErrorReporter(e).to_string()),
+        }
+    } else {
+        verify_storage_proof(
+            storage_root,
+            proof.key,
+            &rlp::encode(&proof.value),
+            &proof.proof,
+        )
+        .map_err(|e| ErrorReporter(e).to_string())
+    }
+}
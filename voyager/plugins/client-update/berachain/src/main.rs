@@ -17,6 +17,7 @@ use tracing::instrument;
 use unionlabs::{
     berachain::LATEST_EXECUTION_PAYLOAD_HEADER_PREFIX,
     encoding::{DecodeAs, Ssz},
+    ethereum::is_deployed_contract,
     ibc::core::commitment::merkle_proof::MerkleProof,
     primitives::H160,
     ErrorReporter,
@@ -141,6 +142,20 @@ impl Module {
                 )
             })?;
 
+        if !is_deployed_contract(
+            &account_update.account_proof,
+            account_update.code_hash.into(),
+        ) {
+            return Err(ErrorObject::owned(
+                -1,
+                format!(
+                    "IBC handler {} has no code at block {block_number}",
+                    self.ibc_handler_address
+                ),
+                None::<()>,
+            ));
+        }
+
         Ok(AccountProof {
             storage_root: account_update.storage_hash.into(),
             proof: account_update
@@ -268,6 +283,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                         client_id: RawClientId::new(self.l1_client_id),
                         height: update_to,
                         finalized: true,
+                        deadline: None,
                     })]),
                     data(OrderedHeaders {
                         headers: vec![(
@@ -1,6 +1,6 @@
 #![warn(clippy::unwrap_used)]
 
-use std::{collections::VecDeque, ops::Div};
+use std::{collections::VecDeque, num::NonZeroU64, ops::Div};
 
 use alloy::{
     providers::{Provider, ProviderBuilder, RootProvider},
@@ -25,6 +25,7 @@ use jsonrpsee::{
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, instrument};
 use unionlabs::{
+    ethereum::is_deployed_contract,
     ibc::core::client::height::Height,
     primitives::{H160, H256},
     ErrorReporter,
@@ -36,7 +37,7 @@ use voyager_message::{
     hook::UpdateHook,
     into_value,
     module::{PluginInfo, PluginServer},
-    DefaultCmd, Plugin, PluginMessage, RawClientId, VoyagerMessage,
+    DefaultCmd, Plugin, PluginMessage, RawClientId, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
 use voyager_vm::{call, defer, now, pass::PassResult, seq, BoxDynError, Op, Visit};
 
@@ -65,6 +66,10 @@ pub struct Module {
 
     pub provider: RootProvider<BoxTransport>,
     pub beacon_api_client: BeaconApiClient,
+
+    pub max_updates_per_batch: Option<NonZeroU64>,
+
+    pub period_boundary_buffer: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +86,19 @@ pub struct Config {
     pub rpc_url: String,
     /// The RPC endpoint for the beacon chain.
     pub beacon_rpc_url: String,
+
+    /// The maximum amount of sync committee periods to fetch light client updates for at once. If
+    /// the client is further behind than this, the headers are fetched in multiple chunks, which
+    /// are all returned in order. If not set, all periods are always fetched at once.
+    #[serde(default)]
+    pub max_updates_per_batch: Option<NonZeroU64>,
+
+    /// The amount of slots that the finalized header must be past the start of a new sync
+    /// committee period before the client is updated into that period. Some counterparties reject
+    /// updates that land right at a period boundary. Defaults to 0, i.e. no buffer. Must be less
+    /// than the length of a sync committee period.
+    #[serde(default)]
+    pub period_boundary_buffer: u64,
 }
 
 fn plugin_name(chain_id: &ChainId) -> String {
@@ -111,6 +129,20 @@ impl Module {
                 )
             })?;
 
+        if !is_deployed_contract(
+            &account_update.account_proof,
+            account_update.code_hash.into(),
+        ) {
+            return Err(ErrorObject::owned(
+                -1,
+                format!(
+                    "IBC handler {} has no code at block {block_number}",
+                    self.ibc_handler_address
+                ),
+                None::<()>,
+            ));
+        }
+
         Ok(AccountProof {
             storage_root: account_update.storage_hash.into(),
             proof: account_update
@@ -164,12 +196,26 @@ impl Plugin for Module {
             .into());
         }
 
+        // the finalized header can never be this far into a period, and the client would never be
+        // updated into the next period
+        if config.period_boundary_buffer >= spec.period() {
+            return Err(format!(
+                "period boundary buffer must be less than the sync committee period: buffer \
+                is {} slots, but the period is {} slots",
+                config.period_boundary_buffer,
+                spec.period()
+            )
+            .into());
+        }
+
         Ok(Self {
             chain_id,
             chain_spec: spec.preset_base,
             ibc_handler_address: config.ibc_handler_address,
             provider,
             beacon_api_client,
+            max_updates_per_batch: config.max_updates_per_batch,
+            period_boundary_buffer: config.period_boundary_buffer,
         })
     }
 
@@ -348,6 +394,20 @@ impl Module {
             })?
             .data;
 
+        let retry = || {
+            seq([
+                defer(now() + 1),
+                call(FetchUpdateHeaders {
+                    client_type: ClientType::new(ClientType::ETHEREUM),
+                    chain_id: self.chain_id.clone(),
+                    client_id: client_id.clone(),
+                    counterparty_chain_id: counterparty_chain_id.clone(),
+                    update_from: update_from_block_number,
+                    update_to: update_to_block_number,
+                }),
+            ])
+        };
+
         // === FETCH VALID FINALITY UPDATE
 
         let does_not_have_has_supermajority = {
@@ -369,17 +429,7 @@ impl Module {
                 "signature supermajority not hit"
             );
 
-            return Ok(seq([
-                defer(now() + 1),
-                call(FetchUpdateHeaders {
-                    client_type: ClientType::new(ClientType::ETHEREUM),
-                    chain_id: self.chain_id.clone(),
-                    client_id,
-                    counterparty_chain_id,
-                    update_from: update_from_block_number,
-                    update_to: update_to_block_number,
-                }),
-            ]));
+            return Ok(retry());
         };
 
         // === FETCH LIGHT CLIENT UPDATES
@@ -401,14 +451,42 @@ impl Module {
             period {target_period}, something is wrong!",
         );
 
+        let slots_into_target_period =
+            finality_update.finalized_header.beacon.slot.get() % spec.period();
+
+        if trusted_period < target_period && slots_into_target_period < self.period_boundary_buffer
+        {
+            info!(
+                %slots_into_target_period,
+                period_boundary_buffer = %self.period_boundary_buffer,
+                "finalized header is too close to the start of period {target_period}, waiting"
+            );
+
+            return Ok(retry());
+        }
+
         // Eth chain is more than 1 signature period ahead of us. We need to do sync committee
         // updates until we reach the `target_period - 1`.
 
         // let target_period = sync_committee_period(finality_update.signature_slot, spec.period());
 
+        // if the client is very far behind, only catch up on some of the periods at a time. the
+        // rest will be caught up on in subsequent updates.
+        let (period_count, is_partial_catch_up) = match self.max_updates_per_batch {
+            Some(max) if target_period - trusted_period > max.get() => {
+                info!(
+                    "client is {} periods behind, only catching up {max} periods in this update",
+                    target_period - trusted_period
+                );
+
+                (max.get(), true)
+            }
+            _ => (target_period - trusted_period, false),
+        };
+
         let light_client_updates = self
             .beacon_api_client
-            .light_client_updates(trusted_period + 1, target_period - trusted_period)
+            .light_client_updates(trusted_period + 1, period_count)
             .await
             .map_err(|e| {
                 ErrorObject::owned(
@@ -440,6 +518,23 @@ impl Module {
                         // REVIEW: Assert that this is greater (i.e. increasing)?
                         trusted_block_number = update.finalized_header.execution.block_number;
 
+                        // period updates always rotate the sync committee, a missing committee
+                        // is a malformed response from the beacon node
+                        let (Some(next_sync_committee), Some(next_sync_committee_branch)) = (
+                            update.next_sync_committee,
+                            update.next_sync_committee_branch,
+                        ) else {
+                            return Err(ErrorObject::owned(
+                                -1,
+                                format!(
+                                    "light client update with signature slot {} is missing the \
+                                    next sync committee",
+                                    update.signature_slot
+                                ),
+                                None::<()>,
+                            ));
+                        };
+
                         vec.push_back(
                             self_
                                 .make_header(
@@ -451,14 +546,7 @@ impl Module {
                                         sync_aggregate: update.sync_aggregate,
                                         signature_slot: update.signature_slot,
                                     },
-                                    Some((
-                                        update
-                                            .next_sync_committee
-                                            .expect("next_sync_committee should exist"),
-                                        update
-                                            .next_sync_committee_branch
-                                            .expect("next_sync_committee_branch should exist"),
-                                    )),
+                                    Some((next_sync_committee, next_sync_committee_branch)),
                                     &spec,
                                 )
                                 .await?,
@@ -476,8 +564,9 @@ impl Module {
             [].into()
         };
 
+        // the finality update can only be applied once the client has caught up to the target period
         let does_not_have_finality_update =
-            last_update_block_number >= update_to_block_number.height();
+            is_partial_catch_up || last_update_block_number >= update_to_block_number.height();
 
         debug!(last_update_block_number, %update_to_block_number);
 
@@ -534,15 +623,29 @@ impl Module {
             .max()
             .expect("expected at least one update");
 
-        Ok(seq([
+        // we wait for one more block just to be sure the counterparty's block time has caught up
+        let timestamp = last_update_signature_slot
+            .get()
+            .checked_add(1)
+            .and_then(|slot| slot.checked_mul(spec.seconds_per_slot))
+            .and_then(|secs| secs.checked_add(genesis.genesis_time))
+            .and_then(Timestamp::try_from_secs)
+            .ok_or_else(|| {
+                ErrorObject::owned(
+                    FATAL_JSONRPC_ERROR_CODE,
+                    format!(
+                        "timestamp of slot {last_update_signature_slot} overflows (genesis time \
+                        {}, seconds per slot {})",
+                        genesis.genesis_time, spec.seconds_per_slot
+                    ),
+                    None::<()>,
+                )
+            })?;
+
+        let ops = [
             call(WaitForTimestamp {
                 chain_id: counterparty_chain_id.clone(),
-                // we wait for one more block just to be sure the counterparty's block time has caught up
-                timestamp: Timestamp::from_secs(
-                    (genesis.genesis_time
-                        + (last_update_signature_slot.get() * spec.seconds_per_slot))
-                        + spec.seconds_per_slot,
-                ),
+                timestamp,
                 finalized: false,
             }),
             voyager_vm::data(OrderedHeaders {
@@ -565,7 +668,23 @@ impl Module {
                     })
                     .collect(),
             }),
-        ]))
+        ];
+
+        if is_partial_catch_up {
+            // the remaining periods are caught up on from the last header of this chunk. all of the
+            // chunks are collected by the aggregation, such that the caller still receives headers
+            // that reach `update_to`.
+            Ok(seq(ops.into_iter().chain([call(FetchUpdateHeaders {
+                client_type: ClientType::new(ClientType::ETHEREUM),
+                chain_id: self.chain_id.clone(),
+                client_id,
+                counterparty_chain_id,
+                update_from: Height::new(last_update_block_number),
+                update_to: update_to_block_number,
+            })])))
+        } else {
+            Ok(seq(ops))
+        }
     }
 
     #[instrument(
@@ -617,25 +736,35 @@ impl Module {
             .into_iter()
             .map(|x| x.data)
             .collect::<Vec<_>>()
-            .pop()
-            .expect("one update was requested, if the rpc returns a value it should be valid here");
+            .pop();
+
+        // the current sync committee is the next sync committee of the previous period, which
+        // is required for both epoch change and within epoch updates
+        let Some(sync_committee) =
+            previous_period_light_client_update.and_then(|update| update.next_sync_committee)
+        else {
+            return Err(ErrorObject::owned(
+                -1,
+                format!(
+                    "light client update for period {previous_period} is missing or has no \
+                    next sync committee"
+                ),
+                None::<()>,
+            ));
+        };
 
         Ok(Header {
             consensus_update: match next_sync_committee {
                 Some((next_sync_committee, next_sync_committee_branch)) => {
                     LightClientUpdate::EpochChange(Box::new(EpochChangeUpdate {
-                        sync_committee: previous_period_light_client_update
-                            .next_sync_committee
-                            .expect("next_sync_committee should exist"),
+                        sync_committee,
                         next_sync_committee,
                         next_sync_committee_branch,
                         update_data: light_client_update_data,
                     }))
                 }
                 None => LightClientUpdate::WithinEpoch(Box::new(WithinEpochUpdate {
-                    sync_committee: previous_period_light_client_update
-                        .next_sync_committee
-                        .expect("next_sync_committee should exist"),
+                    sync_committee,
                     update_data: light_client_update_data,
                 })),
             },
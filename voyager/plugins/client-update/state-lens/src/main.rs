@@ -179,11 +179,19 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
             }) => {
                 let voyager_client = ext.try_get::<VoyagerClient>()?;
 
+                // resolve the latest height of the counterparty once so that all of the following
+                // queries observe the same state
+                let counterparty_latest_height = voyager_client
+                    .query_latest_height(counterparty_chain_id.clone(), false)
+                    .await?;
+
+                debug!(%counterparty_latest_height);
+
                 // state lens client running on the counterparty, tracking self.chain_id
                 let raw_state_lens_client_state = voyager_client
                     .query_ibc_state(
                         counterparty_chain_id.clone(),
-                        QueryHeight::Latest,
+                        QueryHeight::Specific(counterparty_latest_height),
                         ClientStatePath { client_id },
                     )
                     .await?
@@ -229,7 +237,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                 let l1_client_meta = voyager_client
                     .client_meta::<IbcUnion>(
                         counterparty_chain_id.clone(),
-                        QueryHeight::Latest,
+                        QueryHeight::Specific(counterparty_latest_height),
                         state_lens_client_state.l1_client_id,
                     )
                     .await?;
@@ -305,6 +313,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                 client_id: RawClientId::new(state_lens_client_state.l2_client_id),
                                 height: update_to,
                                 finalized: true,
+                                deadline: None,
                             }),
                             continuation,
                         ]),
@@ -320,11 +329,18 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
             }) => {
                 let voyager_client = ext.try_get::<VoyagerClient>()?;
 
+                // resolve the latest height of the counterparty once so that all of the following
+                // queries observe the same state
+                let counterparty_latest_height = voyager_client
+                    .query_latest_height(counterparty_chain_id.clone(), false)
+                    .await?;
+                debug!(%counterparty_latest_height);
+
                 // the client on the counterparty that is tracking the L1
                 let l1_client_meta = voyager_client
                     .client_meta::<IbcUnion>(
                         counterparty_chain_id.clone(),
-                        QueryHeight::Latest,
+                        QueryHeight::Specific(counterparty_latest_height),
                         state_lens_client_state.l1_client_id,
                     )
                     .await?;
@@ -339,16 +355,6 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                     .await?;
                 debug!(?l1_client_info);
 
-                // the client on the L1 that is tracking the L2
-                let l2_client_meta = voyager_client
-                    .client_meta::<IbcUnion>(
-                        l1_client_meta.chain_id.clone(),
-                        QueryHeight::Latest,
-                        state_lens_client_state.l2_client_id,
-                    )
-                    .await?;
-                debug!(?l1_client_meta);
-
                 let l1_latest_height = voyager_client
                     .query_latest_height(l1_client_meta.chain_id.clone(), false)
                     .await?;
@@ -357,11 +363,22 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                     l1_client_meta.chain_id, l1_latest_height
                 );
 
+                // the client on the L1 that is tracking the L2, read at the same height that the
+                // l2 consensus state is proven at below
+                let l2_client_meta = voyager_client
+                    .client_meta::<IbcUnion>(
+                        l1_client_meta.chain_id.clone(),
+                        QueryHeight::Specific(l1_latest_height),
+                        state_lens_client_state.l2_client_id,
+                    )
+                    .await?;
+                debug!(?l2_client_meta);
+
                 // client meta of the state lens client on the counterparty
                 let state_lens_client_meta = voyager_client
                     .client_meta::<IbcUnion>(
                         counterparty_chain_id.clone(),
-                        QueryHeight::Latest,
+                        QueryHeight::Specific(counterparty_latest_height),
                         client_id,
                     )
                     .await?;
@@ -447,6 +464,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                             client_id: RawClientId::new(state_lens_client_state.l1_client_id),
                             height: l1_latest_height,
                             finalized: false,
+                            deadline: None,
                         }),
                         data(OrderedHeaders {
                             headers: vec![(
@@ -35,6 +35,10 @@ pub enum IbcEvent {
         consensus_height: Height,
     },
 
+    // emitted by the evidence module when evidence of validator equivocation is submitted
+    #[serde(rename = "submit_evidence")]
+    SubmitEvidence { evidence_hash: String },
+
     #[serde(rename = "connection_open_init")]
     ConnectionOpenInit {
         connection_id: ConnectionId,
@@ -396,6 +400,7 @@ impl IbcEvent {
             IbcEvent::CreateClient { .. } => "create_client",
             IbcEvent::UpdateClient { .. } => "update_client",
             IbcEvent::ClientMisbehaviour { .. } => "client_misbehaviour",
+            IbcEvent::SubmitEvidence { .. } => "submit_evidence",
             IbcEvent::ConnectionOpenInit { .. } => "connection_open_init",
             IbcEvent::ConnectionOpenTry { .. } => "connection_open_try",
             IbcEvent::ConnectionOpenAck { .. } => "connection_open_ack",
@@ -413,7 +418,6 @@ impl IbcEvent {
             IbcEvent::WasmCreateClient { .. } => "create_client",
             IbcEvent::WasmUpdateClient { .. } => "update_client",
             // IbcEvent::UnionClientMisbehaviour{..} => "client_misbehaviour",
-            // IbcEvent::UnionSubmitEvidence{..} => "submit_evidence",
             IbcEvent::WasmConnectionOpenInit { .. } => "connection_open_init",
             IbcEvent::WasmConnectionOpenTry { .. } => "connection_open_try",
             IbcEvent::WasmConnectionOpenAck { .. } => "connection_open_ack",
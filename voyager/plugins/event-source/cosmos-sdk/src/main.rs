@@ -20,7 +20,7 @@ use jsonrpsee::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 use unionlabs::{
     bech32::Bech32,
     ibc::core::{
@@ -41,7 +41,7 @@ use voyager_message::{
     rpc::missing_state,
     ExtensionsExt, Plugin, PluginMessage, VoyagerClient, VoyagerMessage,
 };
-use voyager_vm::{call, conc, data, pass::PassResult, seq, BoxDynError, Op};
+use voyager_vm::{call, conc, data, noop, pass::PassResult, seq, BoxDynError, Op};
 
 use crate::{
     call::{FetchBlocks, FetchTransactions, MakeChainEvent, ModuleCall},
@@ -618,6 +618,23 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                 let voyager_client = e.try_get::<VoyagerClient>()?;
 
                 match event {
+                    IbcEvent::SubmitEvidence { evidence_hash } => {
+                        // the evidence is about the validators of this chain, so any clients
+                        // tracking it on counterparty chains must be frozen before they trust
+                        // the equivocating validator set. the evidence module only emits the
+                        // hash, so the misbehaviour itself has to be constructed and submitted
+                        // out of band (see `voyager msg submit-misbehaviour`).
+                        warn!(
+                            %height,
+                            %tx_hash,
+                            %evidence_hash,
+                            "observed evidence of equivocation on {}, clients tracking this \
+                            chain should be frozen",
+                            self.chain_id
+                        );
+
+                        Ok(noop())
+                    }
                     IbcEvent::CreateClient { ref client_id, .. }
                     | IbcEvent::UpdateClient { ref client_id, .. }
                     | IbcEvent::ClientMisbehaviour { ref client_id, .. }
@@ -665,6 +682,16 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                                     consensus_heights,
                                 }
                                 .into(),
+                                IbcEvent::ClientMisbehaviour {
+                                    client_id,
+                                    client_type,
+                                    consensus_height,
+                                } => ibc_classic_spec::ClientMisbehaviour {
+                                    client_id,
+                                    client_type: ClientType::new(client_type),
+                                    consensus_height,
+                                }
+                                .into(),
                                 IbcEvent::ConnectionOpenInit {
                                     connection_id,
                                     client_id,
@@ -27,11 +27,23 @@ async fn main() {
     Module::run().await
 }
 
-pub struct Module {}
+pub struct Module {
+    pub check_interval: u64,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub struct Config {}
+pub struct Config {
+    /// How often to check the age of a client that is not older than its `max_age`, in seconds.
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+}
+
+#[must_use]
+#[inline]
+pub const fn default_check_interval() -> u64 {
+    60
+}
 
 impl Plugin for Module {
     type Call = ModuleCall;
@@ -92,8 +104,10 @@ impl Module {
         PLUGIN_NAME.to_owned()
     }
 
-    pub fn new(_: Config) -> Self {
-        Self {}
+    pub fn new(config: Config) -> Self {
+        Self {
+            check_interval: config.check_interval,
+        }
     }
 
     #[instrument(
@@ -160,6 +174,7 @@ impl Module {
                             client_meta.counterparty_height.height() + max_age,
                         ),
                         finalized: false,
+                        deadline: None,
                     }),
                     call(PluginMessage::new(
                         self.plugin_name(),
@@ -174,7 +189,7 @@ impl Module {
             ]))
         } else {
             Ok(seq([
-                defer(now() + 60),
+                defer(now() + self.check_interval),
                 call(PluginMessage::new(
                     self.plugin_name(),
                     ModuleCall::CheckForClientAge(CheckForClientAge {
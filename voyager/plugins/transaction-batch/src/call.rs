@@ -12,13 +12,14 @@ use voyager_message::{
     core::{ChainId, QueryHeight},
     PluginMessage, RawClientId, VoyagerClient, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
-use voyager_vm::{now, promise, Op};
+use voyager_vm::{noop, now, promise, Op};
 
 use crate::{
     call,
     callback::{make_msgs, MakeBatchTransaction, MakeIbcMessagesFromUpdate, ModuleCallback},
-    data::BatchableEvent,
-    IbcSpecExt, Module,
+    data::{BatchableEvent, EventBatch, HandshakeProgress, ModuleData},
+    metrics::PACKET_RELAY_LATENCY,
+    now_millis, IbcSpecExt, Module,
 };
 
 #[model]
@@ -29,6 +30,13 @@ pub enum ModuleCall {
 
     MakeMsgV1(MakeMsg<IbcClassic>),
     MakeMsgUnion(MakeMsg<IbcUnion>),
+
+    MakePacketTimeout(MakePacketTimeout),
+
+    RecordPacketRelayLatency(RecordPacketRelayLatency),
+
+    RecordHandshakeProgressV1(RecordHandshakeProgress<IbcClassic>),
+    RecordHandshakeProgressUnion(RecordHandshakeProgress<IbcUnion>),
 }
 
 /// Constructs multiple batch transactions, where all of the batches are provable at the new consensus height.
@@ -42,6 +50,7 @@ impl<V: IbcSpecExt> MakeTransactionBatchesWithUpdate<V>
 where
     ModuleCall: From<MakeMsg<V>>,
     ModuleCallback: From<MakeBatchTransaction<V>> + From<MakeIbcMessagesFromUpdate<V>>,
+    ModuleData: From<EventBatch<V>>,
 {
     pub async fn call(
         self,
@@ -152,3 +161,73 @@ pub struct MakeMsg<V: IbcSpecExt> {
     /// The original event that was emitted on the origin chain.
     pub event: V::BatchableEvent,
 }
+
+/// Observes the relay latency of the packets in a batch. This is queued after the batch
+/// transaction is submitted, such that the latency covers the full time it took to relay the
+/// packets.
+#[model]
+pub struct RecordPacketRelayLatency {
+    /// The chain id of the chain that the packets were relayed to.
+    pub chain_id: ChainId,
+    pub packets: Vec<RelayedPacket>,
+}
+
+#[model]
+pub struct RelayedPacket {
+    /// The destination channel of the packet.
+    pub channel_id: String,
+    /// unix timestamp (in ms) of when the packet send event was first seen by this plugin.
+    pub first_seen_at: u64,
+}
+
+impl RecordPacketRelayLatency {
+    pub fn call(self) -> Op<VoyagerMessage> {
+        let now = now_millis();
+
+        for packet in self.packets {
+            PACKET_RELAY_LATENCY
+                .with_label_values(&[self.chain_id.as_str(), &packet.channel_id])
+                .observe(now.saturating_sub(packet.first_seen_at) as f64 / 1000.0);
+        }
+
+        noop()
+    }
+}
+
+/// Records the progress of connection and channel handshakes, once the messages constructed for them
+/// have been submitted. This message is persisted in the queue once it has been processed, such that
+/// the progress of each handshake can be followed across both chains.
+#[model]
+pub struct RecordHandshakeProgress<V: IbcSpecExt> {
+    pub progress: Vec<HandshakeProgress<V>>,
+}
+
+impl<V: IbcSpecExt> RecordHandshakeProgress<V> {
+    pub fn call(self) -> Op<VoyagerMessage> {
+        for progress in self.progress {
+            info!(
+                ibc_spec_id = %V::ID,
+                step = %progress.step,
+                origin_chain_id = %progress.origin_chain_id,
+                target_chain_id = %progress.target_chain_id,
+                event = %serde_json::to_string(&progress.event).expect("serialization is infallible; qed;"),
+                "handshake progress"
+            );
+        }
+
+        noop()
+    }
+}
+
+/// Constructs a [`MsgPacketTimeout`](ibc_union_spec::MsgPacketTimeout) for a packet that was sent
+/// on the origin chain and has timed out on the target chain, and submits it on the origin chain.
+#[model]
+pub struct MakePacketTimeout {
+    /// The chain id of the chain that the packet was sent on, and that the timeout will be sent
+    /// to.
+    pub origin_chain_id: ChainId,
+    /// The chain id of the chain that the packet timed out on.
+    pub target_chain_id: ChainId,
+    /// The original packet send event that was emitted on the origin chain.
+    pub event: ibc_union_spec::PacketSend,
+}
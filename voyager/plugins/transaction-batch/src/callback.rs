@@ -4,21 +4,21 @@ use enumorph::Enumorph;
 use ibc_classic_spec::IbcClassic;
 use ibc_union_spec::IbcUnion;
 use itertools::Itertools;
-use jsonrpsee::core::RpcResult;
+use jsonrpsee::{core::RpcResult, types::ErrorObject};
 use macros::model;
 use tracing::{debug, instrument, warn};
 use unionlabs::ibc::core::client::height::Height;
 use voyager_message::{
     call::{SubmitTx, WaitForTrustedHeight},
-    core::{ChainId, ClientStateMeta, QueryHeight},
+    core::{ClientStateMeta, QueryHeight},
     data::{Data, IbcDatagram, OrderedClientUpdates},
-    PluginMessage, RawClientId, VoyagerClient, VoyagerMessage,
+    PluginMessage, RawClientId, VoyagerClient, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
-use voyager_vm::{call, conc, noop, promise, seq, Op};
+use voyager_vm::{call, conc, data, noop, promise, seq, Op};
 
 use crate::{
-    call::{MakeMsg, ModuleCall},
-    data::BatchableEvent,
+    call::{MakeMsg, ModuleCall, RecordHandshakeProgress, RecordPacketRelayLatency, RelayedPacket},
+    data::{BatchableEvent, EventBatch, HandshakeProgress, ModuleData},
     IbcSpecExt, Module,
 };
 
@@ -42,6 +42,7 @@ impl<V: IbcSpecExt> MakeIbcMessagesFromUpdate<V>
 where
     ModuleCall: From<MakeMsg<V>>,
     ModuleCallback: From<MakeBatchTransaction<V>>,
+    ModuleData: From<EventBatch<V>>,
 {
     pub async fn call(
         self,
@@ -115,17 +116,57 @@ pub fn make_msgs<V: IbcSpecExt>(
 where
     ModuleCall: From<MakeMsg<V>>,
     ModuleCallback: From<MakeBatchTransaction<V>>,
+    ModuleData: From<EventBatch<V>>,
 {
-    Ok(conc(batches.into_iter().enumerate().map(|(i, batch)| {
+    // the client may not have been updated high enough to prove all of the events (for example
+    // if it was updated concurrently to a lower height than requested). these events are sent
+    // back to be batched again, where they will be picked up by a later update.
+    let mut not_yet_provable = vec![];
+
+    let batches = batches
+        .into_iter()
+        .map(|batch| {
+            let (provable, unprovable): (Vec<_>, Vec<_>) = batch
+                .into_iter()
+                .partition(|e| e.provable_height <= new_trusted_height);
+
+            not_yet_provable.extend(unprovable);
+
+            provable
+        })
+        .filter(|batch| !batch.is_empty())
+        .collect::<Vec<_>>();
+
+    let requeue = (!not_yet_provable.is_empty()).then(|| {
+        warn!(
+            count = not_yet_provable.len(),
+            "some events are not provable at the new trusted height, they will be requeued"
+        );
+
+        data(PluginMessage::new(
+            module_server.plugin_name(),
+            ModuleData::from(EventBatch {
+                client_id: client_id.clone(),
+                events: not_yet_provable,
+            }),
+        ))
+    });
+
+    let ops = conc(batches.into_iter().enumerate().map(|(i, batch)| {
+        let packets = batch
+            .iter()
+            .filter_map(|batchable_event| {
+                V::packet_send_destination_channel(&batchable_event.event).map(|channel_id| {
+                    RelayedPacket {
+                        channel_id,
+                        first_seen_at: batchable_event.first_seen_at,
+                    }
+                })
+            })
+            .collect();
+
         promise(
             batch.into_iter().map(|batchable_event| {
-                assert!(
-                    batchable_event.provable_height <= new_trusted_height,
-                    "{} <= {}",
-                    batchable_event.provable_height,
-                    new_trusted_height
-                );
-
                 let origin_chain_id = client_meta.chain_id.clone();
                 let target_chain_id = module_server.chain_id.clone();
 
@@ -155,10 +196,16 @@ where
                     client_id: client_id.clone(),
                     // if updates are provided and this is the first batch using this update height, provide the updates along with the messages
                     updates: (i == 0).then(|| updates.clone()).flatten(),
+                    packets,
                 }),
             ),
         )
-    })))
+    }));
+
+    Ok(match requeue {
+        Some(requeue) => conc([ops, requeue]),
+        None => ops,
+    })
 }
 
 #[model]
@@ -167,24 +214,60 @@ pub struct MakeBatchTransaction<V: IbcSpecExt> {
     pub client_id: V::ClientId,
     /// Updates to send before the messages in this message's callback data. If this is `None`, then that means the updates have been included in a previous batch, and this will instead be enqueued with a WaitForTrustedHeight in front of it.
     pub updates: Option<OrderedClientUpdates>,
+    /// The packets in this batch, for which the relay latency is recorded once the batch has been
+    /// submitted.
+    pub packets: Vec<RelayedPacket>,
 }
 
-impl<V: IbcSpecExt> MakeBatchTransaction<V> {
-    #[instrument(skip_all, fields(ibc_spec_id = %V::ID, %chain_id, datas_len = datas.len()))]
-    pub fn call(self, chain_id: ChainId, datas: VecDeque<Data>) -> Op<VoyagerMessage> {
+impl<V: IbcSpecExt> MakeBatchTransaction<V>
+where
+    ModuleCall: From<RecordHandshakeProgress<V>>,
+    HandshakeProgress<V>: TryFrom<ModuleData, Error = ModuleData>,
+{
+    #[instrument(
+        skip_all,
+        fields(ibc_spec_id = %V::ID, chain_id = %module.chain_id, datas_len = datas.len())
+    )]
+    pub fn call(self, module: &Module, datas: VecDeque<Data>) -> RpcResult<Op<VoyagerMessage>> {
         if datas.is_empty() {
             warn!("no IBC messages in queue! this likely means that all of the IBC messages that were queued to be sent were already sent to the destination chain");
         }
 
-        let mut msgs = datas
+        let chain_id = module.chain_id.clone();
+
+        let unexpected_data = |d: &dyn std::fmt::Debug| {
+            ErrorObject::owned(
+                FATAL_JSONRPC_ERROR_CODE,
+                format!("unexpected data in batch: {d:?}"),
+                None::<()>,
+            )
+        };
+
+        // packets that timed out before they could be received are relayed back to their origin
+        // chain separately from this batch, and handshake progress is recorded once the batch has
+        // been submitted
+        let mut datagrams = vec![];
+        let mut timeouts = vec![];
+        let mut progress = vec![];
+
+        for d in datas {
+            match d {
+                Data::IbcDatagram(datagram) => datagrams.push(datagram),
+                d => match d.as_plugin::<ModuleData>(module.plugin_name()) {
+                    Ok(ModuleData::PacketTimedOut(timeout)) => timeouts.push(call(
+                        PluginMessage::new(module.plugin_name(), ModuleCall::from(timeout)),
+                    )),
+                    Ok(d) => progress.push(
+                        HandshakeProgress::<V>::try_from(d).map_err(|d| unexpected_data(&d))?,
+                    ),
+                    Err(d) => return Err(unexpected_data(&d)),
+                },
+            }
+        }
+
+        let mut msgs = datagrams
             .into_iter()
-            .map(|d| {
-                IbcDatagram::try_from(d)
-                    .unwrap()
-                    .decode_datagram::<V>()
-                    .unwrap()
-                    .unwrap()
-            })
+            .map(|d| d.decode_datagram::<V>().unwrap().unwrap())
             .peekable();
 
         // TODO: We may need to sort packet messages when we support ordered channels
@@ -200,7 +283,24 @@ impl<V: IbcSpecExt> MakeBatchTransaction<V> {
         //     (IbcMessage::TimeoutPacket(_), IbcMessage::TimeoutPacket(_)) => todo!(),
         // });
 
-        match self.updates {
+        let record_latency = (!self.packets.is_empty()).then(|| {
+            call(PluginMessage::new(
+                module.plugin_name(),
+                ModuleCall::from(RecordPacketRelayLatency {
+                    chain_id: chain_id.clone(),
+                    packets: self.packets,
+                }),
+            ))
+        });
+
+        let record_progress = (!progress.is_empty()).then(|| {
+            call(PluginMessage::new(
+                module.plugin_name(),
+                ModuleCall::from(RecordHandshakeProgress::<V> { progress }),
+            ))
+        });
+
+        let batch = match self.updates {
             Some(updates) => call(SubmitTx {
                 chain_id,
                 datagrams: updates
@@ -234,6 +334,7 @@ impl<V: IbcSpecExt> MakeBatchTransaction<V> {
                             ibc_spec_id: V::ID,
                             height: required_consensus_height,
                             finalized: false,
+                            deadline: module.trusted_height_deadline(),
                         }),
                         call(SubmitTx {
                             chain_id,
@@ -242,6 +343,25 @@ impl<V: IbcSpecExt> MakeBatchTransaction<V> {
                     ])
                 }
             }
-        }
+        };
+
+        // the latency and handshake progress are only recorded once the transaction has been
+        // submitted successfully
+        let records = record_latency
+            .into_iter()
+            .chain(record_progress)
+            .collect::<Vec<_>>();
+
+        let batch = if records.is_empty() || matches!(batch, Op::Noop) {
+            batch
+        } else {
+            seq([batch].into_iter().chain(records))
+        };
+
+        Ok(if timeouts.is_empty() {
+            batch
+        } else {
+            conc(timeouts.into_iter().chain([batch]))
+        })
     }
 }
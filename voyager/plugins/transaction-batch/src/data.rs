@@ -4,14 +4,29 @@ use ibc_union_spec::IbcUnion;
 use macros::model;
 use subset_of::SubsetOf;
 use unionlabs::ibc::core::client::height::Height;
+use voyager_message::core::ChainId;
 
-use crate::IbcSpecExt;
+use crate::{call::MakePacketTimeout, IbcSpecExt};
 
 #[model]
 #[derive(Enumorph, SubsetOf)]
 pub enum ModuleData {
     BatchEventsV1(EventBatch<IbcClassic>),
     BatchEventsUnion(EventBatch<IbcUnion>),
+    /// A packet that timed out before it could be received. This is returned in place of a
+    /// datagram when making the messages for a batch, and is split out into its own timeout
+    /// by [`MakeBatchTransaction`].
+    ///
+    /// [`MakeBatchTransaction`]: crate::callback::MakeBatchTransaction
+    PacketTimedOut(MakePacketTimeout),
+    /// A handshake message that was constructed. This is returned along with the datagram when
+    /// making the messages for a batch, and is recorded by [`MakeBatchTransaction`] with a
+    /// [`RecordHandshakeProgress`] once the batch has been submitted.
+    ///
+    /// [`MakeBatchTransaction`]: crate::callback::MakeBatchTransaction
+    /// [`RecordHandshakeProgress`]: crate::call::RecordHandshakeProgress
+    HandshakeProgressV1(HandshakeProgress<IbcClassic>),
+    HandshakeProgressUnion(HandshakeProgress<IbcUnion>),
 }
 
 #[model]
@@ -22,6 +37,20 @@ pub struct EventBatch<V: IbcSpecExt> {
     pub events: Vec<BatchableEvent<V>>,
 }
 
+/// A record of a constructed handshake message, such that the progress of a connection or channel
+/// handshake can be followed across both chains.
+#[model]
+pub struct HandshakeProgress<V: IbcSpecExt> {
+    /// The handshake message that was constructed, i.e. `connection_open_try`.
+    pub step: String,
+    /// The chain id of the chain that the event was emitted on.
+    pub origin_chain_id: ChainId,
+    /// The chain id of the chain that the message will be sent to.
+    pub target_chain_id: ChainId,
+    /// The event that the message was constructed from. All ids are those of the origin chain.
+    pub event: V::BatchableEvent,
+}
+
 #[model]
 pub struct BatchableEvent<V: IbcSpecExt> {
     /// unix timestamp (in ms) of when this event was first seen by this plugin.
@@ -63,6 +92,9 @@ impl TryFrom<ibc_classic_spec::FullEvent> for EventClassic {
             ibc_classic_spec::FullEvent::WriteAcknowledgement(e) => {
                 Ok(Self::WriteAcknowledgement(e))
             }
+            // this includes `ClientMisbehaviour`: the misbehaviour itself is not included in the
+            // event, so there is nothing to relay. evidence must be submitted manually with
+            // `voyager msg submit-misbehaviour`.
             _ => Err(()),
         }
     }
@@ -2,6 +2,7 @@ use std::{
     collections::{HashMap, VecDeque},
     convert,
     future::Future,
+    net::SocketAddr,
     pin::Pin,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
@@ -23,6 +24,7 @@ use tracing::{debug, error, info, instrument, trace, warn};
 use unionlabs::{
     ethereum::keccak256,
     ibc::core::{
+        channel::{self, msg_acknowledgement::MsgAcknowledgement},
         client::height::Height,
         commitment::merkle_prefix::MerklePrefix,
         connection::{
@@ -35,24 +37,32 @@ use unionlabs::{
     DELAY_PERIOD,
 };
 use voyager_message::{
-    call::WaitForHeight,
+    call::{FetchUpdateHeaders, SubmitTx, WaitForHeight, WaitForTrustedHeight},
+    callback::AggregateMsgUpdateClientsFromOrderedHeaders,
     core::{ChainId, IbcSpec, QueryHeight},
     data::{ChainEvent, Data, IbcDatagram},
     module::{PluginInfo, PluginServer},
     DefaultCmd, ExtensionsExt, Plugin, PluginMessage, RawClientId, VoyagerClient, VoyagerMessage,
     FATAL_JSONRPC_ERROR_CODE,
 };
-use voyager_vm::{call, data, pass::PassResult, seq, BoxDynError, Op};
+use voyager_vm::{
+    call, conc, data, defer,
+    metrics::{record_dropped, DropReason},
+    noop, now,
+    pass::PassResult,
+    promise, seq, BoxDynError, Op,
+};
 
 use crate::{
-    call::{MakeMsg, MakeTransactionBatchesWithUpdate, ModuleCall},
+    call::{MakeMsg, MakePacketTimeout, MakeTransactionBatchesWithUpdate, ModuleCall},
     callback::ModuleCallback,
-    data::{BatchableEvent, EventBatch, EventClassic, EventUnion, ModuleData},
+    data::{BatchableEvent, EventBatch, EventClassic, EventUnion, HandshakeProgress, ModuleData},
 };
 
 pub mod call;
 pub mod callback;
 pub mod data;
+pub mod metrics;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -63,6 +73,11 @@ async fn main() {
 pub struct Module {
     pub chain_id: ChainId,
     pub client_configs: ClientConfigs,
+    pub commitment_prefixes: HashMap<ChainId, String>,
+    pub channels: Option<Vec<String>>,
+    pub expire_timed_out_packets: bool,
+    pub max_trusted_height_wait_time: Option<Duration>,
+    pub packet_timeout_retry_interval: Duration,
 }
 
 #[derive(Debug, Clone)]
@@ -76,8 +91,45 @@ pub enum ClientConfigs {
 pub struct Config {
     pub chain_id: ChainId,
     pub client_configs: ClientConfigsSerde,
+    /// If set, serve the metrics of this plugin (such as packet relay latency) on this address.
+    #[serde(default)]
+    pub metrics_laddr: Option<SocketAddr>,
+    /// The commitment prefixes of the counterparty chains, used when constructing connection
+    /// handshake messages. Chains that are not specified use [`DEFAULT_COMMITMENT_PREFIX`].
+    #[serde(default)]
+    pub commitment_prefixes: HashMap<ChainId, String>,
+    /// If set, only handle channel handshake and packet events for these channels on this chain.
+    /// Events for all other channels are ignored. Channel open init events are always handled,
+    /// since the channel does not exist on this chain yet.
+    #[serde(default)]
+    pub channels: Option<Vec<String>>,
+    /// If set, packet send events that are waiting to be batched are timed out on their origin
+    /// chain instead of being relayed once their timeout timestamp has passed, according to the
+    /// local clock. Timeouts are only supported for ibc-union packets, expired ibc-classic packets
+    /// are dropped. Handshake events and acknowledgements never expire.
+    #[serde(default)]
+    pub expire_timed_out_packets: bool,
+    /// If set, a batch that is waiting for the client on this chain to be updated to the height
+    /// of its proofs fails once this much time has passed, instead of waiting forever for a
+    /// client that is never updated. This also applies to packet timeouts waiting for the client
+    /// on their origin chain.
+    #[serde(default)]
+    pub max_trusted_height_wait_time: Option<Duration>,
+    /// How long to wait before checking again if a packet that is being timed out has timed out at
+    /// the latest finalized height of its destination chain.
+    #[serde(default = "default_packet_timeout_retry_interval")]
+    pub packet_timeout_retry_interval: Duration,
+}
+
+#[must_use]
+#[inline]
+pub const fn default_packet_timeout_retry_interval() -> Duration {
+    Duration::from_secs(10)
 }
 
+/// The commitment prefix used by ibc-go chains.
+pub const DEFAULT_COMMITMENT_PREFIX: &str = "ibc";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ClientConfig {
@@ -121,6 +173,30 @@ pub trait IbcSpecExt: IbcSpec {
     fn proof_height(msg: &Self::Datagram) -> Height;
 
     fn event_name(msg: &Self::BatchableEvent) -> &'static str;
+
+    /// The channel on this chain that the packet of `msg` is being sent to, if `msg` is a packet
+    /// send event.
+    fn packet_send_destination_channel(msg: &Self::BatchableEvent) -> Option<String>;
+
+    /// The timestamp (in nanoseconds) after which the packet of `msg` can no longer be received,
+    /// if `msg` is a packet send event and the packet has a timeout timestamp.
+    fn packet_send_timeout_timestamp(msg: &Self::BatchableEvent) -> Option<u64>;
+
+    /// The channel that is checked against the configured `channels` for `msg`, if `msg` is
+    /// filtered by channel. This must be kept in sync with the interest filter of this plugin.
+    fn filtered_channel(msg: &Self::BatchableEvent) -> Option<String>;
+
+    /// The handshake message that is constructed from `msg`, if `msg` is a connection or channel
+    /// handshake event.
+    fn handshake_step(msg: &Self::BatchableEvent) -> Option<&'static str>;
+
+    /// The timeout of the packet of `msg`, sent from `origin_chain_id` to `target_chain_id`, if
+    /// `msg` is a packet send event and packet timeouts are supported for this IBC spec.
+    fn make_packet_timeout(
+        origin_chain_id: ChainId,
+        target_chain_id: ChainId,
+        msg: Self::BatchableEvent,
+    ) -> Option<MakePacketTimeout>;
 }
 
 impl IbcSpecExt for IbcClassic {
@@ -143,6 +219,58 @@ impl IbcSpecExt for IbcClassic {
             EventClassic::WriteAcknowledgement(_) => "write_ack",
         }
     }
+
+    fn packet_send_destination_channel(msg: &Self::BatchableEvent) -> Option<String> {
+        match msg {
+            EventClassic::SendPacket(event) => {
+                Some(event.packet.destination_channel.channel_id.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn packet_send_timeout_timestamp(msg: &Self::BatchableEvent) -> Option<u64> {
+        match msg {
+            EventClassic::SendPacket(event) if event.packet.timeout_timestamp > 0 => {
+                Some(event.packet.timeout_timestamp)
+            }
+            _ => None,
+        }
+    }
+
+    fn filtered_channel(msg: &Self::BatchableEvent) -> Option<String> {
+        match msg {
+            EventClassic::ChannelOpenTry(event) => Some(event.counterparty_channel_id.to_string()),
+            EventClassic::ChannelOpenAck(event) => Some(event.counterparty_channel_id.to_string()),
+            EventClassic::SendPacket(event) => {
+                Some(event.packet.destination_channel.channel_id.to_string())
+            }
+            EventClassic::WriteAcknowledgement(event) => {
+                Some(event.packet.source_channel.channel_id.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn handshake_step(msg: &Self::BatchableEvent) -> Option<&'static str> {
+        match msg {
+            EventClassic::ConnectionOpenInit(_) => Some("connection_open_try"),
+            EventClassic::ConnectionOpenTry(_) => Some("connection_open_ack"),
+            EventClassic::ConnectionOpenAck(_) => Some("connection_open_confirm"),
+            EventClassic::ChannelOpenInit(_) => Some("channel_open_try"),
+            EventClassic::ChannelOpenTry(_) => Some("channel_open_ack"),
+            EventClassic::ChannelOpenAck(_) => Some("channel_open_confirm"),
+            EventClassic::SendPacket(_) | EventClassic::WriteAcknowledgement(_) => None,
+        }
+    }
+
+    fn make_packet_timeout(
+        _origin_chain_id: ChainId,
+        _target_chain_id: ChainId,
+        _msg: Self::BatchableEvent,
+    ) -> Option<MakePacketTimeout> {
+        None
+    }
 }
 
 impl IbcSpecExt for IbcUnion {
@@ -165,6 +293,63 @@ impl IbcSpecExt for IbcUnion {
             EventUnion::WriteAck(_) => "write_ack",
         }
     }
+
+    fn packet_send_destination_channel(msg: &Self::BatchableEvent) -> Option<String> {
+        match msg {
+            EventUnion::PacketSend(event) => {
+                Some(event.packet.destination_channel.channel_id.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn packet_send_timeout_timestamp(msg: &Self::BatchableEvent) -> Option<u64> {
+        match msg {
+            EventUnion::PacketSend(event) if event.packet.timeout_timestamp > 0 => {
+                Some(event.packet.timeout_timestamp)
+            }
+            _ => None,
+        }
+    }
+
+    fn filtered_channel(msg: &Self::BatchableEvent) -> Option<String> {
+        match msg {
+            EventUnion::ChannelOpenTry(event) => Some(event.counterparty_channel_id.to_string()),
+            EventUnion::ChannelOpenAck(event) => Some(event.counterparty_channel_id.to_string()),
+            EventUnion::PacketSend(event) => {
+                Some(event.packet.destination_channel.channel_id.to_string())
+            }
+            EventUnion::WriteAck(event) => Some(event.packet.source_channel.channel_id.to_string()),
+            _ => None,
+        }
+    }
+
+    fn handshake_step(msg: &Self::BatchableEvent) -> Option<&'static str> {
+        match msg {
+            EventUnion::ConnectionOpenInit(_) => Some("connection_open_try"),
+            EventUnion::ConnectionOpenTry(_) => Some("connection_open_ack"),
+            EventUnion::ConnectionOpenAck(_) => Some("connection_open_confirm"),
+            EventUnion::ChannelOpenInit(_) => Some("channel_open_try"),
+            EventUnion::ChannelOpenTry(_) => Some("channel_open_ack"),
+            EventUnion::ChannelOpenAck(_) => Some("channel_open_confirm"),
+            EventUnion::PacketSend(_) | EventUnion::WriteAck(_) => None,
+        }
+    }
+
+    fn make_packet_timeout(
+        origin_chain_id: ChainId,
+        target_chain_id: ChainId,
+        msg: Self::BatchableEvent,
+    ) -> Option<MakePacketTimeout> {
+        match msg {
+            EventUnion::PacketSend(event) => Some(MakePacketTimeout {
+                origin_chain_id,
+                target_chain_id,
+                event,
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl ClientConfigs {
@@ -206,6 +391,10 @@ impl Plugin for Module {
     type Cmd = DefaultCmd;
 
     async fn new(config: Self::Config) -> Result<Self, BoxDynError> {
+        if let Some(metrics_laddr) = config.metrics_laddr {
+            voyager_message::metrics::serve(metrics_laddr)?;
+        }
+
         Ok(Module::new(config))
     }
 
@@ -242,18 +431,20 @@ if ."@type" == "data" then
         ) or (
             $event_type == "channel_open_try"
             and ($event_data.connection.counterparty.client_id as $client_id | {clients_filter})
+            and ($event_data.counterparty_channel_id as $channel_id | {channels_filter})
         ) or (
             $event_type == "channel_open_ack"
             and ($event_data.connection.counterparty.client_id as $client_id | {clients_filter})
+            and ($event_data.counterparty_channel_id as $channel_id | {channels_filter})
         ) or (
             $event_type == "send_packet"
             and ($event_data.packet.destination_channel.connection.client_id as $client_id | {clients_filter})
+            and ($event_data.packet.destination_channel.channel_id as $channel_id | {channels_filter})
         ) or (
             $event_type == "write_ack"
             and ($event_data.packet.source_channel.connection.client_id as $client_id | {clients_filter})
-        ) or ($data."@type" == "plugin"
-            and $data."@value".plugin == "{plugin_name}"
-            and $data."@value".message."@type" == "event_batch")
+            and ($event_data.packet.source_channel.channel_id as $channel_id | {channels_filter})
+        )
     # ibc union
     elif $data."@type" == "ibc_event" and $data."@value".counterparty_chain_id == "{chain_id}" and $data."@value".ibc_spec_id == "{ibc_union_id}" then
         $data."@value".event."@type" as $event_type |
@@ -274,18 +465,24 @@ if ."@type" == "data" then
         ) or (
             $event_type == "channel_open_try"
             and ($event_data.connection.counterparty_client_id as $client_id | {clients_filter})
+            and ($event_data.counterparty_channel_id as $channel_id | {channels_filter})
         ) or (
             $event_type == "channel_open_ack"
             and ($event_data.connection.counterparty_client_id as $client_id | {clients_filter})
+            and ($event_data.counterparty_channel_id as $channel_id | {channels_filter})
         ) or (
             $event_type == "packet_send"
             and ($event_data.packet.destination_channel.connection.client_id as $client_id | {clients_filter})
+            and ($event_data.packet.destination_channel.channel_id as $channel_id | {channels_filter})
         ) or (
             $event_type == "write_ack"
             and ($event_data.packet.source_channel.connection.client_id as $client_id | {clients_filter})
-        ) or ($data."@type" == "plugin"
-            and $data."@value".plugin == "{plugin_name}"
-            and $data."@value".message."@type" == "event_batch")
+            and ($event_data.packet.source_channel.channel_id as $channel_id | {channels_filter})
+        )
+    # events that were sent back to be batched again
+    elif $data."@type" == "plugin" and $data."@value".plugin == "{plugin_name}" then
+        $data."@value".message."@type" == "batch_events_v1"
+        or $data."@value".message."@type" == "batch_events_union"
     else
         false
     end
@@ -296,6 +493,7 @@ end
                 chain_id = module.chain_id,
                 plugin_name = module.plugin_name(),
                 clients_filter = module.client_configs.jaq_filter(),
+                channels_filter = module.channels_jaq_filter(),
                 ibc_v1_id = IbcClassic::ID,
                 ibc_union_id = IbcUnion::ID,
             ),
@@ -321,6 +519,86 @@ impl Module {
         Self {
             chain_id: config.chain_id,
             client_configs: ClientConfigs::new(config.client_configs),
+            commitment_prefixes: config.commitment_prefixes,
+            channels: config.channels,
+            expire_timed_out_packets: config.expire_timed_out_packets,
+            max_trusted_height_wait_time: config.max_trusted_height_wait_time,
+            packet_timeout_retry_interval: config.packet_timeout_retry_interval,
+        }
+    }
+
+    fn channels_jaq_filter(&self) -> String {
+        match &self.channels {
+            None => "true".to_owned(),
+            Some(channels) => {
+                let channels_json = serde_json::to_string(
+                    &channels.iter().map(|c| (c, ())).collect::<HashMap<_, _>>(),
+                )
+                .unwrap();
+
+                format!("{channels_json} | has($channel_id | tostring)")
+            }
+        }
+    }
+
+    /// Whether events on `channel_id` should be relayed, as per the configured `channels`. This
+    /// is the rust equivalent of [`Self::channels_jaq_filter`].
+    fn channel_allowed(&self, channel_id: &str) -> bool {
+        match &self.channels {
+            None => true,
+            Some(channels) => channels.iter().any(|c| c == channel_id),
+        }
+    }
+
+    /// The deadline of a [`WaitForTrustedHeight`] queued now, as per the configured
+    /// `max_trusted_height_wait_time`.
+    fn trusted_height_deadline(&self) -> Option<u64> {
+        self.max_trusted_height_wait_time
+            .map(|wait_time| now() + wait_time.as_secs())
+    }
+
+    /// The progress record of the handshake message that is constructed by `msg`, if any.
+    fn handshake_progress<V: IbcSpecExt>(&self, msg: &MakeMsg<V>) -> Option<ModuleData>
+    where
+        ModuleData: From<HandshakeProgress<V>>,
+    {
+        V::handshake_step(&msg.event).map(|step| {
+            HandshakeProgress::<V> {
+                step: step.to_owned(),
+                origin_chain_id: msg.origin_chain_id.clone(),
+                target_chain_id: msg.target_chain_id.clone(),
+                event: msg.event.clone(),
+            }
+            .into()
+        })
+    }
+
+    /// Attach the handshake `progress` record to the `op` constructing the handshake message, such
+    /// that it is passed to the [`MakeBatchTransaction`] callback along with the constructed
+    /// message. This must only be called once the message has been constructed successfully.
+    ///
+    /// [`MakeBatchTransaction`]: crate::callback::MakeBatchTransaction
+    fn with_handshake_progress(
+        &self,
+        progress: Option<ModuleData>,
+        op: Op<VoyagerMessage>,
+    ) -> Op<VoyagerMessage> {
+        match progress {
+            Some(progress) => conc([op, data(PluginMessage::new(self.plugin_name(), progress))]),
+            None => op,
+        }
+    }
+
+    /// The commitment prefix of `chain_id`, falling back to [`DEFAULT_COMMITMENT_PREFIX`] if it
+    /// is not configured.
+    fn commitment_prefix(&self, chain_id: &ChainId) -> MerklePrefix {
+        MerklePrefix {
+            key_prefix: self
+                .commitment_prefixes
+                .get(chain_id)
+                .map_or(DEFAULT_COMMITMENT_PREFIX, String::as_str)
+                .as_bytes()
+                .into(),
         }
     }
 }
@@ -345,10 +623,24 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
             ModuleCall::MakeTransactionBatchesWithUpdateUnion(mk) => {
                 mk.call(self, e.try_get()?).await
             }
-            ModuleCall::MakeMsgV1(make_msg_v1) => do_make_msg_v1(voyager_client, make_msg_v1).await,
+            ModuleCall::MakeMsgV1(make_msg_v1) => {
+                let progress = self.handshake_progress(&make_msg_v1);
+                do_make_msg_v1(self, voyager_client, make_msg_v1)
+                    .await
+                    .map(|op| self.with_handshake_progress(progress, op))
+            }
             ModuleCall::MakeMsgUnion(make_msg_union) => {
-                do_make_msg_union(voyager_client, make_msg_union).await
+                let progress = self.handshake_progress(&make_msg_union);
+                do_make_msg_union(self, voyager_client, make_msg_union)
+                    .await
+                    .map(|op| self.with_handshake_progress(progress, op))
             }
+            ModuleCall::MakePacketTimeout(make_packet_timeout) => {
+                do_make_packet_timeout(self, voyager_client, make_packet_timeout).await
+            }
+            ModuleCall::RecordPacketRelayLatency(record) => Ok(record.call()),
+            ModuleCall::RecordHandshakeProgressV1(record) => Ok(record.call()),
+            ModuleCall::RecordHandshakeProgressUnion(record) => Ok(record.call()),
         }
     }
 
@@ -366,14 +658,22 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
             ModuleCallback::MakeIbcMessagesFromUpdateUnion(cb) => {
                 cb.call(e.try_get()?, self, datas).await
             }
-            ModuleCallback::MakeBatchTransactionV1(cb) => Ok(cb.call(self.chain_id.clone(), datas)),
-            ModuleCallback::MakeBatchTransactionUnion(cb) => {
-                Ok(cb.call(self.chain_id.clone(), datas))
-            }
+            ModuleCallback::MakeBatchTransactionV1(cb) => cb.call(self, datas),
+            ModuleCallback::MakeBatchTransactionUnion(cb) => cb.call(self, datas),
         }
     }
 }
 
+/// The current unix timestamp, in milliseconds.
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        .try_into()
+        .expect("how many milliseconds can there be man")
+}
+
 #[instrument(
     skip_all,
     fields(
@@ -384,6 +684,7 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
     )
 )]
 async fn do_make_msg_union(
+    module: &Module,
     voyager_client: &VoyagerClient,
     MakeMsg {
         origin_chain_id,
@@ -746,10 +1047,32 @@ async fn do_make_msg_union(
             let packet = Packet {
                 source_channel_id: event.packet.source_channel.channel_id,
                 destination_channel_id: event.packet.destination_channel.channel_id,
-                data: event.packet_data.into(),
+                data: event.packet_data.clone().into(),
                 timeout_height: event.packet.timeout_height,
                 timeout_timestamp: event.packet.timeout_timestamp,
             };
+
+            // the destination chain will reject a recv for a packet that has already timed out, so
+            // don't waste a transaction on it
+            if is_packet_timed_out(voyager_client, &target_chain_id, &packet).await? {
+                warn!(
+                    source_channel_id = packet.source_channel_id,
+                    destination_channel_id = packet.destination_channel_id,
+                    timeout_height = packet.timeout_height,
+                    timeout_timestamp = packet.timeout_timestamp,
+                    "packet has already timed out on {target_chain_id}, relaying the timeout \
+                    instead of the recv"
+                );
+
+                return Ok(data(PluginMessage::new(
+                    module.plugin_name(),
+                    ModuleData::from(MakePacketTimeout {
+                        origin_chain_id,
+                        target_chain_id,
+                        event,
+                    }),
+                )));
+            }
             let proof_try = voyager_client
                 .query_ibc_proof(
                     origin_chain_id,
@@ -832,7 +1155,198 @@ async fn do_make_msg_union(
     }
 }
 
+/// Returns whether `packet` can no longer be received on `target_chain_id`, based on the latest
+/// height and timestamp of that chain.
+async fn is_packet_timed_out(
+    voyager_client: &VoyagerClient,
+    target_chain_id: &ChainId,
+    packet: &Packet,
+) -> RpcResult<bool> {
+    let latest_height = voyager_client
+        .query_latest_height(target_chain_id.clone(), false)
+        .await?;
+
+    let latest_timestamp = voyager_client
+        .query_latest_timestamp(target_chain_id.clone(), false)
+        .await?;
+
+    Ok(is_packet_timed_out_at(
+        packet,
+        latest_height.height(),
+        latest_timestamp.as_nanos(),
+    ))
+}
+
+/// Returns whether `packet` can no longer be received on a chain at `height` and `timestamp` (in
+/// nanoseconds).
+fn is_packet_timed_out_at(packet: &Packet, height: u64, timestamp: u64) -> bool {
+    (packet.timeout_height > 0 && height >= packet.timeout_height)
+        || (packet.timeout_timestamp > 0 && timestamp >= packet.timeout_timestamp)
+}
+
+/// The path and height to prove the timeout of `packet` at, given the latest finalized `height`
+/// and `timestamp` (in nanoseconds) of its destination chain. A timeout is proven by the absence
+/// of the packet receipt on the destination chain, at a height where the packet has timed out.
+///
+/// Returns `None` if the packet has not yet timed out at `height`.
+fn packet_timeout_proof_request(
+    packet: &Packet,
+    height: Height,
+    timestamp: u64,
+) -> Option<(ibc_union_spec::BatchReceiptsPath, Height)> {
+    is_packet_timed_out_at(packet, height.height(), timestamp).then(|| {
+        (
+            ibc_union_spec::BatchReceiptsPath {
+                channel_id: packet.destination_channel_id,
+                batch_hash: keccak256(packet.abi_encode()),
+            },
+            height,
+        )
+    })
+}
+
+/// Submit the timeout of `packet` on `origin_chain_id`, once the client (`client_id`) tracking the
+/// destination chain has been updated to `proof_height`. `update` updates the client, if it is not
+/// yet trusted at `proof_height`.
+fn packet_timeout_op(
+    module: &Module,
+    origin_chain_id: ChainId,
+    client_id: <IbcUnion as IbcSpec>::ClientId,
+    update: Option<Op<VoyagerMessage>>,
+    packet: Packet,
+    proof: Bytes,
+    proof_height: Height,
+) -> Op<VoyagerMessage> {
+    seq(update.into_iter().chain([
+        call(WaitForTrustedHeight {
+            chain_id: origin_chain_id.clone(),
+            ibc_spec_id: IbcUnion::ID,
+            client_id: RawClientId::new(client_id),
+            height: proof_height,
+            finalized: false,
+            deadline: module.trusted_height_deadline(),
+        }),
+        call(SubmitTx {
+            chain_id: origin_chain_id,
+            datagrams: vec![IbcDatagram::new::<IbcUnion>(
+                ibc_union_spec::Datagram::from(ibc_union_spec::MsgPacketTimeout {
+                    packet,
+                    proof,
+                    proof_height: proof_height.height(),
+                }),
+            )],
+        }),
+    ]))
+}
+
+async fn do_make_packet_timeout(
+    module: &Module,
+    voyager_client: &VoyagerClient,
+    msg: MakePacketTimeout,
+) -> RpcResult<Op<VoyagerMessage>> {
+    let MakePacketTimeout {
+        origin_chain_id,
+        target_chain_id,
+        event,
+    } = msg.clone();
+
+    let packet = Packet {
+        source_channel_id: event.packet.source_channel.channel_id,
+        destination_channel_id: event.packet.destination_channel.channel_id,
+        data: event.packet_data.into(),
+        timeout_height: event.packet.timeout_height,
+        timeout_timestamp: event.packet.timeout_timestamp,
+    };
+
+    // the proof must be read at a height where the packet has timed out, otherwise the timeout
+    // will be rejected by the origin chain
+    let latest_height = voyager_client
+        .query_latest_height(target_chain_id.clone(), true)
+        .await?;
+
+    let latest_timestamp = voyager_client
+        .query_latest_timestamp(target_chain_id.clone(), true)
+        .await?;
+
+    let Some((proof_path, proof_height)) =
+        packet_timeout_proof_request(&packet, latest_height, latest_timestamp.as_nanos())
+    else {
+        debug!(
+            %latest_height,
+            %latest_timestamp,
+            "packet has not yet timed out at the latest finalized height of {target_chain_id}, \
+            waiting for finalization"
+        );
+
+        return Ok(seq([
+            defer(now() + module.packet_timeout_retry_interval.as_secs()),
+            call(PluginMessage::new(
+                module.plugin_name(),
+                ModuleCall::from(msg),
+            )),
+        ]));
+    };
+
+    let proof_receipt = voyager_client
+        .query_ibc_proof(
+            target_chain_id.clone(),
+            QueryHeight::Specific(proof_height),
+            proof_path,
+        )
+        .await?;
+
+    let client_id = event.packet.source_channel.connection.client_id;
+
+    let client_info = voyager_client
+        .client_info::<IbcUnion>(origin_chain_id.clone(), client_id)
+        .await?;
+
+    let client_meta = voyager_client
+        .client_meta::<IbcUnion>(origin_chain_id.clone(), QueryHeight::Latest, client_id)
+        .await?;
+
+    let encoded_proof_receipt = voyager_client
+        .encode_proof::<IbcUnion>(
+            client_info.client_type.clone(),
+            client_info.ibc_interface,
+            proof_receipt.proof,
+        )
+        .await?;
+
+    // the client on the origin chain is only updated by the batches of events on the origin chain,
+    // which there may not be any more of, so update it to the proof height here
+    let update = (client_meta.counterparty_height < proof_height).then(|| {
+        promise(
+            [call(FetchUpdateHeaders {
+                client_type: client_info.client_type,
+                chain_id: target_chain_id,
+                counterparty_chain_id: origin_chain_id.clone(),
+                client_id: RawClientId::new(client_id),
+                update_from: client_meta.counterparty_height,
+                update_to: proof_height,
+            })],
+            [],
+            AggregateMsgUpdateClientsFromOrderedHeaders {
+                ibc_spec_id: IbcUnion::ID,
+                chain_id: origin_chain_id.clone(),
+                client_id: RawClientId::new(client_id),
+            },
+        )
+    });
+
+    Ok(packet_timeout_op(
+        module,
+        origin_chain_id,
+        client_id,
+        update,
+        packet,
+        encoded_proof_receipt,
+        proof_height,
+    ))
+}
+
 async fn do_make_msg_v1(
+    module: &Module,
     voyager_client: &VoyagerClient,
     MakeMsg {
         origin_chain_id,
@@ -848,7 +1362,7 @@ async fn do_make_msg_v1(
                 encoded_connection_state_proof,
             } = mk_connection_handshake_state_and_proofs(
                 voyager_client,
-                origin_chain_id,
+                origin_chain_id.clone(),
                 target_chain_id,
                 connection_open_init_event.client_id.clone(),
                 connection_open_init_event.counterparty_client_id.clone(),
@@ -863,10 +1377,7 @@ async fn do_make_msg_v1(
                     counterparty: connection::counterparty::Counterparty {
                         client_id: connection_open_init_event.client_id,
                         connection_id: Some(connection_open_init_event.connection_id),
-                        prefix: MerklePrefix {
-                            // TODO: Make configurable
-                            key_prefix: b"ibc".into(),
-                        },
+                        prefix: module.commitment_prefix(&origin_chain_id),
                     },
                     // TODO: Make configurable
                     delay_period: DELAY_PERIOD,
@@ -1119,6 +1630,54 @@ async fn do_make_msg_v1(
         //         proof_height: origin_chain_proof_height,
         //     })))
         // }
+        EventClassic::WriteAcknowledgement(event) => {
+            // the acknowledgement is proven at the height the client on the target chain is
+            // being updated to, not the height the event was emitted at
+            let proof_acked = voyager_client
+                .query_ibc_proof(
+                    origin_chain_id,
+                    QueryHeight::Specific(origin_chain_proof_height),
+                    ibc_classic_spec::AcknowledgementPath {
+                        port_id: event.packet.destination_channel.port_id.clone(),
+                        channel_id: event.packet.destination_channel.channel_id.clone(),
+                        sequence: event.packet.sequence,
+                    },
+                )
+                .await?;
+
+            let client_info = voyager_client
+                .client_info::<IbcClassic>(
+                    target_chain_id,
+                    event.packet.source_channel.connection.client_id.clone(),
+                )
+                .await?;
+
+            let encoded_proof_acked = voyager_client
+                .encode_proof::<IbcClassic>(
+                    client_info.client_type,
+                    client_info.ibc_interface,
+                    proof_acked.proof,
+                )
+                .await?;
+
+            Ok(data(IbcDatagram::new::<IbcClassic>(
+                ibc_classic_spec::Datagram::from(MsgAcknowledgement {
+                    packet: channel::packet::Packet {
+                        sequence: event.packet.sequence,
+                        source_port: event.packet.source_channel.port_id,
+                        source_channel: event.packet.source_channel.channel_id,
+                        destination_port: event.packet.destination_channel.port_id,
+                        destination_channel: event.packet.destination_channel.channel_id,
+                        data: event.packet_data,
+                        timeout_height: event.packet.timeout_height,
+                        timeout_timestamp: event.packet.timeout_timestamp,
+                    },
+                    acknowledgement: event.packet_ack,
+                    proof_acked: encoded_proof_acked,
+                    proof_height: origin_chain_proof_height,
+                }),
+            )))
+        }
 
         // MakeMsgV1::MakeMsgRecvPacket(msg) => make_msg_recv_packet(ctx, msg).await,
         _ => todo!(),
@@ -1145,12 +1704,7 @@ impl Module {
 
                 match ChainEvent::try_from(msg) {
                     Ok(chain_event) => {
-                        let first_seen_at: u64 = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_millis()
-                            .try_into()
-                            .expect("how many milliseconds can there be man");
+                        let first_seen_at = now_millis();
 
                         // client_id is the client id of the client on this chain (we are the counterparty from the perspective of the chain where the event was emitted)
                         // this is the client that will need to be updated before this ibc message can be sent
@@ -1221,6 +1775,13 @@ impl Module {
                                     .or_default()
                                     .extend(message.events.into_iter().map(|event| (idx, event)));
                             }
+                            Ok(
+                                message @ (ModuleData::PacketTimedOut(_)
+                                | ModuleData::HandshakeProgressV1(_)
+                                | ModuleData::HandshakeProgressUnion(_)),
+                            ) => {
+                                error!("unexpected message: {message:?}");
+                            }
                             Err(msg) => {
                                 error!("unexpected message: {msg:?}");
                             }
@@ -1229,18 +1790,44 @@ impl Module {
                 };
             }
 
+            let mut expired_v1 = vec![];
             let (ready_v1, optimize_further_v1) = batchers_v1
                 .into_iter()
-                .flat_map(|(client_id, events)| split_ready(client_id, events, self))
+                .flat_map(|(client_id, events)| {
+                    let (expired, split) = split_ready(client_id.clone(), events, self);
+                    expired_v1.push((client_id, expired));
+                    split
+                })
                 .partition_map::<Vec<_>, Vec<_>, _, _, _>(convert::identity);
 
+            let mut expired_union = vec![];
             let (ready_union, optimize_further_union) = batchers_union
                 .into_iter()
-                .flat_map(|(client_id, events)| split_ready(client_id, events, self))
+                .flat_map(|(client_id, events)| {
+                    let (expired, split) = split_ready(client_id, events, self);
+                    expired_union.push((client_id, expired));
+                    split
+                })
                 .partition_map::<Vec<_>, Vec<_>, _, _, _>(convert::identity);
 
             let voyager_client = e.try_get::<VoyagerClient>()?;
 
+            let expired_v1 = expired_v1
+                .into_iter()
+                .filter(|(_, expired)| !expired.is_empty())
+                .map(|(client_id, expired)| {
+                    mk_timeout_ops(client_id, expired, self, voyager_client)
+                })
+                .collect::<FuturesOrdered<_>>();
+
+            let expired_union = expired_union
+                .into_iter()
+                .filter(|(_, expired)| !expired.is_empty())
+                .map(|(client_id, expired)| {
+                    mk_timeout_ops(client_id, expired, self, voyager_client)
+                })
+                .collect::<FuturesOrdered<_>>();
+
             let ready_v1 = ready_v1
                 .into_iter()
                 .into_group_map()
@@ -1260,7 +1847,12 @@ impl Module {
                     .into_iter()
                     .chain(optimize_further_union)
                     .collect(),
-                ready: ready_v1.chain(ready_union).map(|x| x).try_collect().await?,
+                ready: ready_v1
+                    .chain(ready_union)
+                    .chain(expired_v1)
+                    .chain(expired_union)
+                    .try_collect()
+                    .await?,
             })
         })
     }
@@ -1374,14 +1966,18 @@ fn split_ready<V: IbcSpecExt>(
     client_id: V::ClientId,
     mut events: Vec<(usize, BatchableEvent<V>)>,
     this: &Module,
-) -> Vec<
-    Either<
-        // ready
-        (V::ClientId, (Vec<usize>, Vec<BatchableEvent<V>>)),
-        // optimize further
-        (Vec<usize>, Op<VoyagerMessage>, String),
+) -> (
+    // expired
+    Vec<(usize, BatchableEvent<V>)>,
+    Vec<
+        Either<
+            // ready
+            (V::ClientId, (Vec<usize>, Vec<BatchableEvent<V>>)),
+            // optimize further
+            (Vec<usize>, Op<VoyagerMessage>, String),
+        >,
     >,
->
+)
 where
     ModuleData: From<EventBatch<V>>,
 {
@@ -1390,6 +1986,46 @@ where
     events.sort_by_key(|e| e.1.first_seen_at);
 
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    // events that were queued before the configured channels were narrowed will still be routed
+    // to this plugin, so they need to be filtered here as well
+    events.retain(|(_, e)| match V::filtered_channel(&e.event) {
+        Some(channel_id) if !this.channel_allowed(&channel_id) => {
+            warn!(
+                %client_id,
+                %channel_id,
+                event = V::event_name(&e.event),
+                "event is on a channel that is not configured to be relayed, dropping it"
+            );
+            record_dropped(DropReason::ChannelFiltered);
+            false
+        }
+        _ => true,
+    });
+
+    // packets that can no longer be received are timed out on their origin chain instead
+    let expired = if this.expire_timed_out_packets {
+        let (expired, remaining): (Vec<_>, Vec<_>) = events.into_iter().partition(|(_, e)| {
+            V::packet_send_timeout_timestamp(&e.event)
+                .is_some_and(|timeout_timestamp| u128::from(timeout_timestamp) <= now.as_nanos())
+        });
+
+        for (_, e) in &expired {
+            warn!(
+                %client_id,
+                timeout_timestamp = V::packet_send_timeout_timestamp(&e.event),
+                "packet has timed out while waiting to be batched, timing it out on the origin \
+                chain"
+            );
+        }
+
+        events = remaining;
+
+        expired
+    } else {
+        vec![]
+    };
+
     let is_overdue =
         |first_seen_at| Duration::from_millis(first_seen_at) + client_config.max_wait_time < now;
 
@@ -1418,7 +2054,7 @@ where
     }
 
     // [...overdue_events_sorted_by_provable_height, ...events_sorted_by_provable_height]
-    overdue_events
+    let split = overdue_events
         .into_iter()
         .chain(events)
         .chunks(client_config.max_batch_size)
@@ -1450,7 +2086,53 @@ where
                 ))
             }
         })
-        .collect::<Vec<_>>()
+        .collect::<Vec<_>>();
+
+    (expired, split)
+}
+
+/// Time out the packets of the `expired` events on their origin chain, which is the chain tracked by
+/// `client_id`.
+async fn mk_timeout_ops<V: IbcSpecExt>(
+    client_id: V::ClientId,
+    expired: Vec<(usize, BatchableEvent<V>)>,
+    module: &Module,
+    voyager_client: &VoyagerClient,
+) -> RpcResult<(Vec<usize>, Op<VoyagerMessage>)> {
+    let client_meta = voyager_client
+        .client_meta::<V>(
+            module.chain_id.clone(),
+            QueryHeight::Latest,
+            client_id.clone(),
+        )
+        .await?;
+
+    let (idxs, events): (Vec<_>, Vec<_>) = expired.into_iter().unzip();
+
+    Ok((
+        idxs,
+        conc(events.into_iter().map(|e| {
+            match V::make_packet_timeout(
+                client_meta.chain_id.clone(),
+                module.chain_id.clone(),
+                e.event,
+            ) {
+                Some(timeout) => call(PluginMessage::new(
+                    module.plugin_name(),
+                    ModuleCall::from(timeout),
+                )),
+                None => {
+                    warn!(
+                        %client_id,
+                        ibc_spec_id = %V::ID,
+                        "packet timeouts are not supported for this IBC spec, dropping the packet"
+                    );
+                    record_dropped(DropReason::PacketTimedOut);
+                    noop()
+                }
+            }
+        })),
+    ))
 }
 
 async fn mk_ready_ops<V: IbcSpecExt>(
@@ -1507,6 +2189,11 @@ where
 #[cfg(test)]
 mod tests {
     use serde_json::json;
+    use voyager_message::{core::ClientType, filter::JaqInterestFilter};
+    use voyager_vm::{
+        filter::{FilterResult, InterestFilter},
+        noop,
+    };
 
     use super::*;
 
@@ -1526,4 +2213,403 @@ mod tests {
 
         let _config = serde_json::from_value::<Config>(config_json).unwrap();
     }
+
+    fn test_config() -> Config {
+        serde_json::from_value(json!({
+          "chain_id": "union-devnet-1",
+          "client_configs": {
+            "min_batch_size": 1,
+            "max_batch_size": 3,
+            "max_wait_time": {
+              "secs": 10,
+              "nanos": 0
+            }
+          }
+        }))
+        .unwrap()
+    }
+
+    fn is_interested(config: Config, op: &Op<VoyagerMessage>) -> bool {
+        let filter = JaqInterestFilter::new(vec![Module::info(config)]).unwrap();
+
+        matches!(filter.check_interest(op), FilterResult::Interest(_))
+    }
+
+    #[test]
+    fn interest_filter_matches_requeued_event_batches() {
+        let config = test_config();
+        let plugin_name = Module::new(config.clone()).plugin_name();
+
+        let v1 = data(PluginMessage::new(
+            &plugin_name,
+            ModuleData::from(EventBatch::<IbcClassic> {
+                client_id: ClientId::new("07-tendermint", 0),
+                events: vec![],
+            }),
+        ));
+        assert!(is_interested(config.clone(), &v1));
+
+        let union = data(PluginMessage::new(
+            &plugin_name,
+            ModuleData::from(EventBatch::<IbcUnion> {
+                client_id: 1,
+                events: vec![],
+            }),
+        ));
+        assert!(is_interested(config.clone(), &union));
+
+        // batches for another instance of this plugin are not picked up
+        let other = data(PluginMessage::new(
+            format!("{PLUGIN_NAME}/other-chain"),
+            ModuleData::from(EventBatch::<IbcUnion> {
+                client_id: 1,
+                events: vec![],
+            }),
+        ));
+        assert!(!is_interested(config, &other));
+    }
+
+    fn test_packet(timeout_height: u64, timeout_timestamp: u64) -> Packet {
+        Packet {
+            source_channel_id: 1,
+            destination_channel_id: 2,
+            data: b"data".to_vec().into(),
+            timeout_height,
+            timeout_timestamp,
+        }
+    }
+
+    /// Run the channels filter of a module configured with `channels` against `channel_id`, which
+    /// is a jaq expression.
+    fn channels_filter_matches(channels: Option<&[&str]>, channel_id: &str) -> bool {
+        let mut config = test_config();
+        config.channels = channels.map(|channels| channels.iter().map(|c| c.to_string()).collect());
+        let module = Module::new(config);
+
+        let filter = JaqInterestFilter::new(vec![PluginInfo {
+            name: module.plugin_name(),
+            interest_filter: format!(
+                "{channel_id} as $channel_id | {}",
+                module.channels_jaq_filter()
+            ),
+        }])
+        .unwrap();
+
+        matches!(filter.check_interest(&noop()), FilterResult::Interest(_))
+    }
+
+    #[test]
+    fn channels_jaq_filter_classic() {
+        assert!(channels_filter_matches(
+            Some(&["channel-0"]),
+            r#""channel-0""#
+        ));
+        assert!(!channels_filter_matches(
+            Some(&["channel-0"]),
+            r#""channel-1""#
+        ));
+        assert!(channels_filter_matches(None, r#""channel-1""#));
+    }
+
+    #[test]
+    fn channels_jaq_filter_union() {
+        // union channel ids are numeric, and are compared as strings
+        assert!(channels_filter_matches(Some(&["1", "2"]), "2"));
+        assert!(!channels_filter_matches(Some(&["1", "2"]), "3"));
+        assert!(channels_filter_matches(None, "3"));
+    }
+
+    #[test]
+    fn channel_allowed_matches_jaq_filter() {
+        let mut config = test_config();
+        config.channels = Some(vec!["channel-0".to_owned(), "1".to_owned()]);
+        let module = Module::new(config);
+
+        assert!(module.channel_allowed("channel-0"));
+        assert!(module.channel_allowed(&1_u32.to_string()));
+        assert!(!module.channel_allowed("channel-1"));
+        assert!(!module.channel_allowed(&2_u32.to_string()));
+    }
+
+    #[test]
+    fn handshake_progress_is_recorded_for_handshake_messages() {
+        let module = Module::new(test_config());
+
+        let make_msg = |event| MakeMsg::<IbcUnion> {
+            origin_chain_id: ChainId::new("origin"),
+            origin_chain_proof_height: Height::new(1),
+            target_chain_id: ChainId::new("target"),
+            event,
+        };
+
+        let connection_open_init =
+            EventUnion::ConnectionOpenInit(ibc_union_spec::ConnectionOpenInit {
+                connection_id: 1,
+                client_id: 2,
+                counterparty_client_id: 3,
+            });
+
+        assert_eq!(
+            module.handshake_progress(&make_msg(connection_open_init.clone())),
+            Some(ModuleData::from(HandshakeProgress::<IbcUnion> {
+                step: "connection_open_try".to_owned(),
+                origin_chain_id: ChainId::new("origin"),
+                target_chain_id: ChainId::new("target"),
+                event: connection_open_init,
+            }))
+        );
+
+        let packet_send = EventUnion::PacketSend(ibc_union_spec::PacketSend {
+            packet_data: b"data".to_vec().into(),
+            packet: ibc_union_spec::PacketMetadata {
+                source_channel: ibc_union_spec::ChannelMetadata {
+                    channel_id: 1,
+                    version: String::new(),
+                    connection: ibc_union_spec::ConnectionMetadata {
+                        client_id: 1,
+                        connection_id: 1,
+                    },
+                },
+                destination_channel: ibc_union_spec::ChannelMetadata {
+                    channel_id: 2,
+                    version: String::new(),
+                    connection: ibc_union_spec::ConnectionMetadata {
+                        client_id: 2,
+                        connection_id: 2,
+                    },
+                },
+                timeout_height: 0,
+                timeout_timestamp: 0,
+            },
+        });
+
+        assert_eq!(module.handshake_progress(&make_msg(packet_send)), None);
+    }
+
+    #[test]
+    fn handshake_progress_is_recorded_after_the_batch_is_submitted() {
+        let module = Module::new(test_config());
+
+        let progress = HandshakeProgress::<IbcUnion> {
+            step: "connection_open_try".to_owned(),
+            origin_chain_id: ChainId::new("origin"),
+            target_chain_id: ChainId::new("target"),
+            event: EventUnion::ConnectionOpenInit(ibc_union_spec::ConnectionOpenInit {
+                connection_id: 1,
+                client_id: 2,
+                counterparty_client_id: 3,
+            }),
+        };
+
+        let make_batch_transaction = callback::MakeBatchTransaction::<IbcUnion> {
+            client_id: 1,
+            updates: Some(voyager_message::data::OrderedClientUpdates { updates: vec![] }),
+            packets: vec![],
+        };
+
+        let op = make_batch_transaction
+            .clone()
+            .call(
+                &module,
+                [Data::from(PluginMessage::new(
+                    module.plugin_name(),
+                    ModuleData::from(progress.clone()),
+                ))]
+                .into(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            op,
+            seq([
+                call(SubmitTx {
+                    chain_id: module.chain_id.clone(),
+                    datagrams: vec![],
+                }),
+                call(PluginMessage::new(
+                    module.plugin_name(),
+                    ModuleCall::from(call::RecordHandshakeProgress::<IbcUnion> {
+                        progress: vec![progress.clone()],
+                    }),
+                )),
+            ])
+        );
+
+        // progress for a different ibc spec than the batch is unexpected
+        let progress_v1 = HandshakeProgress::<IbcClassic> {
+            step: "connection_open_try".to_owned(),
+            origin_chain_id: progress.origin_chain_id,
+            target_chain_id: progress.target_chain_id,
+            event: EventClassic::ConnectionOpenInit(ibc_classic_spec::ConnectionOpenInit {
+                connection_id: ConnectionId::new(1),
+                client_id: "client-2".parse().unwrap(),
+                counterparty_client_id: "client-3".parse().unwrap(),
+            }),
+        };
+
+        assert!(make_batch_transaction
+            .call(
+                &module,
+                [Data::from(PluginMessage::new(
+                    module.plugin_name(),
+                    ModuleData::from(progress_v1),
+                ))]
+                .into(),
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn expired_packets_are_timed_out() {
+        let first_seen_at = now_millis();
+
+        let packet_send = |timeout_timestamp| BatchableEvent::<IbcUnion> {
+            first_seen_at,
+            provable_height: Height::new(1),
+            event: EventUnion::PacketSend(ibc_union_spec::PacketSend {
+                packet_data: b"data".to_vec().into(),
+                packet: ibc_union_spec::PacketMetadata {
+                    source_channel: ibc_union_spec::ChannelMetadata {
+                        channel_id: 1,
+                        version: String::new(),
+                        connection: ibc_union_spec::ConnectionMetadata {
+                            client_id: 1,
+                            connection_id: 1,
+                        },
+                    },
+                    destination_channel: ibc_union_spec::ChannelMetadata {
+                        channel_id: 2,
+                        version: String::new(),
+                        connection: ibc_union_spec::ConnectionMetadata {
+                            client_id: 2,
+                            connection_id: 2,
+                        },
+                    },
+                    timeout_height: 0,
+                    timeout_timestamp,
+                },
+            }),
+        };
+
+        let events = vec![(0, packet_send(1)), (1, packet_send(u64::MAX))];
+
+        // expired packets are batched as usual if expiry is disabled
+        let (expired, split) = split_ready(2, events.clone(), &Module::new(test_config()));
+        assert!(expired.is_empty());
+        assert_eq!(split.len(), 1);
+
+        let mut config = test_config();
+        config.expire_timed_out_packets = true;
+
+        let (expired, split) = split_ready(2, events, &Module::new(config));
+        assert_eq!(expired, vec![(0, packet_send(1))]);
+        assert_eq!(split.len(), 1);
+
+        assert_eq!(
+            IbcUnion::make_packet_timeout(
+                ChainId::new("origin"),
+                ChainId::new("target"),
+                expired[0].1.event.clone()
+            )
+            .map(|timeout| timeout.event.packet.timeout_timestamp),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn trusted_height_deadline() {
+        assert_eq!(Module::new(test_config()).trusted_height_deadline(), None);
+
+        let mut config = test_config();
+        config.max_trusted_height_wait_time = Some(Duration::from_secs(60));
+
+        let before = now();
+        let deadline = Module::new(config).trusted_height_deadline().unwrap();
+        assert!((before + 60..=now() + 60).contains(&deadline));
+    }
+
+    #[test]
+    fn packet_timed_out_at() {
+        // timed out by height
+        assert!(!is_packet_timed_out_at(&test_packet(10, 0), 9, u64::MAX));
+        assert!(is_packet_timed_out_at(&test_packet(10, 0), 10, 0));
+
+        // timed out by timestamp
+        assert!(!is_packet_timed_out_at(
+            &test_packet(0, 1000),
+            u64::MAX,
+            999
+        ));
+        assert!(is_packet_timed_out_at(&test_packet(0, 1000), 0, 1000));
+
+        // either timeout is sufficient
+        assert!(is_packet_timed_out_at(&test_packet(10, 1000), 10, 0));
+        assert!(is_packet_timed_out_at(&test_packet(10, 1000), 0, 1000));
+    }
+
+    #[test]
+    fn packet_timeout_proves_receipt_on_destination_channel() {
+        let module = Module::new(test_config());
+        let packet = test_packet(10, 0);
+
+        // not yet timed out at the latest finalized height
+        assert_eq!(
+            packet_timeout_proof_request(&packet, Height::new(9), u64::MAX),
+            None
+        );
+
+        let (proof_path, proof_height) =
+            packet_timeout_proof_request(&packet, Height::new(11), 0).unwrap();
+
+        assert_eq!(
+            proof_path,
+            ibc_union_spec::BatchReceiptsPath {
+                channel_id: 2,
+                batch_hash: keccak256(packet.abi_encode()),
+            }
+        );
+        assert_eq!(proof_height, Height::new(11));
+
+        let update = call(FetchUpdateHeaders {
+            client_type: ClientType::new("client-type"),
+            chain_id: ChainId::new("target"),
+            counterparty_chain_id: ChainId::new("origin"),
+            client_id: RawClientId::new(1),
+            update_from: Height::new(5),
+            update_to: proof_height,
+        });
+
+        assert_eq!(
+            packet_timeout_op(
+                &module,
+                ChainId::new("origin"),
+                1,
+                Some(update.clone()),
+                packet.clone(),
+                b"proof".to_vec().into(),
+                proof_height,
+            ),
+            seq([
+                update,
+                call(WaitForTrustedHeight {
+                    chain_id: ChainId::new("origin"),
+                    ibc_spec_id: IbcUnion::ID,
+                    client_id: RawClientId::new(1),
+                    height: Height::new(11),
+                    finalized: false,
+                    deadline: None,
+                }),
+                call(SubmitTx {
+                    chain_id: ChainId::new("origin"),
+                    datagrams: vec![IbcDatagram::new::<IbcUnion>(
+                        ibc_union_spec::Datagram::from(ibc_union_spec::MsgPacketTimeout {
+                            packet,
+                            proof: b"proof".to_vec().into(),
+                            proof_height: 11,
+                        }),
+                    )],
+                }),
+            ])
+        );
+    }
 }
@@ -0,0 +1,13 @@
+use std::sync::LazyLock;
+
+use prometheus::{register_histogram_vec, HistogramVec};
+
+pub static PACKET_RELAY_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "voyager_transaction_batch_packet_relay_latency_seconds",
+        "The time from a packet send event first being seen to the transaction containing the corresponding recv message being submitted, by destination chain and channel.",
+        &["chain_id", "channel_id"],
+        vec![1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 3600.0],
+    )
+    .unwrap()
+});
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, net::SocketAddr};
 
 use chain_utils::{
     cosmos_sdk::{
@@ -53,6 +53,7 @@ use crate::{
 pub mod call;
 pub mod callback;
 pub mod data;
+pub mod metrics;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -79,6 +80,10 @@ pub struct Config {
     pub rpc_url: String,
     pub grpc_url: String,
     pub gas_config: GasConfig,
+    /// If set, serve the metrics of this plugin (such as the number of submitted, reverted, and
+    /// errored messages) on this address.
+    #[serde(default)]
+    pub metrics_laddr: Option<SocketAddr>,
 }
 
 impl Plugin for Module {
@@ -104,6 +109,10 @@ impl Plugin for Module {
         .into_inner()
         .bech32_prefix;
 
+        if let Some(metrics_laddr) = config.metrics_laddr {
+            voyager_message::metrics::serve(metrics_laddr)?;
+        }
+
         Ok(Self {
             ibc_host_contract_address: config.ibc_host_contract_address,
             keyring: CosmosKeyring::new(
@@ -247,37 +256,60 @@ impl Module {
                                 "submitted cosmos transaction"
                             );
 
+                            metrics::record_msgs(
+                                &self.chain_id,
+                                msg_names.iter().map(String::as_str),
+                                metrics::RESULT_SUBMITTED,
+                            );
+
                             for msg in msg_names {
                                 info!(%tx_hash, %msg, "cosmos tx");
                             }
 
                             Ok(())
                         }
-                        Err(err) => match err {
-                            BroadcastTxCommitError::Tx(CosmosSdkError::ChannelError(
-                                ChannelError::ErrRedundantTx,
-                            )) => {
-                                info!("packet messages are redundant");
-                                Ok(())
-                            }
-                            // BroadcastTxCommitError::Tx(CosmosSdkError::SdkError(
-                            //     SdkError::ErrOutOfGas
-                            // )) => {
-                            //     error!("out of gas");
-                            //     Err(BroadcastTxCommitError::OutOfGas)
-                            // }
-                            BroadcastTxCommitError::Tx(CosmosSdkError::SdkError(
-                                SdkError::ErrWrongSequence
-                            )) => {
-                                warn!("account sequence mismatch on tx submission, message will be requeued and retried");
-                                Err(BroadcastTxCommitError::AccountSequenceMismatch(None))
-                            }
-                            BroadcastTxCommitError::SimulateTx(err) if err.message().contains("account sequence mismatch") => {
-                                warn!("account sequence mismatch on simulation, message will be requeued and retried");
-                                Err(BroadcastTxCommitError::AccountSequenceMismatch(Some(err)))
+                        Err(err) => {
+                            metrics::record_msgs(
+                                &self.chain_id,
+                                msg_names.iter().map(String::as_str),
+                                match err {
+                                    BroadcastTxCommitError::Tx(CosmosSdkError::ChannelError(
+                                        ChannelError::ErrRedundantTx,
+                                    )) => metrics::RESULT_REDUNDANT,
+                                    BroadcastTxCommitError::Tx(_)
+                                    | BroadcastTxCommitError::IbcUnionError(_) => {
+                                        metrics::RESULT_REVERTED
+                                    }
+                                    _ => metrics::RESULT_ERRORED,
+                                },
+                            );
+
+                            match err {
+                                BroadcastTxCommitError::Tx(CosmosSdkError::ChannelError(
+                                    ChannelError::ErrRedundantTx,
+                                )) => {
+                                    info!("packet messages are redundant");
+                                    Ok(())
+                                }
+                                // BroadcastTxCommitError::Tx(CosmosSdkError::SdkError(
+                                //     SdkError::ErrOutOfGas
+                                // )) => {
+                                //     error!("out of gas");
+                                //     Err(BroadcastTxCommitError::OutOfGas)
+                                // }
+                                BroadcastTxCommitError::Tx(CosmosSdkError::SdkError(
+                                    SdkError::ErrWrongSequence
+                                )) => {
+                                    warn!("account sequence mismatch on tx submission, message will be requeued and retried");
+                                    Err(BroadcastTxCommitError::AccountSequenceMismatch(None))
+                                }
+                                BroadcastTxCommitError::SimulateTx(err) if err.message().contains("account sequence mismatch") => {
+                                    warn!("account sequence mismatch on simulation, message will be requeued and retried");
+                                    Err(BroadcastTxCommitError::AccountSequenceMismatch(Some(err)))
+                                }
+                                err => Err(err),
                             }
-                            err => Err(err),
-                        },
+                        }
                     }
                 }
             })
@@ -320,21 +352,27 @@ impl Module {
     ) -> Result<(H256, BoundedI64<0, { i64::MAX }>), BroadcastTxCommitError> {
         let account = self.account_info(&signer.to_string()).await;
 
-        let (tx_body, mut auth_info, simulation_gas_info) =
-            match self.simulate_tx(signer, messages, memo).await {
-                Ok((tx_body, auth_info, simulation_gas_info)) => {
-                    (tx_body, auth_info, simulation_gas_info)
-                }
-                Err((tx_body, auth_info, _err)) => (
-                    tx_body,
-                    auth_info,
-                    GasInfo {
-                        gas_wanted: u64::MAX,
-                        gas_used: u64::MAX,
+        // never submit a transaction that failed simulation, as it would either run out of gas or
+        // revert, wasting fees
+        let (tx_body, mut auth_info, simulation_gas_info) = self
+            .simulate_tx(signer, messages, memo)
+            .await
+            .map_err(|(_, _, err)| {
+                // known errors are classified the same as if the transaction had failed on-chain
+                // (i.e. redundant messages are not an error); anything else may be transient and
+                // is retried
+                match err
+                    .message()
+                    .split(": ")
+                    .find_map(ibc_union::ContractErrorKind::parse_from_error_message)
+                {
+                    Some(ibc_union_error) => BroadcastTxCommitError::IbcUnionError(ibc_union_error),
+                    None => match CosmosSdkError::parse_from_error_message(err.message()) {
+                        Some(error) => BroadcastTxCommitError::Tx(error),
+                        None => BroadcastTxCommitError::SimulateTx(err),
                     },
-                ),
-            };
-        // .map_err(BroadcastTxCommitError::SimulateTx)?;
+                }
+            })?;
 
         info!(
             gas_used = %simulation_gas_info.gas_used,
@@ -866,6 +904,16 @@ fn process_msgs(
                             ),
                         })
                     }
+                    ibc_classic_spec::Datagram::SubmitMisbehaviour(message) => {
+                        mk_any(&protos::ibc::core::client::v1::MsgSubmitMisbehaviour {
+                            client_id: message.client_id.to_string(),
+                            misbehaviour: Some(
+                                protos::google::protobuf::Any::decode(&*message.misbehaviour)
+                                    .expect("value should be encoded as an `Any`"),
+                            ),
+                            signer: signer.to_string(),
+                        })
+                    }
                 },
                 IbcMessage::IbcUnion(msg) => match msg {
                     ibc_union_spec::Datagram::CreateClient(msg_create_client) => {
@@ -1094,7 +1142,23 @@ fn process_msgs(
                             funds: vec![],
                         })
                     }
-                    ibc_union_spec::Datagram::PacketTimeout(_msg_packet_timeout) => todo!(),
+                    ibc_union_spec::Datagram::PacketTimeout(msg_packet_timeout) => {
+                        let packet_timeout = ibc_union_msg::msg::ExecuteMsg::PacketTimeout(
+                            ibc_union_msg::msg::MsgPacketTimeout {
+                                packet: msg_packet_timeout.packet,
+                                proof: msg_packet_timeout.proof,
+                                proof_height: msg_packet_timeout.proof_height,
+                                relayer: signer.to_string(),
+                            },
+                        );
+
+                        mk_any(&protos::cosmwasm::wasm::v1::MsgExecuteContract {
+                            sender: signer.to_string(),
+                            contract: ibc_host_contract_address.to_string(),
+                            msg: serde_json::to_vec(&packet_timeout).unwrap(),
+                            funds: vec![],
+                        })
+                    }
                     ibc_union_spec::Datagram::IntentPacketRecv(_msg_intent_packet_recv) => todo!(),
                     ibc_union_spec::Datagram::BatchSend(_msg_batch_send) => todo!(),
                     ibc_union_spec::Datagram::BatchAcks(_msg_batch_acks) => todo!(),
@@ -0,0 +1,27 @@
+use std::sync::LazyLock;
+
+use prometheus::{register_int_counter_vec, IntCounterVec};
+use voyager_message::core::ChainId;
+
+pub const RESULT_SUBMITTED: &str = "submitted";
+pub const RESULT_REVERTED: &str = "reverted";
+/// The message was not executed because it had already been handled, i.e. by another relayer.
+pub const RESULT_REDUNDANT: &str = "redundant";
+pub const RESULT_ERRORED: &str = "errored";
+
+pub static MSGS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "voyager_transaction_cosmos_sdk_msgs_total",
+        "The number of messages submitted in transactions, by chain, message type, and result (submitted, reverted, redundant, or errored).",
+        &["chain_id", "msg", "result"],
+    )
+    .unwrap()
+});
+
+/// Count `msgs` as having been submitted on `chain_id` with the given result.
+pub fn record_msgs<'a>(chain_id: &ChainId, msgs: impl IntoIterator<Item = &'a str>, result: &str) {
+    for msg in msgs {
+        MSGS.with_label_values(&[chain_id.as_str(), msg, result])
+            .inc();
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive failed submissions after which submission is paused.
+    pub threshold: u32,
+    /// How long to pause submission for once tripped, in seconds.
+    pub cooldown: u64,
+}
+
+/// Stops transaction submission after too many consecutive failures, to bound the amount of gas
+/// that can be burned by e.g. a bug causing every submitted transaction to revert.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<CircuitBreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitBreakerState {
+    consecutive_failures: u32,
+    tripped_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    #[must_use]
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            threshold: config.threshold,
+            cooldown: Duration::from_secs(config.cooldown),
+            state: Mutex::new(CircuitBreakerState::default()),
+        }
+    }
+
+    /// Returns the remaining cooldown if the breaker is currently tripped. The breaker is reset
+    /// once the cooldown has elapsed.
+    pub fn check(&self) -> Option<Duration> {
+        let mut state = self.state.lock().expect("mutex is poisoned");
+
+        let tripped_at = state.tripped_at?;
+
+        match self.cooldown.checked_sub(tripped_at.elapsed()) {
+            Some(remaining) if !remaining.is_zero() => Some(remaining),
+            _ => {
+                info!("circuit breaker cooldown elapsed, resuming submission");
+                *state = CircuitBreakerState::default();
+                None
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.state
+            .lock()
+            .expect("mutex is poisoned")
+            .consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().expect("mutex is poisoned");
+
+        state.consecutive_failures += 1;
+
+        if state.tripped_at.is_none() && state.consecutive_failures >= self.threshold {
+            error!(
+                consecutive_failures = state.consecutive_failures,
+                cooldown = self.cooldown.as_secs(),
+                "circuit breaker tripped, pausing transaction submission"
+            );
+
+            state.tripped_at = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_after_threshold_and_resets_after_cooldown() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            threshold: 2,
+            cooldown: 0,
+        });
+
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.state.lock().unwrap().tripped_at, None);
+
+        breaker.record_failure();
+        assert!(breaker.state.lock().unwrap().tripped_at.is_some());
+
+        // the cooldown is zero, so the breaker is reset as soon as it is checked
+        assert_eq!(breaker.check(), None);
+        assert_eq!(breaker.state.lock().unwrap().consecutive_failures, 0);
+
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            threshold: 1,
+            cooldown: 60,
+        });
+
+        breaker.record_failure();
+        assert!(breaker.check().is_some());
+    }
+}
@@ -1,11 +1,11 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, net::SocketAddr, sync::Arc};
 
 use alloy::{
     contract::{Error, RawCallBuilder},
     network::EthereumWallet,
     providers::{PendingTransactionError, Provider, ProviderBuilder, RootProvider},
     signers::local::LocalSigner,
-    sol_types::{SolEvent, SolInterface},
+    sol_types::{decode_revert_reason, SolEvent, SolInterface},
     transports::{BoxTransport, Transport, TransportError},
 };
 use bip32::secp256k1::ecdsa::{self, SigningKey};
@@ -31,19 +31,22 @@ use voyager_message::{
     data::Data,
     hook::SubmitTxHook,
     module::{PluginInfo, PluginServer},
-    DefaultCmd, Plugin, PluginMessage, VoyagerMessage,
+    DefaultCmd, Plugin, PluginMessage, VoyagerMessage, FATAL_JSONRPC_ERROR_CODE,
 };
 use voyager_vm::{call, defer, now, pass::PassResult, seq, Op, Visit};
 
 use crate::{
     call::ModuleCall,
     callback::ModuleCallback,
+    circuit_breaker::{CircuitBreaker, CircuitBreakerConfig},
     multicall::{Call3, Multicall, MulticallResult},
 };
 
 pub mod call;
 pub mod callback;
+pub mod circuit_breaker;
 pub mod data;
+pub mod metrics;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
@@ -63,7 +66,12 @@ pub struct Module {
     pub keyring: ConcurrentKeyring<alloy::primitives::Address, LocalSigner<SigningKey>>,
 
     pub max_gas_price: Option<u128>,
+    pub max_fee_per_gas: Option<u128>,
+    pub max_priority_fee_per_gas: Option<u128>,
+    pub gas_multiplier: f64,
     pub legacy: bool,
+
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,13 +86,81 @@ pub struct Config {
     /// The RPC endpoint for the execution chain.
     pub rpc_url: String,
 
+    /// The keys to sign transactions with. Each submission uses the next available key in the
+    /// keyring, so configuring multiple keys allows for multiple transactions to be in flight at
+    /// once without contending on a single account's nonce.
     pub keyring: KeyringConfig,
 
     #[serde(default)]
     pub max_gas_price: Option<u128>,
 
+    /// The EIP-1559 max fee per gas to use for submitted transactions. If only one of this and
+    /// `max_priority_fee_per_gas` is set, the other will be estimated by the provider. If neither
+    /// are set, both will be estimated by the provider.
+    #[serde(default)]
+    pub max_fee_per_gas: Option<u128>,
+
+    /// The EIP-1559 max priority fee per gas to use for submitted transactions. See
+    /// `max_fee_per_gas` for how this interacts with fee estimation.
+    #[serde(default)]
+    pub max_priority_fee_per_gas: Option<u128>,
+
+    /// The multiplier to apply to the estimated gas limit of submitted transactions. Must be finite
+    /// and positive.
+    #[serde(default = "default_gas_multiplier")]
+    pub gas_multiplier: f64,
+
     #[serde(default)]
     pub legacy: bool,
+
+    /// If set, serve the metrics of this plugin (such as the number of submitted, reverted, and
+    /// errored messages) on this address.
+    #[serde(default)]
+    pub metrics_laddr: Option<SocketAddr>,
+
+    /// If set, pause submission for `cooldown` seconds after `threshold` consecutive
+    /// non-recoverable failed submissions (see [`TxSubmitError::is_recoverable`]). This bounds the
+    /// amount of gas that can be burned if every transaction is failing, for example due to a bug
+    /// in proof generation.
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+#[must_use]
+#[inline]
+pub const fn default_gas_multiplier() -> f64 {
+    1.5
+}
+
+impl Config {
+    /// Check that the fee configuration can produce a usable transaction. A gas multiplier that is
+    /// not finite and positive would result in a gas limit of 0 (or a saturated one) when applied
+    /// to the estimate.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.gas_multiplier.is_finite() && self.gas_multiplier > 0.0) {
+            return Err(format!(
+                "gas multiplier must be finite and positive, found {}",
+                self.gas_multiplier
+            ));
+        }
+
+        if self.max_fee_per_gas == Some(0) {
+            return Err("max fee per gas must be positive".to_owned());
+        }
+
+        if let (Some(max_fee_per_gas), Some(max_priority_fee_per_gas)) =
+            (self.max_fee_per_gas, self.max_priority_fee_per_gas)
+        {
+            if max_priority_fee_per_gas > max_fee_per_gas {
+                return Err(format!(
+                    "max priority fee per gas ({max_priority_fee_per_gas}) must not be greater \
+                    than max fee per gas ({max_fee_per_gas})"
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Plugin for Module {
@@ -95,6 +171,8 @@ impl Plugin for Module {
     type Cmd = DefaultCmd;
 
     async fn new(config: Self::Config) -> Result<Self, BoxDynError> {
+        config.validate()?;
+
         let provider = ProviderBuilder::new().on_builtin(&config.rpc_url).await?;
 
         let raw_chain_id = provider.get_chain_id().await?;
@@ -108,6 +186,10 @@ impl Plugin for Module {
             .into());
         }
 
+        if let Some(metrics_laddr) = config.metrics_laddr {
+            voyager_message::metrics::serve(metrics_laddr)?;
+        }
+
         Ok(Self {
             chain_id,
             ibc_handler_address: config.ibc_handler_address,
@@ -131,7 +213,13 @@ impl Plugin for Module {
                 }),
             ),
             max_gas_price: config.max_gas_price,
+            max_fee_per_gas: config.max_fee_per_gas,
+            max_priority_fee_per_gas: config.max_priority_fee_per_gas,
+            gas_multiplier: config.gas_multiplier,
             legacy: config.legacy,
+            circuit_breaker: config
+                .circuit_breaker
+                .map(|config| Arc::new(CircuitBreaker::new(config))),
         })
     }
 
@@ -169,14 +257,107 @@ pub enum TxSubmitError {
     PendingTransactionError(#[from] PendingTransactionError),
     #[error("out of gas")]
     OutOfGas,
-    #[error("0x revert")]
-    EmptyRevert(Vec<Datagram>),
+    /// Some of the messages in the batch reverted. The rest of the batch was included in the
+    /// transaction.
+    #[error(
+        "{} message(s) reverted and can be retried, {} message(s) reverted: {}",
+        .retry.len(),
+        .reverts.len(),
+        display_reverts(.reverts)
+    )]
+    Revert {
+        /// Messages that reverted with an empty (0x) revert or a revert that may not occur at a
+        /// later block, and can be retried.
+        retry: Vec<Datagram>,
+        /// Messages that reverted deterministically, and will revert again on every retry.
+        reverts: Vec<MessageRevert>,
+    },
+    #[error("client type `{client_type}` is not registered on the IBC handler")]
+    ClientTypeNotRegistered { client_type: String },
+    #[error("error querying the client registry")]
+    ClientRegistry(#[source] Error),
     #[error("gas price is too high: max {max}, price {price}")]
     GasPriceTooHigh { max: u128, price: u128 },
     #[error("rpc error (this is just the IbcDatagram conversion functions but i need to make those errors better)")]
     RpcError(#[from] ErrorObjectOwned),
 }
 
+impl TxSubmitError {
+    /// Whether retrying the submission could possibly succeed.
+    ///
+    /// Deterministic failures (reverts during execution or gas estimation, ABI encoding/decoding
+    /// failures, invalid datagrams) will fail again on every retry, and as such are not
+    /// recoverable. Everything else (connection errors, nonce too low, replacement transaction
+    /// underpriced, ...) is assumed to be transient.
+    #[must_use]
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            TxSubmitError::Error(error)
+            | TxSubmitError::Estimate(error)
+            | TxSubmitError::ClientRegistry(error) => is_recoverable_contract_error(error),
+            TxSubmitError::PendingTransactionError(_)
+            | TxSubmitError::OutOfGas
+            | TxSubmitError::GasPriceTooHigh { .. } => true,
+            TxSubmitError::Revert { reverts, .. } => reverts.is_empty(),
+            TxSubmitError::ClientTypeNotRegistered { .. } | TxSubmitError::RpcError(_) => false,
+        }
+    }
+}
+
+/// A message in a multicall batch that reverted.
+#[derive(Debug, Clone)]
+pub struct MessageRevert {
+    pub msg: Datagram,
+    pub msg_name: &'static str,
+    /// The decoded revert reason, or the hex-encoded revert data if it could not be decoded.
+    pub reason: String,
+}
+
+fn display_reverts(reverts: &[MessageRevert]) -> String {
+    reverts
+        .iter()
+        .map(|r| format!("{} ({})", r.msg_name, r.reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// How a well-known revert of a single message in a multicall batch is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RevertKind {
+    /// The message has already been handled (i.e. by another relayer), and can be dropped.
+    Redundant,
+    /// The message may succeed at a later block, and should be retried.
+    Transient,
+    /// The message will revert again on every retry.
+    Deterministic,
+}
+
+fn classify_revert(revert: &IbcErrors) -> RevertKind {
+    match revert {
+        // the acknowledgement was already relayed, or the packet was already acknowledged or
+        // timed out
+        IbcErrors::ErrAcknowledgementAlreadyExists(_)
+        | IbcErrors::ErrPacketCommitmentNotFound(_) => RevertKind::Redundant,
+        // the client on this chain has not been updated far enough yet
+        IbcErrors::ErrTimeoutHeightNotReached(_)
+        | IbcErrors::ErrTimeoutTimestampNotReached(_)
+        | IbcErrors::ErrLatestTimestampNotFound(_)
+        | IbcErrors::ErrTrustedConsensusStateNotFound(_) => RevertKind::Transient,
+        _ => RevertKind::Deterministic,
+    }
+}
+
+fn is_recoverable_contract_error(error: &Error) -> bool {
+    match error {
+        Error::TransportError(TransportError::ErrorResp(e))
+        | Error::PendingTransactionError(PendingTransactionError::TransportError(
+            TransportError::ErrorResp(e),
+        )) => !(e.as_revert_data().is_some() || e.message.contains("execution reverted")),
+        Error::AbiError(_) | Error::ContractNotDeployed => false,
+        _ => true,
+    }
+}
+
 #[async_trait]
 impl PluginServer<ModuleCall, ModuleCallback> for Module {
     async fn run_pass(
@@ -218,6 +399,25 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
     async fn call(&self, _: &Extensions, msg: ModuleCall) -> RpcResult<Op<VoyagerMessage>> {
         match msg {
             ModuleCall::SubmitMulticall(msgs) => {
+                if let Some(remaining) = self
+                    .circuit_breaker
+                    .as_ref()
+                    .and_then(|circuit_breaker| circuit_breaker.check())
+                {
+                    warn!(
+                        remaining = remaining.as_secs(),
+                        "circuit breaker is tripped, not submitting"
+                    );
+
+                    return Ok(seq([
+                        defer(now() + remaining.as_secs().max(1)),
+                        call(PluginMessage::new(
+                            self.plugin_name(),
+                            ModuleCall::SubmitMulticall(msgs),
+                        )),
+                    ]));
+                }
+
                 let res = self
                     .keyring
                     .with({
@@ -229,6 +429,16 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                     })
                     .await;
 
+                if let Some(circuit_breaker) = &self.circuit_breaker {
+                    match &res {
+                        Some(Ok(())) => circuit_breaker.record_success(),
+                        // only deterministic failures count towards tripping the breaker, transient
+                        // failures (connection errors, gas price spikes, ...) are retried as usual
+                        Some(Err(err)) if !err.is_recoverable() => circuit_breaker.record_failure(),
+                        Some(Err(_)) | None => {}
+                    }
+                }
+
                 let rewrap_msg =
                     || PluginMessage::new(self.plugin_name(), ModuleCall::SubmitMulticall(msgs));
 
@@ -240,19 +450,43 @@ impl PluginServer<ModuleCall, ModuleCallback> for Module {
                     Some(Err(TxSubmitError::OutOfGas)) => {
                         Ok(seq([defer(now() + 12), call(rewrap_msg())]))
                     }
-                    Some(Err(TxSubmitError::EmptyRevert(msgs))) => Ok(seq([
-                        defer(now() + 12),
-                        call(PluginMessage::new(
-                            self.plugin_name(),
-                            ModuleCall::SubmitMulticall(msgs),
-                        )),
-                    ])),
+                    // the rest of the batch was included in the transaction, only retry the
+                    // messages that can succeed at a later block
+                    Some(Err(TxSubmitError::Revert { retry, reverts })) if !retry.is_empty() => {
+                        if !reverts.is_empty() {
+                            error!(
+                                reverts = %display_reverts(&reverts),
+                                "messages reverted and will not be retried"
+                            );
+                        }
+
+                        Ok(seq([
+                            defer(now() + 12),
+                            call(PluginMessage::new(
+                                self.plugin_name(),
+                                ModuleCall::SubmitMulticall(retry),
+                            )),
+                        ]))
+                    }
+                    Some(Err(err)) if !err.is_recoverable() => {
+                        error!(
+                            error = %ErrorReporter(&err),
+                            "unrecoverable error submitting transaction"
+                        );
+
+                        Err(ErrorObject::owned(
+                            FATAL_JSONRPC_ERROR_CODE,
+                            ErrorReporter(err).to_string(),
+                            None::<()>,
+                        ))
+                    }
                     Some(Err(err)) => Err(ErrorObject::owned(
                         -1,
                         ErrorReporter(err).to_string(),
                         None::<()>,
                     )),
-                    None => Ok(call(rewrap_msg())),
+                    // all keys are currently in use, try again once one has (likely) been freed up
+                    None => Ok(seq([defer(now() + 1), call(rewrap_msg())])),
                 }
             }
         }
@@ -303,6 +537,26 @@ impl Module {
 
         let ibc = Ibc::new(self.ibc_handler_address.into(), &self.provider);
 
+        // createClient will revert if the client type has not been registered, check the
+        // registry directly instead of trying to interpret the revert
+        for client_type in ibc_messages.iter().filter_map(|msg| match msg {
+            Datagram::CreateClient(data) => Some(data.client_type.to_string()),
+            _ => None,
+        }) {
+            let client_impl = ibc
+                .clientRegistry(client_type.clone())
+                .call()
+                .await
+                .map_err(TxSubmitError::ClientRegistry)?
+                ._0;
+
+            if client_impl.is_zero() {
+                return Err(TxSubmitError::ClientTypeNotRegistered { client_type });
+            }
+
+            debug!(%client_type, %client_impl, "client type is registered");
+        }
+
         let msgs = process_msgs(&ibc, ibc_messages, wallet.address().0.into())?;
 
         debug!(?msgs);
@@ -325,7 +579,14 @@ impl Module {
 
         info!("submitting evm tx");
 
-        let gas_estimate = call.estimate_gas().await.map_err(TxSubmitError::Estimate)?;
+        let gas_estimate = call.estimate_gas().await.map_err(|e| {
+            metrics::record_msgs(
+                &self.chain_id,
+                msg_names.iter().map(|x| x.1),
+                metrics::RESULT_ERRORED,
+            );
+            TxSubmitError::Estimate(e)
+        })?;
         //     .map_err(|e| {
         //     ErrorObject::owned(
         //         -1,
@@ -333,11 +594,41 @@ impl Module {
         //     )
         // })?;
 
-        let gas_to_use = gas_estimate + (gas_estimate / 2);
+        let gas_to_use = (gas_estimate as f64 * self.gas_multiplier) as u64;
 
         info!(gas_estimate, gas_to_use, "gas estimatation successful");
 
-        match call.gas(gas_to_use).send().await {
+        let call = call.gas(gas_to_use);
+
+        let call = match (
+            self.legacy,
+            self.max_fee_per_gas,
+            self.max_priority_fee_per_gas,
+        ) {
+            // fees will be filled by the provider's gas filler
+            (true, _, _) | (false, None, None) => call,
+            (false, max_fee_per_gas, max_priority_fee_per_gas) => {
+                let estimate = self
+                    .provider
+                    .estimate_eip1559_fees(None)
+                    .await
+                    .map_err(|e| TxSubmitError::Estimate(Error::TransportError(e)))?;
+
+                let max_fee_per_gas = max_fee_per_gas.unwrap_or(estimate.max_fee_per_gas);
+                let max_priority_fee_per_gas =
+                    max_priority_fee_per_gas.unwrap_or(estimate.max_priority_fee_per_gas);
+
+                info!(
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas, "using configured eip-1559 fees"
+                );
+
+                call.max_fee_per_gas(max_fee_per_gas)
+                    .max_priority_fee_per_gas(max_priority_fee_per_gas)
+            }
+        };
+
+        match call.send().await {
             Ok(ok) => {
                 let tx_hash = <H256>::from(*ok.tx_hash());
                 async move {
@@ -363,10 +654,30 @@ impl Module {
                     );
 
                     let mut retry_msgs = vec![];
+                    let mut reverts = vec![];
 
                     for (idx, (result, (msg, msg_name))) in
                         result._0.into_iter().zip(msg_names).enumerate()
                     {
+                        let known_revert = (!result.success)
+                            .then(|| IbcErrors::abi_decode(&result.returnData, true).ok())
+                            .flatten();
+
+                        metrics::record_msgs(
+                            &self.chain_id,
+                            [msg_name],
+                            if result.success {
+                                metrics::RESULT_SUBMITTED
+                            } else if known_revert
+                                .as_ref()
+                                .is_some_and(|r| classify_revert(r) == RevertKind::Redundant)
+                            {
+                                metrics::RESULT_REDUNDANT
+                            } else {
+                                metrics::RESULT_REVERTED
+                            },
+                        );
+
                         if result.success {
                             info_span!(
                                 "evm tx",
@@ -374,17 +685,45 @@ impl Module {
                                 %idx,
                                 data = %serde_json::to_string(&msg).unwrap(),
                             );
-                        } else if let Ok(known_revert) =
-                            IbcErrors::abi_decode(&result.returnData, true)
-                        {
-                            error!(
-                                msg = %msg_name,
-                                %idx,
-                                revert = ?known_revert,
-                                well_known = true,
-                                data = %serde_json::to_string(&msg).unwrap(),
-                                "evm message failed",
-                            );
+                        } else if let Some(known_revert) = known_revert {
+                            match classify_revert(&known_revert) {
+                                RevertKind::Redundant => {
+                                    info!(
+                                        msg = %msg_name,
+                                        %idx,
+                                        revert = ?known_revert,
+                                        data = %serde_json::to_string(&msg).unwrap(),
+                                        "evm message was already handled, dropping",
+                                    );
+                                }
+                                RevertKind::Transient => {
+                                    warn!(
+                                        msg = %msg_name,
+                                        %idx,
+                                        revert = ?known_revert,
+                                        data = %serde_json::to_string(&msg).unwrap(),
+                                        "evm message failed, it will be retried",
+                                    );
+
+                                    retry_msgs.push(msg);
+                                }
+                                RevertKind::Deterministic => {
+                                    error!(
+                                        msg = %msg_name,
+                                        %idx,
+                                        revert = ?known_revert,
+                                        well_known = true,
+                                        data = %serde_json::to_string(&msg).unwrap(),
+                                        "evm message failed",
+                                    );
+
+                                    reverts.push(MessageRevert {
+                                        msg,
+                                        msg_name,
+                                        reason: format!("{known_revert:?}"),
+                                    });
+                                }
+                            }
                         } else if result.returnData.is_empty() {
                             error!(
                                 msg = %msg_name,
@@ -395,7 +734,7 @@ impl Module {
                                 "evm message failed",
                             );
 
-                            retry_msgs.push((true, msg));
+                            retry_msgs.push(msg);
                         } else {
                             error!(
                                 msg = %msg_name,
@@ -406,20 +745,22 @@ impl Module {
                                 "evm message failed",
                             );
 
-                            retry_msgs.push((false, msg));
+                            reverts.push(MessageRevert {
+                                reason: decode_revert_reason(&result.returnData)
+                                    .unwrap_or_else(|| result.returnData.to_string()),
+                                msg,
+                                msg_name,
+                            });
                         }
                     }
 
-                    // NOTE: An empty iterator returns false
-                    if retry_msgs
-                        .iter()
-                        .any(|(is_empty_revert, _)| *is_empty_revert)
-                    {
-                        Err(TxSubmitError::EmptyRevert(
-                            retry_msgs.into_iter().map(|(_, msg)| msg).collect(),
-                        ))
-                    } else {
+                    if retry_msgs.is_empty() && reverts.is_empty() {
                         Ok(())
+                    } else {
+                        Err(TxSubmitError::Revert {
+                            retry: retry_msgs,
+                            reverts,
+                        })
                     }
                 }
                 .instrument(info_span!(
@@ -438,9 +779,21 @@ impl Module {
                 .contains("insufficient funds for gas * price + value") =>
             {
                 error!("out of gas");
+                metrics::record_msgs(
+                    &self.chain_id,
+                    msg_names.iter().map(|x| x.1),
+                    metrics::RESULT_ERRORED,
+                );
                 Err(TxSubmitError::OutOfGas)
             }
-            Err(err) => Err(TxSubmitError::Error(err)),
+            Err(err) => {
+                metrics::record_msgs(
+                    &self.chain_id,
+                    msg_names.iter().map(|x| x.1),
+                    metrics::RESULT_ERRORED,
+                );
+                Err(TxSubmitError::Error(err))
+            }
         }
     }
 }
@@ -601,17 +954,17 @@ fn process_msgs<T: Transport + Clone, P: Provider<T>>(
                         })
                         .clear_decoder(),
                 ),
-                // Datagram::TimeoutPacket(data) => (
-                //     msg,
-                //     ibc_handler
-                //         .timeoutPacket(MsgPacketTimeout {
-                //             packet: convert_packet(data.packet)?,
-                //             proof: data.proof_unreceived.into(),
-                //             proofHeight: data.proof_height.height(),
-                //             relayer: relayer.into(),
-                //         })
-                //         .clear_decoder(),
-                // ),
+                Datagram::PacketTimeout(data) => (
+                    msg,
+                    ibc_handler
+                        .timeoutPacket(ibc_solidity::MsgPacketTimeout {
+                            packet: data.packet,
+                            proof: data.proof.into(),
+                            proof_height: data.proof_height,
+                            relayer: relayer.into(),
+                        })
+                        .clear_decoder(),
+                ),
                 _ => todo!(),
             })
         })
@@ -674,6 +1027,69 @@ mod tests {
         dbg!(result);
     }
 
+    #[test]
+    fn fee_config_validation() {
+        let config = serde_json::from_value::<Config>(serde_json::json!({
+            "chain_id": "1",
+            "ibc_handler_address": "0x0000000000000000000000000000000000000000",
+            "multicall_address": "0x0000000000000000000000000000000000000000",
+            "rpc_url": "http://localhost:8545",
+            "keyring": {
+                "name": "test",
+                "keys": []
+            },
+            "max_fee_per_gas": 100,
+            "max_priority_fee_per_gas": 10
+        }))
+        .unwrap();
+
+        assert!(config.validate().is_ok());
+
+        for gas_multiplier in [f64::NAN, f64::INFINITY, -1.0, 0.0] {
+            assert!(Config {
+                gas_multiplier,
+                ..config.clone()
+            }
+            .validate()
+            .is_err());
+        }
+
+        assert!(Config {
+            max_fee_per_gas: Some(0),
+            max_priority_fee_per_gas: None,
+            ..config.clone()
+        }
+        .validate()
+        .is_err());
+
+        assert!(Config {
+            max_priority_fee_per_gas: Some(101),
+            ..config.clone()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn revert_classification() {
+        assert_eq!(
+            classify_revert(&IbcErrors::ErrAcknowledgementAlreadyExists(
+                Ibc::ErrAcknowledgementAlreadyExists {}
+            )),
+            RevertKind::Redundant
+        );
+        assert_eq!(
+            classify_revert(&IbcErrors::ErrTimeoutTimestampNotReached(
+                Ibc::ErrTimeoutTimestampNotReached {}
+            )),
+            RevertKind::Transient
+        );
+        assert_eq!(
+            classify_revert(&IbcErrors::ErrInvalidProof(Ibc::ErrInvalidProof {})),
+            RevertKind::Deterministic
+        );
+    }
+
     // TODO: rename of the event broke the test indeed
     // #[test]
     // fn create_client_decode() {
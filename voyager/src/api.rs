@@ -2,25 +2,44 @@ use std::net::SocketAddr;
 
 use axum::{
     extract::State,
+    http::HeaderMap,
+    response::IntoResponse,
     routing::{get, post},
     Json,
 };
 use futures::{
-    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
+    channel::mpsc::{channel, Receiver, Sender},
+    future::join_all,
     SinkExt,
 };
+use jsonrpsee::core::RpcResult;
 use prometheus::TextEncoder;
 use reqwest::StatusCode;
+use serde::Serialize;
 use tracing::error;
-use voyager_message::VoyagerMessage;
+use unionlabs::ibc::core::client::height::Height;
+use voyager_message::{core::ChainId, rpc::server::Server, VoyagerMessage};
 use voyager_vm::Op;
 
-pub fn run(laddr: &SocketAddr) -> UnboundedReceiver<Op<VoyagerMessage>> {
-    let (queue_tx, queue_rx) = unbounded::<Op<VoyagerMessage>>();
+/// The header that can be used to specify an idempotency key for an enqueued op. Ops sent with a
+/// key that has already been used will not be enqueued again.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+pub type EnqueueRequest = (Option<String>, Op<VoyagerMessage>);
+
+/// Serve the rest api on `laddr`. Ops received on `/enqueue` are sent to the returned receiver,
+/// buffering at most `enqueue_buffer_size` of them.
+pub fn run(
+    laddr: &SocketAddr,
+    rpc_server: Server,
+    enqueue_buffer_size: usize,
+) -> Receiver<EnqueueRequest> {
+    let (queue_tx, queue_rx) = channel::<EnqueueRequest>(enqueue_buffer_size);
 
     let app = axum::Router::new()
         .route("/enqueue", post(enqueue))
         .route("/health", get(|| async move { StatusCode::OK }))
+        .route("/ready", get(move || ready(rpc_server.clone())))
         .route("/metrics", get(metrics))
         // .route(
         //     "/signer/balances",
@@ -38,14 +57,88 @@ pub fn run(laddr: &SocketAddr) -> UnboundedReceiver<Op<VoyagerMessage>> {
 
 // #[axum::debug_handler]
 async fn enqueue(
-    State(mut sender): State<UnboundedSender<Op<VoyagerMessage>>>,
+    State(mut sender): State<Sender<EnqueueRequest>>,
+    headers: HeaderMap,
     Json(op): Json<Op<VoyagerMessage>>,
 ) -> StatusCode {
-    sender.send(op).await.expect("receiver should not close");
+    let idempotency_key = match headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|key| key.to_str())
+        .transpose()
+    {
+        Ok(key) => key.map(ToOwned::to_owned),
+        Err(_) => return StatusCode::BAD_REQUEST,
+    };
+
+    // waits for space in the buffer if it is full
+    sender
+        .send((idempotency_key, op))
+        .await
+        .expect("receiver should not close");
 
     StatusCode::OK
 }
 
+/// The health of a chain, as reported by [`chains_health`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainHealth {
+    pub chain_id: ChainId,
+    /// The latest height of the chain, if it is reachable.
+    pub latest_height: Option<Height>,
+    /// The error returned when querying the chain, if it is not reachable.
+    pub error: Option<String>,
+}
+
+impl ChainHealth {
+    #[must_use]
+    pub fn is_up(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Check that every chain with a loaded consensus module is reachable, by querying its latest
+/// height.
+pub async fn chains_health(rpc_server: &Server) -> RpcResult<Vec<ChainHealth>> {
+    let chain_ids = rpc_server
+        .modules()?
+        .info()
+        .consensus
+        .into_iter()
+        .map(|info| info.chain_id);
+
+    Ok(join_all(chain_ids.map(|chain_id| async move {
+        match rpc_server.query_latest_height(&chain_id, false).await {
+            Ok(latest_height) => ChainHealth {
+                chain_id,
+                latest_height: Some(latest_height),
+                error: None,
+            },
+            Err(err) => ChainHealth {
+                chain_id,
+                latest_height: None,
+                error: Some(err.message().to_owned()),
+            },
+        }
+    }))
+    .await)
+}
+
+/// Ready once all chains are reachable. The per-chain report is returned in either case.
+async fn ready(rpc_server: Server) -> impl IntoResponse {
+    match chains_health(&rpc_server).await {
+        Ok(health) => (
+            if health.iter().all(ChainHealth::is_up) {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            },
+            Json(health),
+        )
+            .into_response(),
+        Err(err) => (StatusCode::SERVICE_UNAVAILABLE, err.message().to_owned()).into_response(),
+    }
+}
+
 async fn metrics() -> Result<String, StatusCode> {
     TextEncoder::new()
         .encode_to_string(&prometheus::gather())
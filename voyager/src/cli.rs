@@ -1,7 +1,10 @@
 use std::{ffi::OsString, str::FromStr};
 
 use clap::{self, Parser, Subcommand};
-use unionlabs::{self, bounded::BoundedI64, ibc::core::client::height::Height, result_unwrap};
+use unionlabs::{
+    self, bounded::BoundedI64, ibc::core::client::height::Height, id::ClientId, primitives::Bytes,
+    result_unwrap,
+};
 use voyager_message::{
     core::{ChainId, ClientType, IbcInterface, IbcSpecId, QueryHeight},
     module::{ClientModuleInfo, ConsensusModuleInfo, ProofModuleInfo, StateModuleInfo},
@@ -72,6 +75,9 @@ pub enum Command {
     /// Run Voyager.
     Start,
     /// Query and interact with the queue.
+    ///
+    /// These commands operate on the queue database directly, and as such require the `pg-queue`
+    /// queue backend. They are not supported with the in-memory queue.
     #[command(subcommand, alias = "q")]
     Queue(QueueCmd),
     #[command(subcommand)]
@@ -92,6 +98,8 @@ pub enum Command {
         #[command(subcommand)]
         cmd: RpcCmd,
     },
+    /// Construct ops to create and update clients. This can be used to manually bump a client
+    /// that is close to expiry, without waiting for a packet to trigger an update.
     #[command(subcommand)]
     Msg(MsgCmd),
 }
@@ -148,12 +156,32 @@ pub enum QueueCmd {
         #[arg(long = "message-filter", short = 'm')]
         message_filters: Vec<String>,
     },
+    /// Query all in-progress aggregations, along with the data they have collected so far.
+    ///
+    /// This requires the `pg-queue` queue backend, the in-memory queue can't be queried.
+    QueryAggregations {
+        #[arg(long, default_value_t = result_unwrap!(Pg64::new_const(1)))]
+        page: Pg64,
+        #[arg(long, default_value_t = result_unwrap!(Pg64::new_const(10)))]
+        per_page: Pg64,
+    },
     /// Query a failed message by it's ID.
     QueryFailedById {
         id: Pg64,
         #[arg(long, short = 'e')]
         requeue: bool,
     },
+    /// Remove all pending items that contain the provided JSON value anywhere within them.
+    ///
+    /// Matching follows the semantics of the postgres `@>` operator, but is checked against every
+    /// nested value of the item. For example, `'{"sequence":5,"source_channel":"channel-1"}'`
+    /// will remove all items containing a packet with that source channel and sequence.
+    Remove {
+        #[arg(value_parser(serde_json::Value::from_str))]
+        matching: serde_json::Value,
+    },
+    /// Remove duplicate in-progress aggregations, keeping the oldest of identical aggregations.
+    CompactAggregations,
 }
 
 #[derive(Debug, Subcommand)]
@@ -227,6 +255,8 @@ pub enum RpcCmd {
 
 #[derive(Debug, Subcommand)]
 pub enum MsgCmd {
+    /// Construct an op to create a client on `on` tracking `tracking`, at the given height of
+    /// `tracking`.
     CreateClient {
         #[arg(long, value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
         on: ChainId,
@@ -252,6 +282,8 @@ pub enum MsgCmd {
         #[arg(long, short = 'e', default_value_t = false)]
         enqueue: bool,
     },
+    /// Construct an op to update a client to the latest height of the chain it is tracking, or
+    /// to a specific height.
     UpdateClient {
         #[arg(long, value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
         on: ChainId,
@@ -263,6 +295,24 @@ pub enum MsgCmd {
         #[arg(long)]
         update_to: Option<Height>,
 
+        /// Automatically enqueue the op.
+        #[arg(long, short = 'e', default_value_t = false)]
+        enqueue: bool,
+    },
+    /// Construct an op to submit misbehaviour (i.e. evidence of equivocation on the tracked chain)
+    /// for an IBC classic client on `on`, freezing the client.
+    ///
+    /// Misbehaviour is not submitted automatically from client misbehaviour events, this is
+    /// currently the only way to submit it.
+    SubmitMisbehaviour {
+        #[arg(long, value_parser(|s: &str| ok(ChainId::new(s.to_owned()))))]
+        on: ChainId,
+        #[arg(long)]
+        client_id: ClientId,
+        /// The misbehaviour, encoded as a hex-encoded protobuf `Any`.
+        #[arg(long)]
+        misbehaviour: Bytes,
+
         /// Automatically enqueue the op.
         #[arg(long, short = 'e', default_value_t = false)]
         enqueue: bool,
@@ -29,6 +29,25 @@ pub struct VoyagerConfig {
     // TODO: Specify per plugin
     #[serde(default = "default_optimizer_delay_milliseconds")]
     pub optimizer_delay_milliseconds: u64,
+    /// If set, transactions are built but never submitted. Instead of being routed to a
+    /// transaction plugin, the datagrams of every [`SubmitTx`] are logged.
+    ///
+    /// [`SubmitTx`]: voyager_message::call::SubmitTx
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The maximum nesting depth of an op that will be processed. Ops nested deeper than this are
+    /// dropped, protecting against pathological message graphs.
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// The number of self client and consensus states to cache, each. These are immutable for a
+    /// given height, so caching them avoids redundant queries when creating many clients at once.
+    #[serde(default = "default_self_state_cache_size")]
+    pub self_state_cache_size: usize,
+    /// The maximum number of ops received on the rest api that can be buffered before being
+    /// enqueued. Once the buffer is full, requests to `/enqueue` wait until there is space,
+    /// applying backpressure to the senders instead of buffering without bound.
+    #[serde(default = "default_enqueue_buffer_size")]
+    pub enqueue_buffer_size: usize,
 }
 
 #[must_use]
@@ -48,3 +67,21 @@ pub const fn default_rpc_laddr() -> SocketAddr {
 pub const fn default_optimizer_delay_milliseconds() -> u64 {
     100
 }
+
+#[must_use]
+#[inline]
+pub const fn default_max_depth() -> usize {
+    voyager_vm::DEFAULT_MAX_DEPTH
+}
+
+#[must_use]
+#[inline]
+pub const fn default_self_state_cache_size() -> usize {
+    voyager_message::rpc::server::DEFAULT_SELF_STATE_CACHE_SIZE
+}
+
+#[must_use]
+#[inline]
+pub const fn default_enqueue_buffer_size() -> usize {
+    1024
+}
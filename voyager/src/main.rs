@@ -18,17 +18,23 @@ use anyhow::{anyhow, Context as _};
 use clap::Parser;
 use ibc_classic_spec::IbcClassic;
 use ibc_union_spec::IbcUnion;
-use pg_queue::PgQueueConfig;
+use pg_queue::{PayloadFormat, PgQueueConfig};
 use schemars::gen::{SchemaGenerator, SchemaSettings};
 use serde::Serialize;
 use tikv_jemallocator::Jemalloc;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
+use unionlabs::{
+    encoding::{DecodeAs, Proto},
+    google::protobuf::any::RawAny,
+    ibc::core::client::msg_submit_misbehaviour::MsgSubmitMisbehaviour,
+};
 use voyager_message::{
-    call::{FetchBlocks, FetchUpdateHeaders},
+    call::{FetchBlocks, FetchUpdateHeaders, SubmitTx},
     callback::AggregateMsgUpdateClientsFromOrderedHeaders,
     context::{get_plugin_info, Context, IbcSpecHandler, ModulesConfig},
     core::{IbcSpec, QueryHeight},
+    data::IbcDatagram,
     filter::{make_filter, run_filter, JaqInterestFilter},
     rpc::{IbcState, VoyagerRpcClient},
     VoyagerMessage,
@@ -40,7 +46,10 @@ static GLOBAL: Jemalloc = Jemalloc;
 
 use crate::{
     cli::{AppArgs, Command, ConfigCmd, ModuleCmd, MsgCmd, PluginCmd, QueueCmd, RpcCmd},
-    config::{default_rest_laddr, default_rpc_laddr, Config, VoyagerConfig},
+    config::{
+        default_enqueue_buffer_size, default_max_depth, default_rest_laddr, default_rpc_laddr,
+        default_self_state_cache_size, Config, VoyagerConfig,
+    },
     queue::{QueueConfig, Voyager},
     utils::make_msg_create_client,
 };
@@ -156,8 +165,17 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                         min_connections: None,
                         idle_timeout: None,
                         max_lifetime: None,
+                        payload_format: PayloadFormat::default(),
+                        deduplicate: false,
+                        connect_retries: 0,
+                        connect_backoff: None,
+                        compact_aggregations_interval: None,
                     }),
                     optimizer_delay_milliseconds: 100,
+                    dry_run: false,
+                    max_depth: default_max_depth(),
+                    self_state_cache_size: default_self_state_cache_size(),
+                    enqueue_buffer_size: default_enqueue_buffer_size(),
                 },
             }),
             ConfigCmd::Schema => print_json(
@@ -252,7 +270,7 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
             let db = || {
                 Ok(match get_voyager_config()?.voyager.queue {
                     QueueConfig::PgQueue(cfg) => pg_queue::PgQueue::<VoyagerMessage>::new(cfg),
-                    QueueConfig::InMemory => {
+                    QueueConfig::InMemory { .. } => {
                         return Err(anyhow!(
                             "no database set in config, queue commands \
                             require the `pg-queue` database backend"
@@ -294,6 +312,14 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
 
                     print_json(&record);
                 }
+                QueueCmd::QueryAggregations { page, per_page } => {
+                    let record = db()?
+                        .await?
+                        .query_aggregations(page.into(), per_page.into())
+                        .await?;
+
+                    print_json(&record);
+                }
                 QueueCmd::QueryFailedById { id, requeue } => {
                     let q = db()?.await?;
 
@@ -311,6 +337,16 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
 
                     print_json(&record);
                 }
+                QueueCmd::Remove { matching } => {
+                    let removed = db()?.await?.remove_matching(&matching).await?;
+
+                    println!("removed {removed} items");
+                }
+                QueueCmd::CompactAggregations => {
+                    let removed = db()?.await?.compact_aggregations().await?;
+
+                    println!("removed {removed} duplicate aggregations");
+                }
             }
         }
         Command::InitFetch {
@@ -574,6 +610,14 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                     }
                 };
 
+                if update_to <= client_meta.counterparty_height {
+                    return Err(anyhow!(
+                        "client {client_id} on {on} is already at height {}, \
+                        cannot update to {update_to}",
+                        client_meta.counterparty_height
+                    ));
+                }
+
                 let op = promise::<VoyagerMessage>(
                     [call(FetchUpdateHeaders {
                         client_type: client_info.client_type,
@@ -598,6 +642,37 @@ async fn do_main(args: cli::AppArgs) -> anyhow::Result<()> {
                     print_json(&op);
                 }
             }
+            MsgCmd::SubmitMisbehaviour {
+                on,
+                client_id,
+                misbehaviour,
+                enqueue,
+            } => {
+                // the misbehaviour is only decoded once the transaction is built by the
+                // transaction plugin, so make sure it's valid before it's enqueued
+                RawAny::decode_as::<Proto>(&misbehaviour).map_err(|err| {
+                    anyhow!("misbehaviour must be a protobuf encoded `Any`: {err}")
+                })?;
+
+                let op = call::<VoyagerMessage>(SubmitTx {
+                    chain_id: on,
+                    datagrams: vec![IbcDatagram::new::<IbcClassic>(
+                        ibc_classic_spec::Datagram::from(MsgSubmitMisbehaviour {
+                            client_id,
+                            misbehaviour,
+                        }),
+                    )],
+                });
+
+                if enqueue {
+                    let voyager_config = get_voyager_config()?;
+
+                    println!("enqueueing msg");
+                    send_enqueue(&voyager_config.voyager.rest_laddr, op).await?;
+                } else {
+                    print_json(&op);
+                }
+            }
         },
     }
 
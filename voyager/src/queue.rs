@@ -6,10 +6,11 @@ use anyhow::{bail, Context as _};
 use futures::{future::BoxFuture, stream::FuturesUnordered, Future, FutureExt, StreamExt};
 use ibc_classic_spec::IbcClassic;
 use ibc_union_spec::IbcUnion;
+use jsonrpsee::core::RpcResult;
 use pg_queue::{PgQueue, PgQueueConfig};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error, info, info_span, trace, trace_span};
+use tracing::{debug, error, info, info_span, trace, trace_span, warn};
 use tracing_futures::Instrument;
 use unionlabs::ErrorReporter;
 use voyager_message::{
@@ -17,10 +18,16 @@ use voyager_message::{
     pass::PluginOptPass, rpc::VoyagerRpcServer, VoyagerMessage,
 };
 use voyager_vm::{
-    engine::Engine, in_memory::InMemoryQueue, pass::Pass, BoxDynError, Captures, ItemId, Op, Queue,
+    engine::Engine,
+    in_memory::{InMemoryQueue, InMemoryQueueConfig},
+    pass::Pass,
+    BoxDynError, Captures, ItemId, Op, Queue,
 };
 
-use crate::{api, config::Config};
+use crate::{
+    api::{self, ChainHealth},
+    config::Config,
+};
 
 #[derive(Debug)]
 pub struct Voyager {
@@ -31,12 +38,18 @@ pub struct Voyager {
     rpc_laddr: SocketAddr,
     queue: QueueImpl,
     optimizer_delay_milliseconds: u64,
+    max_depth: usize,
+    enqueue_buffer_size: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "kebab-case", tag = "type")]
 pub enum QueueConfig {
-    InMemory,
+    InMemory {
+        /// Skip enqueueing items that are identical to an item that is already pending.
+        #[serde(default)]
+        deduplicate: bool,
+    },
     PgQueue(PgQueueConfig),
 }
 
@@ -60,8 +73,8 @@ impl Queue<VoyagerMessage> for QueueImpl {
     fn new(cfg: Self::Config) -> impl Future<Output = Result<Self, Self::Error>> {
         async move {
             Ok(match cfg {
-                QueueConfig::InMemory => Self::InMemory(
-                    InMemoryQueue::new(())
+                QueueConfig::InMemory { deduplicate } => Self::InMemory(
+                    InMemoryQueue::new(InMemoryQueueConfig { deduplicate })
                         .await
                         .map_err(AnyQueueError::InMemory)?,
                 ),
@@ -95,6 +108,30 @@ impl Queue<VoyagerMessage> for QueueImpl {
         }
     }
 
+    fn enqueue_idempotent<'a>(
+        &'a self,
+        key: &'a str,
+        item: Op<VoyagerMessage>,
+        filter: &'a JaqInterestFilter,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'a {
+        async move {
+            match self {
+                QueueImpl::InMemory(queue) => queue
+                    .enqueue_idempotent(key, item, filter)
+                    .await
+                    .map_err(AnyQueueError::InMemory)?,
+                QueueImpl::PgQueue(queue) => queue
+                    .enqueue_idempotent(key, item, filter)
+                    .await
+                    .map_err(AnyQueueError::PgQueue)?,
+            };
+
+            trace!("queued");
+
+            Ok(())
+        }
+    }
+
     fn process<'a, F, Fut, R>(
         &'a self,
         filter: &'a JaqInterestFilter,
@@ -139,6 +176,22 @@ impl Queue<VoyagerMessage> for QueueImpl {
                 .map_err(|e| e.map_left(AnyQueueError::PgQueue)),
         }
     }
+
+    async fn remove<'a, P>(&'a self, predicate: P) -> Result<u64, Self::Error>
+    where
+        P: Fn(&Op<VoyagerMessage>) -> bool + Send + Sync + 'a,
+    {
+        match self {
+            QueueImpl::InMemory(queue) => queue
+                .remove(predicate)
+                .await
+                .map_err(AnyQueueError::InMemory),
+            QueueImpl::PgQueue(queue) => queue
+                .remove(predicate)
+                .await
+                .map_err(AnyQueueError::PgQueue),
+        }
+    }
 }
 
 impl Voyager {
@@ -147,21 +200,36 @@ impl Voyager {
             .await
             .context("error initializing queue")?;
 
+        let mut context = Context::new(config.plugins, config.modules, |h| {
+            h.register::<IbcClassic>();
+            h.register::<IbcUnion>();
+        })
+        .await
+        .context("error initializing plugins")?;
+
+        context.dry_run = config.voyager.dry_run;
+        context
+            .rpc_server
+            .set_self_state_cache_size(config.voyager.self_state_cache_size);
+
         Ok(Self {
-            context: Context::new(config.plugins, config.modules, |h| {
-                h.register::<IbcClassic>();
-                h.register::<IbcUnion>();
-            })
-            .await
-            .context("error initializing plugins")?,
+            context,
             num_workers: config.voyager.num_workers,
             rest_laddr: config.voyager.rest_laddr,
             rpc_laddr: config.voyager.rpc_laddr,
             queue,
             optimizer_delay_milliseconds: config.voyager.optimizer_delay_milliseconds,
+            max_depth: config.voyager.max_depth,
+            enqueue_buffer_size: config.voyager.enqueue_buffer_size,
         })
     }
 
+    /// Check that every configured chain is reachable. This is also served at `/ready` on the
+    /// rest api, for use in readiness probes.
+    pub async fn health(&self) -> RpcResult<Vec<ChainHealth>> {
+        api::chains_health(&self.context.rpc_server).await
+    }
+
     #[allow(clippy::too_many_lines)]
     pub async fn run(self) -> anyhow::Result<()> {
         let interest_filter = JaqInterestFilter::new(
@@ -174,9 +242,31 @@ impl Voyager {
                     interest_filter,
                 })
                 .collect(),
-        )?;
+        )?
+        .with_dry_run(self.context.dry_run);
+
+        if self.context.dry_run {
+            warn!("running in dry run mode, no transactions will be submitted");
+        }
+
+        match self.health().await {
+            Ok(health) => {
+                for chain in health.into_iter().filter(|chain| !chain.is_up()) {
+                    warn!(
+                        chain_id = %chain.chain_id,
+                        error = chain.error.as_deref(),
+                        "chain is not reachable"
+                    );
+                }
+            }
+            Err(err) => warn!(%err, "unable to check the health of the configured chains"),
+        }
 
-        let queue_rx = api::run(&self.rest_laddr);
+        let queue_rx = api::run(
+            &self.rest_laddr,
+            self.context.rpc_server.clone(),
+            self.enqueue_buffer_size,
+        );
 
         {
             let mut tasks =
@@ -205,10 +295,17 @@ impl Voyager {
 
                     pin_utils::pin_mut!(queue_rx);
 
-                    while let Some(op) = queue_rx.next().await {
+                    while let Some((idempotency_key, op)) = queue_rx.next().await {
                         info!("received new message: {}", into_value(&op));
 
-                        self.queue.enqueue(op, &interest_filter).await?;
+                        match idempotency_key {
+                            Some(key) => {
+                                self.queue
+                                    .enqueue_idempotent(&key, op, &interest_filter)
+                                    .await?
+                            }
+                            None => self.queue.enqueue(op, &interest_filter).await?,
+                        }
                     }
 
                     Ok(())
@@ -224,6 +321,7 @@ impl Voyager {
                 tasks.push(Box::pin(
                     AssertUnwindSafe(
                         Engine::new(&self.context, &self.queue, &interest_filter)
+                            .with_max_depth(self.max_depth)
                             .run()
                             .for_each(|res| async move {
                                 match res {
@@ -248,6 +346,39 @@ impl Voyager {
                 ));
             }
 
+            if let Some((queue, interval)) = match &self.queue {
+                QueueImpl::PgQueue(queue) => queue
+                    .compact_aggregations_interval()
+                    .map(|interval| (queue, interval)),
+                QueueImpl::InMemory(_) => None,
+            } {
+                info!("spawning aggregation compactor");
+
+                tasks.push(Box::pin(
+                    AssertUnwindSafe(
+                        async move {
+                            loop {
+                                tokio::time::sleep(interval).await;
+
+                                match queue.compact_aggregations().await {
+                                    Ok(removed) => {
+                                        debug!(%removed, "compacted aggregations");
+                                    }
+                                    Err(error) => {
+                                        error!(
+                                            error = %ErrorReporter(error),
+                                            "error compacting aggregations"
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                        .instrument(info_span!("compact_aggregations")),
+                    )
+                    .catch_unwind(),
+                ));
+            }
+
             for (plugin_name, filter) in self.context.interest_filters() {
                 info!(%plugin_name, "spawning optimizer");
 